@@ -3,7 +3,11 @@ use std::io::Write;
 use std::path::{Path, PathBuf};
 
 use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
-use csv_managed::cli::{BooleanFormat, ProcessArgs};
+use csv_managed::cli::{
+    BooleanFormat, DialectArgs, DistinctKeep, InputFormatArgs, JsonDecimals, MalformedArgs, OnMiss,
+    OnTypeError, OutputFormat, ParquetArgs, ProcessArgs, SplitArgs,
+};
+use csv_managed::dialect::Dialect;
 use csv_managed::index::{CsvIndex, IndexDefinition};
 use csv_managed::process;
 use encoding_rs::UTF_8;
@@ -33,19 +37,30 @@ fn build_index(csv_path: &Path) -> PathBuf {
         IndexDefinition::parse("recent=ordered_at:desc").expect("parse recent"),
         IndexDefinition::parse("ordered_at:asc,ship_time:asc").expect("parse asc pair"),
     ];
-    let index =
-        CsvIndex::build(csv_path, &definitions, None, None, b',', UTF_8).expect("build index");
+    let index = CsvIndex::build(
+        csv_path,
+        &definitions,
+        None,
+        None,
+        b',',
+        UTF_8,
+        &Dialect::default(),
+    )
+    .expect("build index");
     index.save(&index_path).expect("save index");
     index_path
 }
 
 fn base_process_args(input: &Path, output: &Path) -> ProcessArgs {
     ProcessArgs {
-        input: input.to_path_buf(),
+        inputs: vec![input.to_path_buf()],
+        source_column: None,
         output: Some(output.to_path_buf()),
         schema: None,
+        on_miss: OnMiss::Empty,
         index: None,
         index_variant: None,
+        rebuild_stale_index: false,
         sort: vec!["ordered_at:asc".to_string(), "ship_time:asc".to_string()],
         columns: vec!["ordered_at".to_string(), "status".to_string()],
         exclude_columns: Vec::new(),
@@ -54,15 +69,34 @@ fn base_process_args(input: &Path, output: &Path) -> ProcessArgs {
         filter_exprs: Vec::new(),
         row_numbers: false,
         limit: Some(20000),
+        sample: None,
+        sample_fraction: None,
+        seed: None,
         delimiter: None,
         output_delimiter: None,
         input_encoding: None,
         output_encoding: None,
         boolean_format: BooleanFormat::Original,
+        output_format: OutputFormat::Csv,
+        json_decimals: JsonDecimals::String,
         preview: false,
         table: false,
         apply_mappings: false,
         skip_mappings: false,
+        memory_limit: None,
+        temp_dir: None,
+        threads: None,
+        distinct: false,
+        distinct_on: Vec::new(),
+        keep: DistinctKeep::First,
+        on_type_error: OnTypeError::Fail,
+        type_reject_file: None,
+        windows: Vec::new(),
+        input_format: InputFormatArgs::default(),
+        parquet: ParquetArgs::default(),
+        split: SplitArgs::default(),
+        dialect: DialectArgs::default(),
+        malformed: MalformedArgs::default(),
     }
 }
 
@@ -71,12 +105,15 @@ fn bench_index_vs_sort(c: &mut Criterion) {
     let index_path = build_index(csv_path.as_path());
     let in_memory_output = temp_dir.path().join("in_memory.csv");
     let indexed_output = temp_dir.path().join("indexed.csv");
+    let spilled_output = temp_dir.path().join("spilled.csv");
 
     let in_memory_args = base_process_args(csv_path.as_path(), in_memory_output.as_path());
     let mut indexed_args = base_process_args(csv_path.as_path(), indexed_output.as_path());
     indexed_args.index = Some(index_path.clone());
     indexed_args.index_variant = Some("recent".to_string());
     indexed_args.sort = vec!["ordered_at:desc".to_string()];
+    let mut spilled_args = base_process_args(csv_path.as_path(), spilled_output.as_path());
+    spilled_args.memory_limit = Some(1 << 20);
 
     let mut group = c.benchmark_group("process_sort");
 
@@ -100,6 +137,16 @@ fn bench_index_vs_sort(c: &mut Criterion) {
         );
     });
 
+    group.bench_function("external_sort_1m_budget", |b| {
+        b.iter_batched(
+            || (),
+            |_| {
+                process::execute(&spilled_args).expect("process external sort");
+            },
+            BatchSize::SmallInput,
+        );
+    });
+
     drop(temp_dir);
     group.finish();
 }
@@ -0,0 +1,624 @@
+//! Group-by aggregation for the `aggregate` command.
+//!
+//! Rows are parsed with the schema's datatypes, filtered with the same
+//! `--filter`/`--filter-expr` machinery as `process`, and grouped by the typed
+//! values of the `--group-by` columns. Each group keeps one accumulator per
+//! `--agg` specification:
+//!
+//! | Function | Result |
+//! |----------|--------|
+//! | `count` / `count(col)` | Rows in the group / non-empty values of `col` |
+//! | `sum(col)` | Exact sum; Integer sums use 128-bit integers, Decimal and Currency use `Decimal` |
+//! | `mean(col)` | Sum divided by the non-empty count |
+//! | `min(col)`, `max(col)` | Typed minimum/maximum using the sort ordering |
+//! | `count_distinct(col)` | Number of distinct non-empty typed values |
+//! | `first(col)`, `last(col)` | Value from the first/last row of the group in input order |
+//!
+//! Groups are emitted in ascending order of their typed key.
+//!
+//! # Complexity
+//!
+//! O(n log g) time for n rows and g groups; memory is O(g) plus the distinct
+//! values retained by `count_distinct`.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{Context, Result, anyhow, bail};
+use log::info;
+use rust_decimal::{Decimal, prelude::ToPrimitive};
+
+use crate::{
+    cli::{AggregateArgs, InputFormatArgs},
+    data::{ComparableValue, CurrencyValue, FixedDecimalValue, Value},
+    filter, io_utils,
+    rows::{evaluate_filter_expressions, parse_typed_row},
+    schema::{self, ColumnType, DECIMAL_MAX_PRECISION, DecimalSpec, Schema},
+    table,
+};
+
+/// Extra decimal places kept when dividing for `mean` beyond the column's own scale.
+const MEAN_SCALE: u32 = 4;
+
+pub fn execute(args: &AggregateArgs) -> Result<()> {
+    if args.schema.is_none() && io_utils::is_dash(&args.input) {
+        return Err(anyhow!(
+            "Reading from stdin requires --schema (or --meta) for aggregate operations"
+        ));
+    }
+
+    let delimiter = io_utils::resolve_input_delimiter(&args.input, args.delimiter);
+    let encoding = io_utils::resolve_encoding(args.input_encoding.as_deref())?;
+    let mut schema = match &args.schema {
+        Some(path) => {
+            Schema::load(path).with_context(|| format!("Loading schema from {path:?}"))?
+        }
+        None => {
+            schema::infer_schema_with_input_format(
+                &args.input,
+                0,
+                delimiter,
+                encoding,
+                None,
+                &InputFormatArgs::default(),
+                &args.dialect,
+            )
+            .with_context(|| format!("Inferring schema from {:?}", args.input))?
+            .0
+        }
+    };
+    schema.apply_dialect_args(&args.dialect)?;
+    let dialect = schema.dialect.clone().unwrap_or_default();
+
+    let group_columns = args
+        .group_by
+        .iter()
+        .flat_map(|s| s.split(','))
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(|name| {
+            schema
+                .column_index(name)
+                .ok_or_else(|| anyhow!("Group-by column '{name}' not found in schema"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let specs = args
+        .aggregates
+        .iter()
+        .flat_map(|s| split_specs(s))
+        .map(|spec| AggregateSpec::parse(&spec, &schema))
+        .collect::<Result<Vec<_>>>()?;
+    let filters = filter::parse_filters(&args.filters)?;
+
+    let expects_headers = schema.expects_headers();
+    let mut reader =
+        io_utils::open_csv_reader_from_path(&args.input, delimiter, expects_headers, &dialect)?;
+    let headers = if expects_headers {
+        let headers = io_utils::reader_headers(&mut reader, encoding)?;
+        schema
+            .validate_headers(&headers)
+            .with_context(|| format!("Validating headers for {:?}", args.input))?;
+        headers
+    } else {
+        schema.headers()
+    };
+
+    let mut groups: BTreeMap<Vec<ComparableValue>, Group> = BTreeMap::new();
+    if group_columns.is_empty() {
+        groups.insert(Vec::new(), Group::new(Vec::new(), &specs, &schema));
+    }
+
+    for (row_idx, record) in reader.byte_records().enumerate() {
+        let record = record.with_context(|| format!("Reading row {}", row_idx + 2))?;
+        let mut decoded = io_utils::decode_record(&record, encoding)?;
+        if schema.has_transformations() {
+            schema
+                .apply_transformations_to_row(&mut decoded)
+                .with_context(|| format!("Applying datatype mappings to row {}", row_idx + 2))?;
+        }
+        schema.apply_replacements_to_row(&mut decoded);
+        let typed = parse_typed_row(&schema, &decoded)
+            .with_context(|| format!("Parsing row {}", row_idx + 2))?;
+        if !filters.is_empty()
+            && !filter::evaluate_conditions(&filters, &schema, &headers, &decoded, &typed)?
+        {
+            continue;
+        }
+        if !args.filter_exprs.is_empty()
+            && !evaluate_filter_expressions(
+                &args.filter_exprs,
+                &headers,
+                &decoded,
+                &typed,
+                Some(row_idx + 1),
+            )?
+        {
+            continue;
+        }
+
+        let key = group_columns
+            .iter()
+            .map(|idx| ComparableValue(typed[*idx].clone()))
+            .collect::<Vec<_>>();
+        let group = groups.entry(key).or_insert_with(|| {
+            let key_display = group_columns
+                .iter()
+                .map(|idx| decoded[*idx].clone())
+                .collect();
+            Group::new(key_display, &specs, &schema)
+        });
+        group
+            .ingest(&specs, &decoded, &typed)
+            .with_context(|| format!("Aggregating row {}", row_idx + 2))?;
+    }
+
+    let mut output_headers = group_columns
+        .iter()
+        .map(|idx| schema.columns[*idx].output_name().to_string())
+        .collect::<Vec<_>>();
+    output_headers.extend(specs.iter().map(|spec| spec.name.clone()));
+    let rows = groups
+        .into_values()
+        .map(Group::render)
+        .collect::<Result<Vec<_>>>()?;
+
+    let output_path = args.output.as_deref();
+    if args.table && output_path.is_none_or(io_utils::is_dash) {
+        table::print_table(&output_headers, &rows);
+    } else {
+        let output_delimiter =
+            io_utils::resolve_output_delimiter(output_path, args.output_delimiter, delimiter);
+        let output_encoding = io_utils::resolve_encoding(args.output_encoding.as_deref())?;
+        let mut writer =
+            io_utils::open_csv_writer(output_path, output_delimiter, output_encoding, &dialect)?;
+        writer
+            .write_record(&output_headers)
+            .context("Writing output headers")?;
+        for row in &rows {
+            writer.write_record(row).context("Writing output row")?;
+        }
+        io_utils::finish_csv_writer(writer)?;
+    }
+    info!(
+        "Computed {} aggregate(s) for {} group(s)",
+        specs.len(),
+        rows.len()
+    );
+    Ok(())
+}
+
+/// Splits a comma-separated `--agg` value while keeping commas inside parentheses.
+fn split_specs(value: &str) -> Vec<String> {
+    let mut specs = Vec::new();
+    let mut depth = 0usize;
+    let mut current = String::new();
+    for ch in value.chars() {
+        match ch {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                specs.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(ch);
+    }
+    specs.push(current);
+    specs
+        .into_iter()
+        .map(|spec| spec.trim().to_string())
+        .filter(|spec| !spec.is_empty())
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AggregateFunction {
+    Count,
+    Sum,
+    Min,
+    Max,
+    Mean,
+    CountDistinct,
+    First,
+    Last,
+}
+
+impl AggregateFunction {
+    fn parse(name: &str) -> Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "count" => Ok(Self::Count),
+            "sum" => Ok(Self::Sum),
+            "min" => Ok(Self::Min),
+            "max" => Ok(Self::Max),
+            "mean" | "avg" => Ok(Self::Mean),
+            "count_distinct" => Ok(Self::CountDistinct),
+            "first" => Ok(Self::First),
+            "last" => Ok(Self::Last),
+            other => Err(anyhow!(
+                "Unknown aggregate function '{other}' (expected count, sum, min, max, mean, count_distinct, first, or last)"
+            )),
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Count => "count",
+            Self::Sum => "sum",
+            Self::Min => "min",
+            Self::Max => "max",
+            Self::Mean => "mean",
+            Self::CountDistinct => "count_distinct",
+            Self::First => "first",
+            Self::Last => "last",
+        }
+    }
+}
+
+/// A parsed `--agg` directive such as `total=sum(amount)`.
+#[derive(Debug, Clone)]
+struct AggregateSpec {
+    name: String,
+    function: AggregateFunction,
+    column: Option<usize>,
+}
+
+impl AggregateSpec {
+    fn parse(spec: &str, schema: &Schema) -> Result<Self> {
+        let (alias, body) = match spec.split_once('=') {
+            Some((alias, body)) if !alias.contains('(') => (Some(alias.trim()), body.trim()),
+            _ => (None, spec.trim()),
+        };
+        let (function_name, column_name) = match body.split_once('(') {
+            Some((function, rest)) => {
+                let column = rest
+                    .strip_suffix(')')
+                    .ok_or_else(|| anyhow!("Aggregate '{spec}' is missing a closing ')'"))?
+                    .trim();
+                (function.trim(), (!column.is_empty()).then_some(column))
+            }
+            None => (body, None),
+        };
+        let function = AggregateFunction::parse(function_name)
+            .with_context(|| format!("Parsing aggregate '{spec}'"))?;
+
+        let column = match column_name {
+            Some(name) => Some(
+                schema
+                    .column_index(name)
+                    .ok_or_else(|| anyhow!("Aggregate column '{name}' not found in schema"))?,
+            ),
+            None if function == AggregateFunction::Count => None,
+            None => bail!("Aggregate '{spec}' requires a column"),
+        };
+        if let Some(idx) = column
+            && matches!(function, AggregateFunction::Sum | AggregateFunction::Mean)
+            && !is_summable(&schema.columns[idx].datatype)
+        {
+            bail!(
+                "Aggregate '{spec}' requires a numeric column but '{}' is {}",
+                schema.columns[idx].output_name(),
+                schema.columns[idx].datatype.describe()
+            );
+        }
+
+        let name = match (alias.filter(|alias| !alias.is_empty()), column) {
+            (Some(alias), _) => alias.to_string(),
+            (None, Some(idx)) => {
+                format!("{}_{}", function.label(), schema.columns[idx].output_name())
+            }
+            (None, None) => function.label().to_string(),
+        };
+        Ok(Self {
+            name,
+            function,
+            column,
+        })
+    }
+}
+
+fn is_summable(datatype: &ColumnType) -> bool {
+    matches!(
+        datatype,
+        ColumnType::Integer | ColumnType::Float | ColumnType::Decimal(_) | ColumnType::Currency
+    )
+}
+
+/// Running numeric total that stays exact for integer and fixed-point columns.
+#[derive(Debug, Clone)]
+pub(crate) enum NumericSum {
+    Integer(i128),
+    Float(f64),
+    Decimal(Decimal),
+    Currency(Decimal),
+}
+
+impl NumericSum {
+    pub(crate) fn for_type(datatype: &ColumnType) -> Self {
+        match datatype {
+            ColumnType::Integer => Self::Integer(0),
+            ColumnType::Decimal(_) => Self::Decimal(Decimal::ZERO),
+            ColumnType::Currency => Self::Currency(Decimal::ZERO),
+            _ => Self::Float(0.0),
+        }
+    }
+
+    pub(crate) fn add(&mut self, value: &Value) -> Result<()> {
+        match (self, value) {
+            (Self::Integer(total), Value::Integer(v)) => *total += i128::from(*v),
+            (Self::Float(total), Value::Float(v)) => *total += v,
+            (Self::Float(total), Value::Integer(v)) => *total += *v as f64,
+            (Self::Decimal(total) | Self::Currency(total), Value::Decimal(v)) => {
+                *total = total
+                    .checked_add(*v.amount())
+                    .ok_or_else(|| anyhow!("Decimal sum overflowed"))?;
+            }
+            (Self::Decimal(total) | Self::Currency(total), Value::Currency(v)) => {
+                *total = total
+                    .checked_add(*v.amount())
+                    .ok_or_else(|| anyhow!("Currency sum overflowed"))?;
+            }
+            (_, other) => bail!("Cannot add value '{}' to a numeric sum", other.as_display()),
+        }
+        Ok(())
+    }
+
+    pub(crate) fn render(&self) -> String {
+        match self {
+            Self::Integer(total) => total.to_string(),
+            Self::Float(total) => Value::Float(*total).as_display(),
+            Self::Decimal(total) | Self::Currency(total) => total.to_string(),
+        }
+    }
+
+    /// Typed view of the running total, matching the column type
+    /// [`WindowPlan::column_types`](crate::window::WindowPlan::column_types)
+    /// declares for it. Fixed-point totals stay exact; a total that no longer fits
+    /// its type falls back to a float.
+    pub(crate) fn as_value(&self) -> Option<Value> {
+        match self {
+            Self::Integer(total) => Some(
+                i64::try_from(*total)
+                    .map(Value::Integer)
+                    .unwrap_or(Value::Float(*total as f64)),
+            ),
+            Self::Float(total) => Some(Value::Float(*total)),
+            Self::Decimal(total) => {
+                let spec = DecimalSpec {
+                    precision: DECIMAL_MAX_PRECISION,
+                    scale: total.scale(),
+                };
+                FixedDecimalValue::from_decimal(*total, &spec, None)
+                    .map(Value::Decimal)
+                    .ok()
+                    .or_else(|| total.to_f64().map(Value::Float))
+            }
+            Self::Currency(total) => CurrencyValue::from_decimal(*total)
+                .map(Value::Currency)
+                .ok()
+                .or_else(|| total.to_f64().map(Value::Float)),
+        }
+    }
+
+    fn render_mean(&self, count: usize) -> Result<String> {
+        if count == 0 {
+            return Ok(String::new());
+        }
+        Ok(match self {
+            Self::Integer(total) => {
+                let total = Decimal::try_from_i128_with_scale(*total, 0)
+                    .context("Integer sum exceeds the range supported for mean")?;
+                (total / Decimal::from(count))
+                    .round_dp(MEAN_SCALE)
+                    .normalize()
+                    .to_string()
+            }
+            Self::Float(total) => Value::Float(total / count as f64).as_display(),
+            Self::Decimal(total) | Self::Currency(total) => {
+                let scale = total.scale() + MEAN_SCALE;
+                (total / Decimal::from(count))
+                    .round_dp(scale)
+                    .normalize()
+                    .to_string()
+            }
+        })
+    }
+}
+
+#[derive(Debug)]
+enum Accumulator {
+    Count(usize),
+    Sum(NumericSum),
+    Mean(NumericSum, usize),
+    Min(Option<Value>),
+    Max(Option<Value>),
+    CountDistinct(BTreeSet<ComparableValue>),
+    First(Option<String>),
+    Last(Option<String>),
+}
+
+impl Accumulator {
+    fn new(spec: &AggregateSpec, schema: &Schema) -> Self {
+        let datatype = spec.column.map(|idx| &schema.columns[idx].datatype);
+        match spec.function {
+            AggregateFunction::Count => Self::Count(0),
+            AggregateFunction::Sum => {
+                Self::Sum(NumericSum::for_type(datatype.unwrap_or(&ColumnType::Float)))
+            }
+            AggregateFunction::Mean => Self::Mean(
+                NumericSum::for_type(datatype.unwrap_or(&ColumnType::Float)),
+                0,
+            ),
+            AggregateFunction::Min => Self::Min(None),
+            AggregateFunction::Max => Self::Max(None),
+            AggregateFunction::CountDistinct => Self::CountDistinct(BTreeSet::new()),
+            AggregateFunction::First => Self::First(None),
+            AggregateFunction::Last => Self::Last(None),
+        }
+    }
+
+    fn ingest(&mut self, raw: Option<&String>, typed: Option<&Value>) -> Result<()> {
+        match self {
+            Self::Count(count) => {
+                // A bare `count` has no column and counts every row.
+                if raw.is_none() || typed.is_some() {
+                    *count += 1;
+                }
+            }
+            Self::Sum(sum) => {
+                if let Some(value) = typed {
+                    sum.add(value)?;
+                }
+            }
+            Self::Mean(sum, count) => {
+                if let Some(value) = typed {
+                    sum.add(value)?;
+                    *count += 1;
+                }
+            }
+            Self::Min(current) => {
+                if let Some(value) = typed
+                    && current.as_ref().is_none_or(|existing| value < existing)
+                {
+                    *current = Some(value.clone());
+                }
+            }
+            Self::Max(current) => {
+                if let Some(value) = typed
+                    && current.as_ref().is_none_or(|existing| value > existing)
+                {
+                    *current = Some(value.clone());
+                }
+            }
+            Self::CountDistinct(seen) => {
+                if let Some(value) = typed {
+                    seen.insert(ComparableValue(Some(value.clone())));
+                }
+            }
+            Self::First(current) => {
+                if current.is_none() {
+                    *current = Some(raw.cloned().unwrap_or_default());
+                }
+            }
+            Self::Last(current) => *current = Some(raw.cloned().unwrap_or_default()),
+        }
+        Ok(())
+    }
+
+    fn render(self) -> Result<String> {
+        Ok(match self {
+            Self::Count(count) => count.to_string(),
+            Self::Sum(sum) => sum.render(),
+            Self::Mean(sum, count) => sum.render_mean(count)?,
+            Self::Min(value) | Self::Max(value) => {
+                value.map(|value| value.as_display()).unwrap_or_default()
+            }
+            Self::CountDistinct(seen) => seen.len().to_string(),
+            Self::First(value) | Self::Last(value) => value.unwrap_or_default(),
+        })
+    }
+}
+
+struct Group {
+    key: Vec<String>,
+    accumulators: Vec<Accumulator>,
+}
+
+impl Group {
+    fn new(key: Vec<String>, specs: &[AggregateSpec], schema: &Schema) -> Self {
+        Self {
+            key,
+            accumulators: specs
+                .iter()
+                .map(|spec| Accumulator::new(spec, schema))
+                .collect(),
+        }
+    }
+
+    fn ingest(
+        &mut self,
+        specs: &[AggregateSpec],
+        raw: &[String],
+        typed: &[Option<Value>],
+    ) -> Result<()> {
+        for (spec, accumulator) in specs.iter().zip(self.accumulators.iter_mut()) {
+            let (raw_value, typed_value) = match spec.column {
+                Some(idx) => (raw.get(idx), typed.get(idx).and_then(Option::as_ref)),
+                None => (None, None),
+            };
+            accumulator
+                .ingest(raw_value, typed_value)
+                .with_context(|| format!("Computing '{}'", spec.name))?;
+        }
+        Ok(())
+    }
+
+    fn render(self) -> Result<Vec<String>> {
+        let mut row = self.key;
+        for accumulator in self.accumulators {
+            row.push(accumulator.render()?);
+        }
+        Ok(row)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::parse_typed_value;
+    use crate::schema::DecimalSpec;
+
+    fn schema() -> Schema {
+        let mut schema = Schema::from_headers(&[
+            "region".to_string(),
+            "amount".to_string(),
+            "units".to_string(),
+        ]);
+        schema.columns[1].datatype = ColumnType::Decimal(DecimalSpec {
+            precision: 12,
+            scale: 2,
+        });
+        schema.columns[2].datatype = ColumnType::Integer;
+        schema
+    }
+
+    #[test]
+    fn split_specs_keeps_parenthesised_commas() {
+        assert_eq!(
+            split_specs("count, total=sum(amount),max(units)"),
+            vec!["count", "total=sum(amount)", "max(units)"]
+        );
+    }
+
+    #[test]
+    fn parse_spec_names_and_validates_columns() {
+        let schema = schema();
+        let spec = AggregateSpec::parse("total=sum(amount)", &schema).expect("alias spec");
+        assert_eq!(spec.name, "total");
+        assert_eq!(spec.column, Some(1));
+
+        let spec = AggregateSpec::parse("mean(units)", &schema).expect("default name");
+        assert_eq!(spec.name, "mean_units");
+
+        assert!(AggregateSpec::parse("sum(region)", &schema).is_err());
+        assert!(AggregateSpec::parse("median(units)", &schema).is_err());
+        assert!(AggregateSpec::parse("max", &schema).is_err());
+    }
+
+    #[test]
+    fn decimal_sum_and_mean_stay_exact() {
+        let schema = schema();
+        let spec = AggregateSpec::parse("sum(amount)", &schema).unwrap();
+        let mean_spec = AggregateSpec::parse("mean(amount)", &schema).unwrap();
+        let mut sum = Accumulator::new(&spec, &schema);
+        let mut mean = Accumulator::new(&mean_spec, &schema);
+        for raw in ["0.10", "0.20", "0.30"] {
+            let value = parse_typed_value(raw, &schema.columns[1].datatype)
+                .unwrap()
+                .unwrap();
+            sum.ingest(None, Some(&value)).unwrap();
+            mean.ingest(None, Some(&value)).unwrap();
+        }
+        assert_eq!(sum.render().unwrap(), "0.60");
+        assert_eq!(mean.render().unwrap(), "0.2");
+    }
+}
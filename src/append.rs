@@ -3,21 +3,31 @@
 //! Concatenates two or more CSV files into a single output stream, writing the
 //! header row once and validating that all input files share the same column
 //! layout. Optionally applies schema-driven type checking during append.
+//! Output can be split by column value or size (see [`crate::split`]).
 //!
 //! # Complexity
 //!
 //! Append is O(n) where n is the total row count across all input files.
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result, anyhow};
 use log::info;
 
-use crate::{cli::AppendArgs, data::parse_typed_value, io_utils, schema::Schema};
+use crate::{
+    cli::{AppendArgs, OutputFormat, ParquetArgs},
+    data::parse_typed_value,
+    dialect::Dialect,
+    io_utils,
+    malformed::{MalformedRows, MalformedSummary},
+    parquet_output::ParquetOutput,
+    schema::{ColumnType, Schema},
+    split::CsvOutput,
+};
 
 /// Concatenates multiple CSV files into a single output stream, validating header
 /// consistency and optionally applying schema-driven type transformations.
-pub fn execute(args: &AppendArgs) -> Result<()> {
+pub fn execute(args: &AppendArgs) -> Result<MalformedSummary> {
     if args.inputs.is_empty() {
         return Err(anyhow!("At least one input file must be provided"));
     }
@@ -33,20 +43,50 @@ pub fn execute(args: &AppendArgs) -> Result<()> {
     } else {
         None
     };
+    let dialect = args.dialect.apply(
+        schema
+            .as_ref()
+            .and_then(|schema| schema.dialect.clone())
+            .unwrap_or_default(),
+    );
+    dialect.validate()?;
+    let malformed = MalformedRows::from_args(&args.malformed)?;
 
     let mut baseline_headers: Option<Vec<String>> = None;
-    let mut writer =
-        io_utils::open_csv_writer(args.output.as_deref(), output_delimiter, output_encoding)?;
+    let mut output = match args.output_format {
+        OutputFormat::Csv => AppendOutput::Csv(CsvOutput::open(
+            args.output.as_deref(),
+            output_delimiter,
+            output_encoding,
+            &dialect,
+            &args.split,
+        )?),
+        OutputFormat::Parquet => {
+            if args.split.is_enabled() {
+                return Err(anyhow!("Splitting output is only supported for CSV output"));
+            }
+            AppendOutput::Parquet {
+                path: args.output.clone(),
+                settings: args.parquet.clone(),
+                writer: None,
+            }
+        }
+        OutputFormat::Json | OutputFormat::Jsonl => {
+            return Err(anyhow!("append supports --output-format csv or parquet"));
+        }
+    };
     let mut total_rows = 0usize;
     let context = AppendContext {
         delimiter,
         encoding: input_encoding,
+        dialect: &dialect,
         schema: schema.as_ref(),
+        malformed: malformed.as_ref(),
     };
 
     {
         let mut state = AppendState {
-            writer: &mut writer,
+            output: &mut output,
             baseline_headers: &mut baseline_headers,
             total_rows: &mut total_rows,
         };
@@ -57,18 +97,76 @@ pub fn execute(args: &AppendArgs) -> Result<()> {
         }
     }
 
+    output.finish()?;
+    let malformed_rows = malformed
+        .as_ref()
+        .map(MalformedRows::finish)
+        .transpose()?
+        .unwrap_or_default();
     info!("Wrote {total_rows} data row(s) to output");
-    Ok(())
+    Ok(malformed_rows)
 }
 
 struct AppendContext<'schema> {
     delimiter: u8,
     encoding: &'static encoding_rs::Encoding,
+    dialect: &'schema Dialect,
     schema: Option<&'schema Schema>,
+    malformed: Option<&'schema MalformedRows>,
+}
+
+/// Append destination. The Parquet file is created once the first input's headers
+/// are known.
+enum AppendOutput {
+    Csv(CsvOutput),
+    Parquet {
+        path: Option<PathBuf>,
+        settings: ParquetArgs,
+        writer: Option<ParquetOutput>,
+    },
+}
+
+impl AppendOutput {
+    fn start(&mut self, columns: Vec<(String, ColumnType)>, write_headers: bool) -> Result<()> {
+        match self {
+            AppendOutput::Csv(output) => {
+                let headers = columns
+                    .into_iter()
+                    .map(|(name, _)| name)
+                    .collect::<Vec<_>>();
+                output.start(&headers, write_headers)
+            }
+            AppendOutput::Parquet {
+                path,
+                settings,
+                writer,
+            } => {
+                *writer = Some(ParquetOutput::open(path.as_deref(), &columns, settings)?);
+                Ok(())
+            }
+        }
+    }
+
+    fn write_record(&mut self, record: &[String]) -> Result<()> {
+        match self {
+            AppendOutput::Csv(output) => output.write_record(record),
+            AppendOutput::Parquet { writer, .. } => writer
+                .as_mut()
+                .ok_or_else(|| anyhow!("Parquet output was not started"))?
+                .write_record(record),
+        }
+    }
+
+    fn finish(self) -> Result<()> {
+        match self {
+            AppendOutput::Csv(output) => output.finish(),
+            AppendOutput::Parquet { writer, .. } => writer.map_or(Ok(()), ParquetOutput::finish),
+        }
+    }
 }
 
 struct AppendState<'writer> {
-    writer: &'writer mut csv::Writer<Box<dyn std::io::Write>>,
+    output: &'writer mut AppendOutput,
     baseline_headers: &'writer mut Option<Vec<String>>,
     total_rows: &'writer mut usize,
 }
@@ -79,27 +177,43 @@ fn append_single(
     context: &AppendContext<'_>,
     state: &mut AppendState<'_>,
 ) -> Result<()> {
-    let (mut reader, headers, expects_headers) = if let Some(schema) = context.schema {
+    let (headers, records, expects_headers) = if let Some(schema) = context.schema {
         let expects_headers = schema.expects_headers();
-        let mut reader =
-            io_utils::open_csv_reader_from_path(path, context.delimiter, expects_headers)?;
-        let headers = if expects_headers {
-            io_utils::reader_headers(&mut reader, context.encoding)?
-        } else {
-            schema.headers()
-        };
-        (reader, headers, expects_headers)
+        let (headers, records) = io_utils::open_csv_records(
+            path,
+            context.delimiter,
+            context.encoding,
+            expects_headers,
+            context.dialect,
+            context.malformed,
+        )?;
+        (
+            headers.unwrap_or_else(|| schema.headers()),
+            records,
+            expects_headers,
+        )
     } else {
-        let layout =
-            crate::schema::detect_csv_layout(path, context.delimiter, context.encoding, None)?;
-        let mut reader =
-            io_utils::open_csv_reader_from_path(path, context.delimiter, layout.has_headers)?;
-        let headers = if layout.has_headers {
-            io_utils::reader_headers(&mut reader, context.encoding)?
-        } else {
-            layout.headers.clone()
-        };
-        (reader, headers, layout.has_headers)
+        let layout = crate::schema::detect_csv_layout(
+            path,
+            context.delimiter,
+            context.encoding,
+            None,
+            context.dialect,
+            context.malformed.is_some(),
+        )?;
+        let (headers, records) = io_utils::open_csv_records(
+            path,
+            context.delimiter,
+            context.encoding,
+            layout.has_headers,
+            context.dialect,
+            context.malformed,
+        )?;
+        (
+            headers.unwrap_or_else(|| layout.headers.clone()),
+            records,
+            layout.has_headers,
+        )
     };
 
     if let Some(schema) = context.schema {
@@ -123,22 +237,22 @@ fn append_single(
 
     if write_header {
         if let Some(schema) = context.schema {
-            if schema.expects_headers() {
-                let output_headers = schema.output_headers();
-                state
-                    .writer
-                    .write_record(output_headers.iter())
-                    .with_context(|| "Writing output headers")?;
-            }
-        } else if expects_headers {
-            state
-                .writer
-                .write_record(headers.iter())
-                .with_context(|| "Writing output headers")?;
+            let columns = schema
+                .output_headers()
+                .into_iter()
+                .zip(schema.columns.iter().map(|column| column.datatype.clone()))
+                .collect();
+            state.output.start(columns, schema.expects_headers())?;
+        } else {
+            let columns = headers
+                .iter()
+                .map(|header| (header.clone(), ColumnType::String))
+                .collect();
+            state.output.start(columns, expects_headers)?;
         }
     }
 
-    for (row_idx, record) in reader.byte_records().enumerate() {
+    for (row_idx, record) in records.enumerate() {
         let record = record.with_context(|| format!("Reading row {} in {path:?}", row_idx + 2))?;
         let mut decoded = io_utils::decode_record(&record, context.encoding)?;
         if let Some(schema) = context.schema {
@@ -156,8 +270,8 @@ fn append_single(
             validate_record(schema, &decoded, row_idx + 2)?;
         }
         state
-            .writer
-            .write_record(decoded.iter())
+            .output
+            .write_record(&decoded)
             .with_context(|| format!("Writing row {} from {path:?}", row_idx + 2))?;
         *state.total_rows += 1;
     }
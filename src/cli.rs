@@ -1,7 +1,7 @@
 //! CLI argument definitions using `clap` derive macros.
 //!
 //! Defines the top-level [`Cli`] struct and [`Commands`] enum for all
-//! subcommands: `schema`, `index`, `process`, `append`, `stats`, and `install`.
+//! subcommands: `schema`, `index`, `process`, `aggregate`, `append`, `stats`, and `install`.
 //! Each subcommand has a dedicated `*Args` struct with typed fields.
 //!
 //! Special argument preprocessing (e.g., `--report-invalid:stats:counts`
@@ -11,6 +11,8 @@ use std::path::PathBuf;
 
 use clap::{Args, Parser, Subcommand, ValueEnum};
 
+use crate::dialect::{Dialect, LineTerminator, QuoteStyle, TrimMode};
+
 #[derive(Debug, Parser)]
 #[command(author, version, about = "Manage CSV files efficiently", long_about = None)]
 pub struct Cli {
@@ -25,13 +27,15 @@ pub enum Commands {
     /// Create a B-Tree index (.idx) for one or more columns
     Index(IndexArgs),
     /// Transform a CSV file using sorting, filtering, projection, derivations, and schema-driven replacements
-    Process(ProcessArgs),
+    Process(Box<ProcessArgs>),
+    /// Group rows by one or more columns and compute per-group aggregates
+    Aggregate(AggregateArgs),
     /// Append multiple CSV files into a single output
     Append(AppendArgs),
     /// Produce summary statistics for numeric columns or frequency counts via --frequency
     Stats(StatsArgs),
-    // /// Join two CSV files on common columns
-    // Join(JoinArgs),
+    /// Join two CSV files on common columns
+    Join(JoinArgs),
     /// Install the csv-managed binary via cargo install
     Install(InstallArgs),
 }
@@ -79,6 +83,9 @@ pub struct SchemaProbeArgs {
     /// Number of rows to sample when inferring types (0 means full scan)
     #[arg(long = "sample-rows", default_value_t = 2000)]
     pub sample_rows: usize,
+    /// Draw the --sample-rows rows at random from the whole file with this seed instead of taking the first rows
+    #[arg(long = "seed")]
+    pub seed: Option<u64>,
     /// CSV delimiter character (supports ',', 'tab', ';', '|')
     #[arg(long, value_parser = parse_delimiter)]
     pub delimiter: Option<u8>,
@@ -103,6 +110,13 @@ pub struct SchemaProbeArgs {
     /// Force header detection outcome (`true` treats first row as header, `false` treats it as data)
     #[arg(long = "assume-header", value_name = "true|false")]
     pub assume_header: Option<bool>,
+    /// Treat the input as fixed-width text and suggest column positions from blank runs
+    #[arg(long = "fixed-width")]
+    pub fixed_width: bool,
+    #[command(flatten)]
+    pub input_format: InputFormatArgs,
+    #[command(flatten)]
+    pub dialect: DialectArgs,
 }
 
 #[derive(Debug, Args, Clone)]
@@ -140,6 +154,10 @@ pub struct SchemaVerifyArgs {
     /// Report invalid rows by summary (default) or detail. Append ':detail' and/or ':summary' and optionally a LIMIT value.
     #[arg(long = "report-invalid", value_name = "OPTIONS", num_args = 0..=3)]
     pub report_invalid: Option<Vec<String>>,
+    #[command(flatten)]
+    pub input_format: InputFormatArgs,
+    #[command(flatten)]
+    pub dialect: DialectArgs,
 }
 
 #[derive(Debug, Args)]
@@ -165,31 +183,45 @@ pub struct IndexArgs {
     /// Limit number of rows to scan (useful for prototyping)
     #[arg(long)]
     pub limit: Option<usize>,
+    /// Add rows appended to the input since the existing index was built, keeping its variants
+    #[arg(long, conflicts_with_all = ["columns", "specs", "coverings", "limit"])]
+    pub update: bool,
     /// CSV delimiter character (supports ',', 'tab', ';', '|')
     #[arg(long, value_parser = parse_delimiter)]
     pub delimiter: Option<u8>,
     /// Character encoding of the input file (defaults to utf-8)
     #[arg(long = "input-encoding")]
     pub input_encoding: Option<String>,
+    #[command(flatten)]
+    pub dialect: DialectArgs,
 }
 
 #[derive(Debug, Args)]
 pub struct ProcessArgs {
-    /// Input CSV file to process
-    #[arg(short = 'i', long = "input")]
-    pub input: PathBuf,
-    /// Output CSV file (stdout if omitted)
+    /// Input CSV file(s) or glob patterns to process as one stream
+    #[arg(short = 'i', long = "input", required = true, action = clap::ArgAction::Append)]
+    pub inputs: Vec<PathBuf>,
+    /// Add a column with this name recording the input file each row came from
+    #[arg(long = "source-column")]
+    pub source_column: Option<String>,
+    /// Output CSV file (stdout if omitted), or a file name pattern when splitting
     #[arg(short = 'o', long = "output")]
     pub output: Option<PathBuf>,
     /// Schema file to drive typed operations and apply value replacements
     #[arg(short = 'm', long = "schema", alias = "meta")]
     pub schema: Option<PathBuf>,
+    /// How to fill schema-declared lookup columns when a key has no match
+    #[arg(long = "on-miss", default_value = "empty")]
+    pub on_miss: OnMiss,
     /// Existing index file to speed up operations
     #[arg(short = 'x', long = "index")]
     pub index: Option<PathBuf>,
     /// Specific index variant name to use from the selected index file
     #[arg(long = "index-variant")]
     pub index_variant: Option<String>,
+    /// Rebuild and overwrite the --index file when the input changed since it was built, instead of failing
+    #[arg(long = "rebuild-stale-index", requires = "index")]
+    pub rebuild_stale_index: bool,
     /// Sort directives of the form `column[:asc|desc]`
     #[arg(long = "sort", action = clap::ArgAction::Append)]
     pub sort: Vec<String>,
@@ -202,6 +234,9 @@ pub struct ProcessArgs {
     /// Additional derived columns using `name=expression`
     #[arg(long = "derive", action = clap::ArgAction::Append)]
     pub derives: Vec<String>,
+    /// Window columns such as `balance=running_sum(amount) partition by account order by posted_at`
+    #[arg(long = "window", action = clap::ArgAction::Append)]
+    pub windows: Vec<String>,
     /// Row-level filters such as `amount>=100` or `status = shipped`
     #[arg(long = "filter", action = clap::ArgAction::Append)]
     pub filters: Vec<String>,
@@ -214,6 +249,15 @@ pub struct ProcessArgs {
     /// Limit number of rows emitted
     #[arg(long)]
     pub limit: Option<usize>,
+    /// Keep a uniform random sample of this many rows (after filters)
+    #[arg(long = "sample", conflicts_with = "sample_fraction")]
+    pub sample: Option<usize>,
+    /// Keep each row with this probability, e.g. `0.01` (after filters)
+    #[arg(long = "sample-fraction")]
+    pub sample_fraction: Option<f64>,
+    /// Seed for --sample / --sample-fraction so runs select the same rows
+    #[arg(long = "seed")]
+    pub seed: Option<u64>,
     /// CSV delimiter character for reading input
     #[arg(long, value_parser = parse_delimiter)]
     pub delimiter: Option<u8>,
@@ -229,6 +273,12 @@ pub struct ProcessArgs {
     /// Normalize boolean columns in output
     #[arg(long = "boolean-format", default_value = "original")]
     pub boolean_format: BooleanFormat,
+    /// Output format: csv, json (an array of objects), jsonl (one object per line), or parquet
+    #[arg(long = "output-format", default_value = "csv")]
+    pub output_format: OutputFormat,
+    /// Render Decimal and Currency values in JSON output as exact strings or bare numbers
+    #[arg(long = "json-decimals", default_value = "string")]
+    pub json_decimals: JsonDecimals,
     /// Render results as a preview table on stdout (disables --output and defaults the row limit)
     #[arg(long = "preview")]
     pub preview: bool,
@@ -241,6 +291,173 @@ pub struct ProcessArgs {
     /// Skip schema-defined datatype mappings even if they exist
     #[arg(long = "skip-mappings")]
     pub skip_mappings: bool,
+    /// Memory budget for sorting without an index (e.g. `512M`, `2G`); sorted runs spill to disk beyond it
+    #[arg(long = "memory-limit", value_parser = parse_memory_limit)]
+    pub memory_limit: Option<usize>,
+    /// Directory for sort spill files (defaults to the system temp directory)
+    #[arg(long = "temp-dir")]
+    pub temp_dir: Option<PathBuf>,
+    /// Worker threads for parsing, filtering, and derives (0 = all cores; output order is preserved; ignored with --index)
+    #[arg(long = "threads")]
+    pub threads: Option<usize>,
+    /// Drop rows whose typed values duplicate an earlier row across all columns
+    #[arg(long = "distinct", conflicts_with = "distinct_on")]
+    pub distinct: bool,
+    /// Drop rows whose typed values in this comma-separated list of columns duplicate another row
+    #[arg(long = "distinct-on", action = clap::ArgAction::Append)]
+    pub distinct_on: Vec<String>,
+    /// Which occurrence of a duplicate key to keep
+    #[arg(long = "keep", default_value = "first")]
+    pub keep: DistinctKeep,
+    /// Cells that fail their datatype mapping or column type: fail, null (empty the cell), or quarantine (route the row to --type-reject-file)
+    #[arg(long = "on-type-error", default_value = "fail")]
+    pub on_type_error: OnTypeError,
+    /// CSV file receiving rows quarantined for type errors, with row_number, column_name, raw_value, and reason columns appended
+    #[arg(long = "type-reject-file")]
+    pub type_reject_file: Option<PathBuf>,
+    #[command(flatten)]
+    pub input_format: InputFormatArgs,
+    #[command(flatten)]
+    pub parquet: ParquetArgs,
+    #[command(flatten)]
+    pub split: SplitArgs,
+    #[command(flatten)]
+    pub dialect: DialectArgs,
+    #[command(flatten)]
+    pub malformed: MalformedArgs,
+}
+
+/// Parquet writer settings shared by `process` and `append`.
+#[derive(Debug, Args, Clone, Default)]
+pub struct ParquetArgs {
+    /// Rows per Parquet row group (defaults to 1048576)
+    #[arg(long = "row-group-size")]
+    pub row_group_size: Option<usize>,
+    /// Compression codec for Parquet output
+    #[arg(long = "parquet-compression", default_value = "snappy")]
+    pub compression: ParquetCompression,
+}
+
+/// Reader settings for JSON and Excel inputs, shared by the commands that read them.
+#[derive(Debug, Args, Clone)]
+pub struct InputFormatArgs {
+    /// Separator joining nested JSON object keys into column names (e.g. `address.city`)
+    #[arg(long = "json-separator", default_value = ".")]
+    pub json_separator: String,
+    /// Join JSON array elements into one `|`-separated cell, or explode them into one row per element
+    #[arg(long = "json-arrays", default_value = "join")]
+    pub json_arrays: JsonArrays,
+    /// Worksheet to read from an .xlsx/.xls/.ods workbook (defaults to the first sheet)
+    #[arg(long = "sheet")]
+    pub sheet: Option<String>,
+    /// Cell range to read from the worksheet, such as `B2:F200` or `A3:H` (defaults to the used range)
+    #[arg(long = "range", value_name = "A1:B2")]
+    pub range: Option<String>,
+}
+
+impl Default for InputFormatArgs {
+    fn default() -> Self {
+        Self {
+            json_separator: ".".to_string(),
+            json_arrays: JsonArrays::Join,
+            sheet: None,
+            range: None,
+        }
+    }
+}
+
+/// CSV dialect overrides layered over a schema's `dialect:` block (or over the
+/// detected dialect during `schema infer`).
+#[derive(Debug, Args, Clone, Default)]
+pub struct DialectArgs {
+    /// When to quote output fields: always, necessary, non-numeric, or never
+    #[arg(long = "quote-style")]
+    pub quote_style: Option<QuoteStyle>,
+    /// Quote character for reading and writing (defaults to `"`)
+    #[arg(long = "quote-char", value_parser = parse_dialect_char)]
+    pub quote: Option<char>,
+    /// Escape character for quotes inside quoted fields (defaults to doubling the quote)
+    #[arg(long = "escape-char", value_parser = parse_dialect_char)]
+    pub escape: Option<char>,
+    /// Treat quote characters in the input as ordinary text
+    #[arg(long = "no-quoting")]
+    pub no_quoting: bool,
+    /// Line ending for output records: lf or crlf
+    #[arg(long = "line-terminator")]
+    pub terminator: Option<LineTerminator>,
+    /// Skip input lines starting with this character
+    #[arg(long = "comment-char", value_parser = parse_dialect_char)]
+    pub comment: Option<char>,
+    /// Trim surrounding whitespace from input headers, fields, or all
+    #[arg(long = "trim")]
+    pub trim: Option<TrimMode>,
+    /// Start CSV output with a UTF-8 byte-order mark
+    #[arg(long = "bom")]
+    pub bom: bool,
+}
+
+impl DialectArgs {
+    /// Returns `base` with every flag given on the command line applied.
+    pub fn apply(&self, mut base: Dialect) -> Dialect {
+        if let Some(quote_style) = self.quote_style {
+            base.quote_style = quote_style;
+        }
+        if let Some(quote) = self.quote {
+            base.quote = quote;
+        }
+        if self.escape.is_some() {
+            base.escape = self.escape;
+        }
+        if self.no_quoting {
+            base.quoting = false;
+        }
+        if let Some(terminator) = self.terminator {
+            base.terminator = terminator;
+        }
+        if self.comment.is_some() {
+            base.comment = self.comment;
+        }
+        if let Some(trim) = self.trim {
+            base.trim = trim;
+        }
+        if self.bom {
+            base.bom = true;
+        }
+        base
+    }
+}
+
+/// Ragged-row handling shared by `process`, `stats`, and `append`.
+#[derive(Debug, Args, Clone, Default)]
+pub struct MalformedArgs {
+    /// Rows whose field count differs from the header: fail, skip, pad (short rows padded, long rows truncated), or quarantine
+    #[arg(long = "on-malformed", default_value = "fail")]
+    pub on_malformed: OnMalformed,
+    /// CSV file receiving quarantined rows with their source, line number, and reason
+    #[arg(long = "reject-file")]
+    pub reject_file: Option<PathBuf>,
+}
+
+/// Output splitting shared by `process` and `append`; `--output` becomes a file name pattern.
+#[derive(Debug, Args, Clone, Default)]
+pub struct SplitArgs {
+    /// Write one file per distinct value of these comma-separated output columns (pattern placeholder `{column}`)
+    #[arg(long = "split-by", action = clap::ArgAction::Append)]
+    pub split_by: Vec<String>,
+    /// Roll over to the next numbered file after this many data rows (pattern placeholder `{n}` or `{n:04}`)
+    #[arg(long = "max-rows-per-file")]
+    pub max_rows_per_file: Option<usize>,
+    /// Roll over to the next numbered file before exceeding this size (e.g. `64M`, `1G`)
+    #[arg(long = "max-bytes-per-file", value_parser = parse_file_size)]
+    pub max_bytes_per_file: Option<usize>,
+}
+
+impl SplitArgs {
+    pub fn is_enabled(&self) -> bool {
+        !self.split_by.is_empty()
+            || self.max_rows_per_file.is_some()
+            || self.max_bytes_per_file.is_some()
+    }
 }
 
 #[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq, Default)]
@@ -252,12 +469,128 @@ pub enum BooleanFormat {
     OneZero,
 }
 
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq, Default)]
+#[value(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    #[default]
+    Csv,
+    Json,
+    Jsonl,
+    Parquet,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq, Default)]
+#[value(rename_all = "kebab-case")]
+pub enum ParquetCompression {
+    None,
+    #[default]
+    Snappy,
+    Gzip,
+    Zstd,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq, Default)]
+#[value(rename_all = "kebab-case")]
+pub enum JsonDecimals {
+    #[default]
+    String,
+    Number,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq, Default)]
+#[value(rename_all = "kebab-case")]
+pub enum JsonArrays {
+    #[default]
+    Join,
+    Explode,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq, Default)]
+#[value(rename_all = "kebab-case")]
+pub enum OnMiss {
+    #[default]
+    Empty,
+    Error,
+    Default,
+}
+
+/// What to do with a row whose field count differs from the header.
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq, Default)]
+#[value(rename_all = "kebab-case")]
+pub enum OnMalformed {
+    #[default]
+    Fail,
+    Skip,
+    Pad,
+    Quarantine,
+}
+
+/// What `process` does with a cell that fails its datatype mapping or column type.
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq, Default)]
+#[value(rename_all = "kebab-case")]
+pub enum OnTypeError {
+    #[default]
+    Fail,
+    Null,
+    Quarantine,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq, Default)]
+#[value(rename_all = "kebab-case")]
+pub enum DistinctKeep {
+    #[default]
+    First,
+    Last,
+}
+
+#[derive(Debug, Args)]
+pub struct AggregateArgs {
+    /// Input CSV file to aggregate
+    #[arg(short = 'i', long = "input")]
+    pub input: PathBuf,
+    /// Output CSV file (stdout if omitted)
+    #[arg(short = 'o', long = "output")]
+    pub output: Option<PathBuf>,
+    /// Schema file to drive typed grouping and aggregation
+    #[arg(short = 'm', long = "schema", alias = "meta")]
+    pub schema: Option<PathBuf>,
+    /// Comma-separated list of columns to group by (omit for a single overall row)
+    #[arg(short = 'g', long = "group-by", action = clap::ArgAction::Append)]
+    pub group_by: Vec<String>,
+    /// Aggregates of the form `[name=]function(column)`: count, sum, min, max, mean, count_distinct, first, last
+    #[arg(short = 'a', long = "agg", required = true, action = clap::ArgAction::Append)]
+    pub aggregates: Vec<String>,
+    /// Row-level filters such as `amount>=100` or `status = shipped`
+    #[arg(long = "filter", action = clap::ArgAction::Append)]
+    pub filters: Vec<String>,
+    /// Evalexpr-based filter expressions that must evaluate to truthy values
+    #[arg(long = "filter-expr", action = clap::ArgAction::Append)]
+    pub filter_exprs: Vec<String>,
+    /// CSV delimiter character for reading input
+    #[arg(long, value_parser = parse_delimiter)]
+    pub delimiter: Option<u8>,
+    /// Delimiter to use for output (defaults to input delimiter)
+    #[arg(long = "output-delimiter", value_parser = parse_delimiter)]
+    pub output_delimiter: Option<u8>,
+    /// Character encoding of the input file (defaults to utf-8)
+    #[arg(long = "input-encoding")]
+    pub input_encoding: Option<String>,
+    /// Character encoding for the output file/stdout (defaults to utf-8)
+    #[arg(long = "output-encoding")]
+    pub output_encoding: Option<String>,
+    /// Render output as an elastic table to stdout
+    #[arg(long = "table")]
+    pub table: bool,
+    #[command(flatten)]
+    pub dialect: DialectArgs,
+}
+
 #[derive(Debug, Args)]
 pub struct AppendArgs {
     /// One or more CSV files to append
     #[arg(short = 'i', long = "input", required = true, action = clap::ArgAction::Append)]
     pub inputs: Vec<PathBuf>,
-    /// Destination CSV file (stdout if omitted)
+    /// Destination file (stdout if omitted for CSV), or a file name pattern when splitting
     #[arg(short = 'o', long = "output")]
     pub output: Option<PathBuf>,
     /// Schema file to verify against
@@ -272,6 +605,17 @@ pub struct AppendArgs {
     /// Character encoding for the output file/stdout (defaults to utf-8)
     #[arg(long = "output-encoding")]
     pub output_encoding: Option<String>,
+    /// Output format: csv or parquet (typed from --schema; all strings without one)
+    #[arg(long = "output-format", default_value = "csv")]
+    pub output_format: OutputFormat,
+    #[command(flatten)]
+    pub parquet: ParquetArgs,
+    #[command(flatten)]
+    pub split: SplitArgs,
+    #[command(flatten)]
+    pub dialect: DialectArgs,
+    #[command(flatten)]
+    pub malformed: MalformedArgs,
 }
 
 #[derive(Debug, Args)]
@@ -306,6 +650,12 @@ pub struct StatsArgs {
     /// Maximum distinct values to display per column when --frequency is used (0 = all)
     #[arg(long, default_value_t = 0)]
     pub top: usize,
+    #[command(flatten)]
+    pub input_format: InputFormatArgs,
+    #[command(flatten)]
+    pub dialect: DialectArgs,
+    #[command(flatten)]
+    pub malformed: MalformedArgs,
 }
 
 #[derive(Debug, Args)]
@@ -315,18 +665,31 @@ pub struct SchemaColumnsArgs {
     pub schema: PathBuf,
 }
 
-#[derive(Debug, Clone, Copy, ValueEnum)]
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq)]
 #[value(rename_all = "kebab-case")]
 pub enum JoinKind {
     Inner,
     Left,
     Right,
     Full,
+    Semi,
+    Anti,
+    Cross,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq, Default)]
+#[value(rename_all = "kebab-case")]
+pub enum RightMatch {
+    First,
+    Last,
+    #[default]
+    All,
+    Error,
 }
 
 #[derive(Debug, Args)]
 pub struct JoinArgs {
-    /// Left CSV input
+    /// Left CSV input (use - for stdin)
     #[arg(long = "left")]
     pub left: PathBuf,
     /// Right CSV input
@@ -335,21 +698,39 @@ pub struct JoinArgs {
     /// Output CSV file (stdout if omitted)
     #[arg(short = 'o', long = "output")]
     pub output: Option<PathBuf>,
-    /// Comma-separated key columns from the left file
+    /// Comma-separated key columns from the left file (not used by --type cross)
     #[arg(long = "left-key")]
-    pub left_key: String,
-    /// Comma-separated key columns from the right file
+    pub left_key: Option<String>,
+    /// Comma-separated key columns from the right file (not used by --type cross)
     #[arg(long = "right-key")]
-    pub right_key: String,
-    /// Join type (inner, left, right, full)
+    pub right_key: Option<String>,
+    /// Join type (inner, left, right, full, semi, anti, cross)
     #[arg(long = "type", value_enum, default_value = "inner")]
     pub kind: JoinKind,
+    /// Which right rows to use when several share a join key
+    #[arg(long = "right-match", default_value = "all")]
+    pub right_match: RightMatch,
+    /// Prefix for left non-key columns whose names collide with right columns
+    #[arg(long = "left-prefix")]
+    pub left_prefix: Option<String>,
+    /// Prefix for right columns whose names collide with left columns
+    #[arg(long = "right-prefix")]
+    pub right_prefix: Option<String>,
     /// Schema for the left file
     #[arg(long = "left-schema", alias = "left-meta")]
     pub left_schema: Option<PathBuf>,
+    /// Assumed datatypes for left columns (name:type, comma separated or repeatable)
+    #[arg(long = "left-assume-types", action = clap::ArgAction::Append)]
+    pub left_assume_types: Vec<String>,
     /// Schema for the right file
     #[arg(long = "right-schema", alias = "right-meta")]
     pub right_schema: Option<PathBuf>,
+    /// Index file for the left input; with --right-index, merge join in index order instead of hashing the right input
+    #[arg(long = "left-index")]
+    pub left_index: Option<PathBuf>,
+    /// Index file for the right input (requires --left-index)
+    #[arg(long = "right-index")]
+    pub right_index: Option<PathBuf>,
     /// CSV delimiter character for inputs
     #[arg(long = "delimiter", value_parser = parse_delimiter)]
     pub delimiter: Option<u8>,
@@ -362,6 +743,8 @@ pub struct JoinArgs {
     /// Character encoding for the output file/stdout (defaults to utf-8)
     #[arg(long = "output-encoding")]
     pub output_encoding: Option<String>,
+    #[command(flatten)]
+    pub dialect: DialectArgs,
 }
 
 #[derive(Debug, Args)]
@@ -401,3 +784,60 @@ pub fn parse_delimiter(value: &str) -> Result<u8, String> {
         }
     }
 }
+
+fn parse_dialect_char(value: &str) -> Result<char, String> {
+    let mut chars = value.chars();
+    let first = chars
+        .next()
+        .ok_or_else(|| "Character cannot be empty".to_string())?;
+    if chars.next().is_some() {
+        return Err("Expected a single character".to_string());
+    }
+    if !first.is_ascii() {
+        return Err("Character must be ASCII".to_string());
+    }
+    Ok(first)
+}
+
+pub fn parse_memory_limit(value: &str) -> Result<usize, String> {
+    parse_byte_size(value, "Memory limit")
+}
+
+pub fn parse_file_size(value: &str) -> Result<usize, String> {
+    parse_byte_size(value, "File size")
+}
+
+fn parse_byte_size(value: &str, what: &str) -> Result<usize, String> {
+    let trimmed = value.trim();
+    let split = trimmed
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(trimmed.len());
+    let (digits, unit) = trimmed.split_at(split);
+    if digits.is_empty() {
+        return Err(format!(
+            "{what} '{value}' must start with a number (e.g. 512M, 2G)"
+        ));
+    }
+    let amount: usize = digits
+        .parse()
+        .map_err(|_| format!("{what} '{value}' is too large"))?;
+    let multiplier: usize = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        other => {
+            return Err(format!(
+                "Unknown {} unit '{other}' (expected K, M, or G)",
+                what.to_ascii_lowercase()
+            ));
+        }
+    };
+    let bytes = amount
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("{what} '{value}' is too large"))?;
+    if bytes == 0 {
+        return Err(format!("{what} must be greater than zero"));
+    }
+    Ok(bytes)
+}
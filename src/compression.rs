@@ -0,0 +1,441 @@
+//! Transparent gzip and zstd compression for CSV inputs and outputs.
+//!
+//! Paths ending in `.gz` / `.gzip` or `.zst` / `.zstd` are decompressed while
+//! reading and compressed while writing, so `orders.csv.gz` and
+//! `orders.tsv.zst` work anywhere a plain file does. The inner extension still
+//! picks the delimiter (`.tsv.gz` is tab-separated). Standard input is sniffed
+//! for the gzip and zstd magic bytes instead, since it has no extension.
+//!
+//! Gzip output is written as BGZF: a series of independent gzip members of at
+//! most 64 KiB each, the layout produced by `bgzip`. Any gzip reader accepts
+//! it, and because each block can be located and inflated on its own,
+//! [`open_seekable`] can seek to the uncompressed byte offsets stored in an
+//! index. Plain (single-member) gzip and zstd sources stream normally but
+//! cannot be indexed.
+
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use flate2::{Compression as Level, Crc, read::MultiGzDecoder, write::DeflateEncoder};
+
+use crate::io_utils::FinishWrite;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Uncompressed bytes per BGZF block, as written by `bgzip`.
+const BGZF_BLOCK_DATA: usize = 0xff00;
+const BGZF_HEADER_LEN: usize = 18;
+/// The empty block `bgzip` appends to mark the end of a BGZF file.
+const BGZF_EOF: [u8; 28] = [
+    0x1f, 0x8b, 0x08, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0x06, 0x00, 0x42, 0x43, 0x02, 0x00,
+    0x1b, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+/// Compression applied to a file, chosen by its extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("gz") || ext.eq_ignore_ascii_case("gzip") => {
+                Compression::Gzip
+            }
+            Some(ext) if ext.eq_ignore_ascii_case("zst") || ext.eq_ignore_ascii_case("zstd") => {
+                Compression::Zstd
+            }
+            _ => Compression::None,
+        }
+    }
+}
+
+/// `path` without its compression extension (`orders.tsv.gz` → `orders.tsv`).
+pub fn inner_path(path: &Path) -> PathBuf {
+    match Compression::from_path(path) {
+        Compression::None => path.to_path_buf(),
+        _ => path.with_extension(""),
+    }
+}
+
+/// Wraps a file opened from `path` in the decompressor its extension calls for.
+pub(crate) fn decompress_file(path: &Path, file: File) -> Result<Box<dyn Read>> {
+    let reader = BufReader::new(file);
+    Ok(match Compression::from_path(path) {
+        Compression::None => Box::new(reader),
+        Compression::Gzip => Box::new(MultiGzDecoder::new(reader)),
+        Compression::Zstd => Box::new(
+            zstd::Decoder::with_buffer(reader)
+                .with_context(|| format!("Opening zstd stream {path:?}"))?,
+        ),
+    })
+}
+
+/// Wraps a stream (standard input) in a decompressor when it starts with the
+/// gzip or zstd magic bytes.
+pub(crate) fn decompress_stream<R: Read + 'static>(reader: R) -> Result<Box<dyn Read>> {
+    let mut reader = BufReader::new(reader);
+    let head = reader.fill_buf().context("Reading standard input")?;
+    Ok(if head.starts_with(&GZIP_MAGIC) {
+        Box::new(MultiGzDecoder::new(reader))
+    } else if head.starts_with(&ZSTD_MAGIC) {
+        Box::new(zstd::Decoder::with_buffer(reader).context("Opening zstd stream")?)
+    } else {
+        Box::new(reader)
+    })
+}
+
+/// Wraps `writer` in the compressor the extension of `path` calls for.
+pub(crate) fn compress<W: Write + 'static>(path: &Path, writer: W) -> Result<Box<dyn FinishWrite>> {
+    Ok(Box::new(match Compression::from_path(path) {
+        Compression::None => OutputStream::Plain(writer),
+        Compression::Gzip => OutputStream::Gzip(BgzfWriter::new(writer)),
+        Compression::Zstd => OutputStream::Zstd(Some(
+            zstd::Encoder::new(writer, zstd::DEFAULT_COMPRESSION_LEVEL)
+                .with_context(|| format!("Starting zstd stream for {path:?}"))?,
+        )),
+    }))
+}
+
+/// An output file or stream, compressed or not. Compressed streams write their
+/// last block and trailer in [`FinishWrite::finish`].
+pub(crate) enum OutputStream<W: Write> {
+    Plain(W),
+    Gzip(BgzfWriter<W>),
+    /// `None` once the frame has been finished.
+    Zstd(Option<zstd::Encoder<'static, W>>),
+}
+
+impl<W: Write> OutputStream<W> {
+    fn zstd<'e>(
+        encoder: &'e mut Option<zstd::Encoder<'static, W>>,
+    ) -> io::Result<&'e mut zstd::Encoder<'static, W>> {
+        encoder
+            .as_mut()
+            .ok_or_else(|| io::Error::other("zstd stream already finished"))
+    }
+}
+
+impl<W: Write> Write for OutputStream<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            OutputStream::Plain(writer) => writer.write(buf),
+            OutputStream::Gzip(writer) => writer.write(buf),
+            OutputStream::Zstd(encoder) => Self::zstd(encoder)?.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            OutputStream::Plain(writer) => writer.flush(),
+            OutputStream::Gzip(writer) => writer.flush(),
+            OutputStream::Zstd(encoder) => Self::zstd(encoder)?.flush(),
+        }
+    }
+}
+
+impl<W: Write> FinishWrite for OutputStream<W> {
+    fn finish(&mut self) -> io::Result<()> {
+        match self {
+            OutputStream::Plain(writer) => writer.flush(),
+            OutputStream::Gzip(writer) => writer.finish(),
+            OutputStream::Zstd(encoder) => match encoder.take() {
+                Some(encoder) => encoder.finish()?.flush(),
+                None => Ok(()),
+            },
+        }
+    }
+}
+
+/// A reader that can be repositioned to an uncompressed byte offset.
+pub trait SeekRead: Read + Seek {}
+
+impl<T: Read + Seek> SeekRead for T {}
+
+/// Opens `path` for index-driven reads: plain files directly and BGZF gzip
+/// files block by block. Other compressed files are rejected.
+pub fn open_seekable(path: &Path) -> Result<Box<dyn SeekRead>> {
+    let file = File::open(path).with_context(|| format!("Opening input file {path:?}"))?;
+    match Compression::from_path(path) {
+        Compression::None => Ok(Box::new(BufReader::new(file))),
+        Compression::Gzip => Ok(Box::new(
+            BgzfReader::new(BufReader::new(file))
+                .with_context(|| format!("Opening {path:?} for indexed reads"))?,
+        )),
+        Compression::Zstd => bail!(
+            "Indexed reads of {path:?} are not supported: zstd files cannot be seeked; use an uncompressed or block-gzipped (.gz) copy"
+        ),
+    }
+}
+
+/// Gzip writer that emits BGZF blocks so the output stays seekable.
+pub(crate) struct BgzfWriter<W: Write> {
+    inner: W,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> BgzfWriter<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            buffer: Vec::with_capacity(BGZF_BLOCK_DATA),
+        }
+    }
+
+    fn write_block(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let mut encoder = DeflateEncoder::new(Vec::new(), Level::default());
+        encoder.write_all(&self.buffer)?;
+        let deflated = encoder.finish()?;
+        let mut crc = Crc::new();
+        crc.update(&self.buffer);
+        let block_size = u16::try_from(BGZF_HEADER_LEN + deflated.len() + 8 - 1)
+            .map_err(|_| io::Error::other("BGZF block exceeds 64 KiB"))?;
+
+        let mut header = [0u8; BGZF_HEADER_LEN];
+        header[..12].copy_from_slice(&BGZF_EOF[..12]);
+        header[12..16].copy_from_slice(&[b'B', b'C', 2, 0]);
+        header[16..18].copy_from_slice(&block_size.to_le_bytes());
+        self.inner.write_all(&header)?;
+        self.inner.write_all(&deflated)?;
+        self.inner.write_all(&crc.sum().to_le_bytes())?;
+        self.inner
+            .write_all(&(self.buffer.len() as u32).to_le_bytes())?;
+        self.buffer.clear();
+        Ok(())
+    }
+
+    /// Writes the pending block and the end-of-file marker.
+    fn finish(&mut self) -> io::Result<()> {
+        self.write_block()?;
+        self.inner.write_all(&BGZF_EOF)?;
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Write for BgzfWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let take = buf.len().min(BGZF_BLOCK_DATA - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..take]);
+        if self.buffer.len() == BGZF_BLOCK_DATA {
+            self.write_block()?;
+        }
+        Ok(take)
+    }
+
+    /// Closes the pending block; BGZF blocks may hold any amount of data.
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_block()?;
+        self.inner.flush()
+    }
+}
+
+/// Location of one BGZF block in the compressed and uncompressed streams.
+#[derive(Debug, Clone, Copy)]
+struct Block {
+    offset: u64,
+    size: usize,
+    start: u64,
+    len: u64,
+}
+
+/// Reads a BGZF file and seeks to uncompressed offsets by inflating only the
+/// block that holds them.
+struct BgzfReader<R> {
+    inner: R,
+    blocks: Vec<Block>,
+    next: usize,
+    data: Vec<u8>,
+    pos: usize,
+}
+
+impl<R: Read + Seek> BgzfReader<R> {
+    /// Walks the block headers to map uncompressed offsets to blocks.
+    fn new(mut inner: R) -> Result<Self> {
+        let mut blocks = Vec::new();
+        let mut offset = 0u64;
+        let mut start = 0u64;
+        let mut header = [0u8; 12];
+        let end = inner.seek(SeekFrom::End(0))?;
+        while offset < end {
+            inner.seek(SeekFrom::Start(offset))?;
+            let complete = inner.read_exact(&mut header).is_ok();
+            if !complete || !header.starts_with(&GZIP_MAGIC) || header[2] != 8 || header[3] & 4 == 0
+            {
+                bail!(
+                    "not a block-gzipped (BGZF) file at byte {offset}; recompress it with `bgzip` or `csv-managed process -o <file>.gz` to index it"
+                );
+            }
+            let mut extra = vec![0u8; u16::from_le_bytes([header[10], header[11]]).into()];
+            inner.read_exact(&mut extra)?;
+            let size = block_size(&extra).ok_or_else(|| {
+                anyhow!(
+                    "gzip member at byte {offset} has no BGZF block size; recompress it with `bgzip` or `csv-managed process -o <file>.gz` to index it"
+                )
+            })?;
+            inner.seek(SeekFrom::Start(offset + size as u64 - 4))?;
+            let mut isize = [0u8; 4];
+            inner.read_exact(&mut isize)?;
+            let len = u64::from(u32::from_le_bytes(isize));
+            blocks.push(Block {
+                offset,
+                size,
+                start,
+                len,
+            });
+            offset += size as u64;
+            start += len;
+        }
+        Ok(Self {
+            inner,
+            blocks,
+            next: 0,
+            data: Vec::new(),
+            pos: 0,
+        })
+    }
+
+    fn load(&mut self, idx: usize) -> io::Result<()> {
+        let block = self.blocks[idx];
+        self.inner.seek(SeekFrom::Start(block.offset))?;
+        let mut compressed = vec![0u8; block.size];
+        self.inner.read_exact(&mut compressed)?;
+        self.data.clear();
+        MultiGzDecoder::new(compressed.as_slice()).read_to_end(&mut self.data)?;
+        self.pos = 0;
+        self.next = idx + 1;
+        Ok(())
+    }
+
+    fn position(&self) -> u64 {
+        match self.next.checked_sub(1) {
+            Some(idx) => self.blocks[idx].start + self.pos as u64,
+            None => 0,
+        }
+    }
+
+    fn total_len(&self) -> u64 {
+        self.blocks
+            .last()
+            .map_or(0, |block| block.start + block.len)
+    }
+}
+
+/// Size of the block from the `BC` subfield of a gzip extra field.
+fn block_size(extra: &[u8]) -> Option<usize> {
+    let mut rest = extra;
+    while rest.len() >= 4 {
+        let len = usize::from(u16::from_le_bytes([rest[2], rest[3]]));
+        let data = rest.get(4..4 + len)?;
+        if rest[..2] == [b'B', b'C'] && len == 2 {
+            return Some(usize::from(u16::from_le_bytes([data[0], data[1]])) + 1);
+        }
+        rest = &rest[4 + len..];
+    }
+    None
+}
+
+impl<R: Read + Seek> Read for BgzfReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos >= self.data.len() {
+            if self.next >= self.blocks.len() {
+                return Ok(0);
+            }
+            self.load(self.next)?;
+        }
+        let count = buf.len().min(self.data.len() - self.pos);
+        buf[..count].copy_from_slice(&self.data[self.pos..self.pos + count]);
+        self.pos += count;
+        Ok(count)
+    }
+}
+
+impl<R: Read + Seek> Seek for BgzfReader<R> {
+    fn seek(&mut self, target: SeekFrom) -> io::Result<u64> {
+        let target = match target {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(delta) => self.position().checked_add_signed(delta),
+            SeekFrom::End(delta) => self.total_len().checked_add_signed(delta),
+        }
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "seek before start"))?;
+        let idx = self
+            .blocks
+            .partition_point(|block| block.start + block.len <= target);
+        if idx == self.blocks.len() {
+            self.data.clear();
+            self.pos = 0;
+            self.next = idx;
+            return Ok(target);
+        }
+        // Index-driven reads seek once per row, mostly within the block already inflated.
+        let loaded = idx + 1 == self.next && self.data.len() as u64 == self.blocks[idx].len;
+        if !loaded {
+            self.load(idx)?;
+        }
+        self.pos = (target - self.blocks[idx].start) as usize;
+        Ok(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn bgzf_output_streams_and_seeks_across_blocks() {
+        let text = (0..20_000)
+            .map(|n| format!("{n},row-{n}\n"))
+            .collect::<String>();
+        let mut compressed = Vec::new();
+        let mut writer = BgzfWriter::new(&mut compressed);
+        writer.write_all(text.as_bytes()).unwrap();
+        writer.finish().unwrap();
+        assert!(compressed.ends_with(&BGZF_EOF));
+
+        let mut streamed = String::new();
+        MultiGzDecoder::new(compressed.as_slice())
+            .read_to_string(&mut streamed)
+            .unwrap();
+        assert_eq!(streamed, text);
+
+        let mut reader = BgzfReader::new(Cursor::new(compressed)).unwrap();
+        assert!(reader.blocks.len() > 2);
+        let offset = text.find("15000,").unwrap() as u64;
+        reader.seek(SeekFrom::Start(offset)).unwrap();
+        let mut line = String::new();
+        BufReader::new(&mut reader).read_line(&mut line).unwrap();
+        assert_eq!(line, "15000,row-15000\n");
+        reader.seek(SeekFrom::Start(0)).unwrap();
+        let mut first = [0u8; 6];
+        reader.read_exact(&mut first).unwrap();
+        assert_eq!(&first, b"0,row-");
+        // Seeking within the loaded block does not reread it.
+        let inner = reader.inner.position();
+        reader.seek(SeekFrom::Start(2)).unwrap();
+        reader.read_exact(&mut first).unwrap();
+        assert_eq!(&first, b"row-0\n");
+        assert_eq!(reader.inner.position(), inner);
+
+        assert_eq!(
+            inner_path(Path::new("a/orders.tsv.gz")),
+            Path::new("a/orders.tsv")
+        );
+        assert_eq!(
+            Compression::from_path(Path::new("x.csv.ZST")),
+            Compression::Zstd
+        );
+        assert!(BgzfReader::new(Cursor::new(b"plain,text\n".to_vec())).is_err());
+    }
+}
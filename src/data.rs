@@ -339,6 +339,8 @@ pub fn parse_naive_datetime(value: &str) -> Result<NaiveDateTime> {
     const DATETIME_FORMATS: &[&str] = &[
         "%Y-%m-%d %H:%M:%S",
         "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M:%S%.f",
         "%d/%m/%Y %H:%M:%S",
         "%m/%d/%Y %H:%M:%S",
         "%Y-%m-%d %H:%M",
@@ -353,7 +355,7 @@ pub fn parse_naive_datetime(value: &str) -> Result<NaiveDateTime> {
 }
 
 pub fn parse_naive_time(value: &str) -> Result<NaiveTime> {
-    const TIME_FORMATS: &[&str] = &["%H:%M:%S", "%H:%M"];
+    const TIME_FORMATS: &[&str] = &["%H:%M:%S", "%H:%M:%S%.f", "%H:%M"];
     for fmt in TIME_FORMATS {
         if let Ok(parsed) = NaiveTime::parse_from_str(value, fmt) {
             return Ok(parsed);
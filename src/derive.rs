@@ -44,10 +44,7 @@ impl DerivedColumn {
         typed_row: &[Option<Value>],
         row_number: Option<usize>,
     ) -> Result<String> {
-        let context = expr::build_context(headers, raw_row, typed_row, row_number)?;
-
-        let result = eval_with_context(&self.expression, &context)
-            .with_context(|| format!("Evaluating expression for column '{}'", self.name))?;
+        let result = self.evaluate_value(headers, raw_row, typed_row, row_number)?;
         Ok(match result {
             EvalValue::String(s) => s,
             EvalValue::Int(i) => i.to_string(),
@@ -61,6 +58,20 @@ impl DerivedColumn {
             EvalValue::Empty => String::new(),
         })
     }
+
+    /// Evaluates the expression and returns the raw `evalexpr` result, for sinks
+    /// that keep numbers and booleans distinct from text.
+    pub fn evaluate_value(
+        &self,
+        headers: &[String],
+        raw_row: &[String],
+        typed_row: &[Option<Value>],
+        row_number: Option<usize>,
+    ) -> Result<EvalValue> {
+        let context = expr::build_context(headers, raw_row, typed_row, row_number)?;
+        eval_with_context(&self.expression, &context)
+            .with_context(|| format!("Evaluating expression for column '{}'", self.name))
+    }
 }
 
 pub fn parse_derived_columns(specs: &[String]) -> Result<Vec<DerivedColumn>> {
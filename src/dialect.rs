@@ -0,0 +1,379 @@
+//! CSV dialect: quoting, escapes, line terminators, comments, trimming, and BOMs.
+//!
+//! A schema may carry a `dialect:` block describing how its files are written:
+//!
+//! ```yaml
+//! dialect:
+//!   quote_style: necessary   # always (default), necessary, non-numeric, never
+//!   quote: "'"               # default '"'
+//!   escape: \                # default: quotes are doubled
+//!   terminator: crlf         # lf (default) or crlf
+//!   comment: '#'             # skip input lines starting with this character
+//!   trim: all                # none (default), headers, fields, all
+//!   bom: true                # start output with a byte-order mark
+//!   quoting: false           # treat input quotes as ordinary characters
+//! ```
+//!
+//! Readers honour the quote, escape, comment, trim, and quoting settings (any
+//! line ending is accepted, and a leading UTF-8 BOM is always skipped); writers
+//! honour the quote style, quote, escape, terminator, and BOM settings. The
+//! `--quote-style`, `--quote-char`, `--escape-char`, `--no-quoting`,
+//! `--line-terminator`, `--comment-char`, `--trim`, and `--bom` flags override
+//! the schema for one run. `schema infer` detects the dialect from the start of the file and
+//! records it when it differs from the defaults.
+
+use std::{io::Read, path::Path};
+
+use anyhow::{Result, ensure};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::io_utils;
+
+/// Bytes read from the start of a file when detecting its dialect.
+const DETECT_BYTES: u64 = 64 * 1024;
+
+/// When output fields are wrapped in quotes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+#[value(rename_all = "kebab-case")]
+pub enum QuoteStyle {
+    /// Quote every field.
+    #[default]
+    Always,
+    /// Quote only fields containing the delimiter, a quote, or a line break.
+    Necessary,
+    /// Quote every field that is not a number.
+    NonNumeric,
+    /// Never quote fields.
+    Never,
+}
+
+/// Line ending written after each output record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+#[value(rename_all = "kebab-case")]
+pub enum LineTerminator {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+/// Which input values have surrounding whitespace removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+#[value(rename_all = "kebab-case")]
+pub enum TrimMode {
+    #[default]
+    None,
+    Headers,
+    Fields,
+    All,
+}
+
+/// How a family of CSV files is quoted, escaped, and terminated.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Dialect {
+    #[serde(skip_serializing_if = "is_default")]
+    pub quote_style: QuoteStyle,
+    #[serde(skip_serializing_if = "is_true")]
+    pub quoting: bool,
+    #[serde(skip_serializing_if = "is_double_quote")]
+    pub quote: char,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub escape: Option<char>,
+    #[serde(skip_serializing_if = "is_default")]
+    pub terminator: LineTerminator,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<char>,
+    #[serde(skip_serializing_if = "is_default")]
+    pub trim: TrimMode,
+    #[serde(skip_serializing_if = "is_default")]
+    pub bom: bool,
+}
+
+impl Default for Dialect {
+    fn default() -> Self {
+        Self {
+            quote_style: QuoteStyle::Always,
+            quoting: true,
+            quote: '"',
+            escape: None,
+            terminator: LineTerminator::Lf,
+            comment: None,
+            trim: TrimMode::None,
+            bom: false,
+        }
+    }
+}
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+fn is_true(value: &bool) -> bool {
+    *value
+}
+
+fn is_double_quote(value: &char) -> bool {
+    *value == '"'
+}
+
+impl Dialect {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Checks that the quote, escape, and comment characters are single ASCII bytes.
+    pub fn validate(&self) -> Result<()> {
+        for (name, value) in [
+            ("quote", Some(self.quote)),
+            ("escape", self.escape),
+            ("comment", self.comment),
+        ] {
+            if let Some(value) = value {
+                ensure!(
+                    value.is_ascii(),
+                    "Dialect {name} character '{value}' must be ASCII"
+                );
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn reader_builder(&self, delimiter: u8, has_headers: bool) -> csv::ReaderBuilder {
+        let mut builder = csv::ReaderBuilder::new();
+        builder
+            .has_headers(has_headers)
+            .delimiter(delimiter)
+            .quoting(self.quoting)
+            .quote(self.quote as u8)
+            .double_quote(self.escape.is_none())
+            .escape(self.escape.map(|escape| escape as u8))
+            .comment(self.comment.map(|comment| comment as u8))
+            .trim(match self.trim {
+                TrimMode::None => csv::Trim::None,
+                TrimMode::Headers => csv::Trim::Headers,
+                TrimMode::Fields => csv::Trim::Fields,
+                TrimMode::All => csv::Trim::All,
+            })
+            .flexible(false);
+        builder
+    }
+
+    pub(crate) fn writer_builder(&self, delimiter: u8) -> csv::WriterBuilder {
+        let mut builder = csv::WriterBuilder::new();
+        builder
+            .delimiter(delimiter)
+            .quote_style(match self.quote_style {
+                QuoteStyle::Always => csv::QuoteStyle::Always,
+                QuoteStyle::Necessary => csv::QuoteStyle::Necessary,
+                QuoteStyle::NonNumeric => csv::QuoteStyle::NonNumeric,
+                QuoteStyle::Never => csv::QuoteStyle::Never,
+            })
+            .quote(self.quote as u8)
+            .double_quote(self.escape.is_none())
+            .escape(self.escape.map_or(b'\\', |escape| escape as u8))
+            .terminator(match self.terminator {
+                LineTerminator::Lf => csv::Terminator::Any(b'\n'),
+                LineTerminator::Crlf => csv::Terminator::CRLF,
+            });
+        builder
+    }
+}
+
+/// Detects the dialect of `path` from its first 64 KiB. Standard input is not
+/// sampled and gets the default dialect.
+pub fn detect(path: &Path, delimiter: u8) -> Result<Dialect> {
+    if io_utils::is_dash(path) {
+        return Ok(Dialect::default());
+    }
+    let mut sample = Vec::new();
+    io_utils::open_input_reader(path)?
+        .take(DETECT_BYTES)
+        .read_to_end(&mut sample)?;
+    let truncated = sample.len() as u64 == DETECT_BYTES;
+    Ok(detect_bytes(&sample, delimiter, truncated))
+}
+
+/// One raw field: its unquoted text and whether it was quoted.
+struct RawField {
+    text: Vec<u8>,
+    quoted: bool,
+}
+
+fn detect_bytes(sample: &[u8], delimiter: u8, truncated: bool) -> Dialect {
+    let mut dialect = Dialect::default();
+    let sample = match sample.strip_prefix(b"\xef\xbb\xbf") {
+        Some(rest) => {
+            dialect.bom = true;
+            rest
+        }
+        None => sample,
+    };
+    let (records, saw_doubled, saw_backslash) = split_records(sample, delimiter, truncated);
+    let Some(first_line) = records.first() else {
+        return dialect;
+    };
+    if first_line.1 {
+        dialect.terminator = LineTerminator::Crlf;
+    }
+    if saw_backslash && !saw_doubled {
+        dialect.escape = Some('\\');
+    }
+
+    // Lines starting with '#' that do not fit the table are comments.
+    let width = records
+        .iter()
+        .find(|(fields, _)| !fields[0].text.starts_with(b"#") || fields[0].quoted)
+        .map(|(fields, _)| fields.len());
+    let is_comment = |fields: &[RawField]| {
+        !fields[0].quoted && fields[0].text.starts_with(b"#") && Some(fields.len()) != width
+    };
+    if records.iter().any(|(fields, _)| is_comment(fields)) {
+        dialect.comment = Some('#');
+    }
+
+    let fields = records
+        .iter()
+        .filter(|(fields, _)| !is_comment(fields))
+        .flat_map(|(fields, _)| fields.iter().enumerate())
+        .filter(|(_, field)| field.quoted || !field.text.is_empty())
+        .collect::<Vec<_>>();
+    if fields.is_empty() {
+        return dialect;
+    }
+    let is_number = |field: &RawField| {
+        std::str::from_utf8(&field.text).is_ok_and(|text| text.trim().parse::<f64>().is_ok())
+    };
+    let all_quoted = fields.iter().all(|(_, field)| field.quoted);
+    let any_quoted = fields.iter().any(|(_, field)| field.quoted);
+    let quotes_exactly_text = fields
+        .iter()
+        .all(|(_, field)| field.quoted != is_number(field));
+    // A sample without quotes says nothing about them, so the default stays.
+    dialect.quote_style = if all_quoted || !any_quoted {
+        QuoteStyle::Always
+    } else if quotes_exactly_text {
+        QuoteStyle::NonNumeric
+    } else {
+        QuoteStyle::Necessary
+    };
+
+    // Padding after every delimiter (`a, b, c`) is trimmed away.
+    let mut later = fields
+        .iter()
+        .filter(|(idx, field)| *idx > 0 && !field.quoted)
+        .peekable();
+    if later.peek().is_some() && later.all(|(_, field)| field.text.starts_with(b" ")) {
+        dialect.trim = TrimMode::All;
+    }
+    dialect
+}
+
+/// Records of raw fields, each paired with whether it ended in CRLF.
+type RawRecords = Vec<(Vec<RawField>, bool)>;
+
+/// Splits `sample` into records and reports whether doubled quotes or
+/// backslash-escaped quotes appeared inside quoted fields. Blank lines are
+/// skipped, and a final partial record is dropped when the sample was cut short.
+fn split_records(sample: &[u8], delimiter: u8, truncated: bool) -> (RawRecords, bool, bool) {
+    let mut records = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = RawField {
+        text: Vec::new(),
+        quoted: false,
+    };
+    let mut in_quotes = false;
+    let mut saw_doubled = false;
+    let mut saw_backslash = false;
+    let mut idx = 0;
+    while idx < sample.len() {
+        let byte = sample[idx];
+        if in_quotes {
+            match byte {
+                b'"' if sample.get(idx + 1) == Some(&b'"') => {
+                    saw_doubled = true;
+                    field.text.push(b'"');
+                    idx += 1;
+                }
+                b'\\' if sample.get(idx + 1) == Some(&b'"') => {
+                    saw_backslash = true;
+                    field.text.push(b'"');
+                    idx += 1;
+                }
+                b'"' => in_quotes = false,
+                _ => field.text.push(byte),
+            }
+        } else if byte == b'"' && field.text.is_empty() && !field.quoted {
+            field.quoted = true;
+            in_quotes = true;
+        } else if byte == delimiter {
+            fields.push(std::mem::replace(
+                &mut field,
+                RawField {
+                    text: Vec::new(),
+                    quoted: false,
+                },
+            ));
+        } else if byte == b'\r' && sample.get(idx + 1) == Some(&b'\n') {
+            // Consumed with the line feed that follows.
+        } else if byte == b'\n' {
+            let crlf = idx > 0 && sample[idx - 1] == b'\r';
+            fields.push(std::mem::replace(
+                &mut field,
+                RawField {
+                    text: Vec::new(),
+                    quoted: false,
+                },
+            ));
+            if !(fields.len() == 1 && fields[0].text.is_empty() && !fields[0].quoted) {
+                records.push((std::mem::take(&mut fields), crlf));
+            }
+            fields.clear();
+        } else {
+            field.text.push(byte);
+        }
+        idx += 1;
+    }
+    if !truncated && (!fields.is_empty() || !field.text.is_empty() || field.quoted) {
+        fields.push(field);
+        records.push((fields, false));
+    }
+    (records, saw_doubled, saw_backslash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_quoting_terminators_comments_and_boms() {
+        let plain = detect_bytes(b"id,name\n1,alpha\n2,\"b,c\"\n", b',', false);
+        assert_eq!(plain.quote_style, QuoteStyle::Necessary);
+        assert_eq!(plain.terminator, LineTerminator::Lf);
+        assert!(detect_bytes(b"id,name\n1,alpha\n", b',', false).is_default());
+
+        let always = detect_bytes(b"\"id\",\"name\"\r\n\"1\",\"alpha\"\r\n", b',', false);
+        assert_eq!(always.quote_style, QuoteStyle::Always);
+        assert_eq!(always.terminator, LineTerminator::Crlf);
+
+        let non_numeric = detect_bytes(
+            b"\xef\xbb\xbf# exported 2024-03-01\n\"id\",\"name\"\n1,\"a \\\"b\\\"\"\n",
+            b',',
+            false,
+        );
+        assert_eq!(non_numeric.quote_style, QuoteStyle::NonNumeric);
+        assert_eq!(non_numeric.comment, Some('#'));
+        assert_eq!(non_numeric.escape, Some('\\'));
+        assert!(non_numeric.bom);
+
+        let padded = detect_bytes(b"id, name, qty\n1, alpha, 3\n", b',', false);
+        assert_eq!(padded.trim, TrimMode::All);
+
+        let yaml = serde_yaml::to_string(&non_numeric).unwrap();
+        assert_eq!(serde_yaml::from_str::<Dialect>(&yaml).unwrap(), non_numeric);
+        assert!(!yaml.contains("quoting"));
+    }
+}
@@ -0,0 +1,193 @@
+//! Typed de-duplication for `process --distinct` and `--distinct-on`.
+//!
+//! Keys are built from the parsed [`Value`](crate::data::Value)s of the key
+//! columns and compared as [`ComparableValue`]s, so `1.0` and `1.00` in a
+//! decimal column, or `0042` and `42` in an integer column, are duplicates.
+//!
+//! Two strategies are provided:
+//!
+//! - [`SeenKeys`] remembers every key in an ordered set and works on rows in any
+//!   order. Memory grows with the number of distinct keys (and, for
+//!   `--keep last`, with the retained rows).
+//! - [`AdjacentDistinct`] assumes rows with equal keys arrive consecutively, as
+//!   they do when reading through an index variant led by the key columns, and
+//!   holds only the current group's candidate row.
+//!
+//! Both pick the occurrence with the lowest (`first`) or highest (`last`) input
+//! position, recorded in [`RowData::ordinal`].
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{cli::DistinctKeep, data::ComparableValue, process::RowData};
+
+/// Key columns and retention rule for a distinct run.
+#[derive(Debug, Clone)]
+pub(crate) struct DistinctPlan {
+    pub(crate) columns: Vec<usize>,
+    pub(crate) keep: DistinctKeep,
+}
+
+impl DistinctPlan {
+    fn key(&self, row: &RowData) -> Vec<ComparableValue> {
+        self.columns
+            .iter()
+            .map(|idx| ComparableValue(row.typed.get(*idx).cloned().flatten()))
+            .collect()
+    }
+
+    fn prefers(&self, candidate: &RowData, current: &RowData) -> bool {
+        match self.keep {
+            DistinctKeep::First => candidate.ordinal < current.ordinal,
+            DistinctKeep::Last => candidate.ordinal > current.ordinal,
+        }
+    }
+}
+
+/// Order-independent de-duplication backed by an ordered set of seen keys.
+pub(crate) struct SeenKeys<'p> {
+    plan: &'p DistinctPlan,
+    seen: BTreeSet<Vec<ComparableValue>>,
+    latest: BTreeMap<Vec<ComparableValue>, RowData>,
+}
+
+impl<'p> SeenKeys<'p> {
+    pub(crate) fn new(plan: &'p DistinctPlan) -> Self {
+        Self {
+            plan,
+            seen: BTreeSet::new(),
+            latest: BTreeMap::new(),
+        }
+    }
+
+    /// Offers a row in input order.
+    ///
+    /// With `--keep first` the row is returned if its key is new. With
+    /// `--keep last` rows are retained internally and released by [`finish`](Self::finish).
+    pub(crate) fn offer(&mut self, row: RowData) -> Option<RowData> {
+        let key = self.plan.key(&row);
+        match self.plan.keep {
+            DistinctKeep::First => self.seen.insert(key).then_some(row),
+            DistinctKeep::Last => {
+                self.latest.insert(key, row);
+                None
+            }
+        }
+    }
+
+    /// Returns rows still held back, ordered by input position.
+    pub(crate) fn finish(self) -> Vec<RowData> {
+        let mut rows = self.latest.into_values().collect::<Vec<_>>();
+        rows.sort_by_key(|row| row.ordinal);
+        rows
+    }
+}
+
+/// De-duplication for streams where equal keys are adjacent.
+pub(crate) struct AdjacentDistinct<'p> {
+    plan: &'p DistinctPlan,
+    current: Option<(Vec<ComparableValue>, RowData)>,
+}
+
+impl<'p> AdjacentDistinct<'p> {
+    pub(crate) fn new(plan: &'p DistinctPlan) -> Self {
+        Self {
+            plan,
+            current: None,
+        }
+    }
+
+    /// Offers the next row of the stream, returning the previous group's
+    /// representative once a new key begins.
+    pub(crate) fn offer(&mut self, row: RowData) -> Option<RowData> {
+        let key = self.plan.key(&row);
+        match self.current.take() {
+            Some((current_key, current)) if current_key == key => {
+                let keep = if self.plan.prefers(&row, &current) {
+                    row
+                } else {
+                    current
+                };
+                self.current = Some((current_key, keep));
+                None
+            }
+            previous => {
+                self.current = Some((key, row));
+                previous.map(|(_, row)| row)
+            }
+        }
+    }
+
+    /// Returns the representative of the final group, if any.
+    pub(crate) fn finish(self) -> Option<RowData> {
+        self.current.map(|(_, row)| row)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::Value;
+
+    fn row(ordinal: usize, key: i64) -> RowData {
+        RowData {
+            raw: vec![key.to_string()],
+            typed: vec![Some(Value::Integer(key))],
+            ordinal,
+        }
+    }
+
+    fn plan(keep: DistinctKeep) -> DistinctPlan {
+        DistinctPlan {
+            columns: vec![0],
+            keep,
+        }
+    }
+
+    #[test]
+    fn seen_keys_keep_first_and_last_follow_input_position() {
+        let keys = [3, 1, 3, 2, 1, 3];
+
+        let first_plan = plan(DistinctKeep::First);
+        let mut first = SeenKeys::new(&first_plan);
+        let kept = keys
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, key)| first.offer(row(idx, *key)))
+            .map(|row| row.ordinal)
+            .collect::<Vec<_>>();
+        assert_eq!(kept, vec![0, 1, 3]);
+        assert!(first.finish().is_empty());
+
+        let last_plan = plan(DistinctKeep::Last);
+        let mut last = SeenKeys::new(&last_plan);
+        for (idx, key) in keys.iter().enumerate() {
+            assert!(last.offer(row(idx, *key)).is_none());
+        }
+        let kept = last
+            .finish()
+            .into_iter()
+            .map(|row| row.ordinal)
+            .collect::<Vec<_>>();
+        assert_eq!(kept, vec![3, 4, 5]);
+    }
+
+    #[test]
+    fn adjacent_groups_pick_by_position_not_arrival() {
+        // Grouped stream as an index would produce it: key 1 rows, then key 2 rows.
+        let stream = [(4, 1), (1, 1), (7, 1), (2, 2), (9, 2)];
+        for (keep, expected) in [
+            (DistinctKeep::First, vec![1, 2]),
+            (DistinctKeep::Last, vec![7, 9]),
+        ] {
+            let plan = plan(keep);
+            let mut distinct = AdjacentDistinct::new(&plan);
+            let mut kept = stream
+                .iter()
+                .filter_map(|(ordinal, key)| distinct.offer(row(*ordinal, *key)))
+                .map(|row| row.ordinal)
+                .collect::<Vec<_>>();
+            kept.extend(distinct.finish().map(|row| row.ordinal));
+            assert_eq!(kept, expected);
+        }
+    }
+}
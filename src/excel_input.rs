@@ -0,0 +1,259 @@
+//! Excel and OpenDocument worksheet inputs for `process`, `stats`,
+//! `schema probe/infer`, and `schema verify`.
+//!
+//! Files ending in `.xlsx`, `.xlsm`, `.xlsb`, `.xls`, or `.ods` are read one
+//! worksheet at a time: `--sheet` picks it by name (the first sheet by default)
+//! and `--range` narrows it to a block of cells such as `B2:F200` (or `A3:H` to
+//! run to the last used row). The first row of the range is the header row
+//! unless the schema says `has_headers: false`.
+//!
+//! Cells are rendered as text:
+//!
+//! - Text cells keep their exact text, including leading zeros.
+//! - Numbers drop Excel's binary noise (15 significant digits) and whole
+//!   numbers lose their `.0`, so `42` stays an Integer.
+//! - Date-formatted serial numbers become `2024-03-01` when they fall on a whole
+//!   day, `2024-03-01 08:30:00` otherwise, and `08:30:00` for time-only values.
+//! - Booleans become `true` / `false`; error cells keep their code (`#N/A`).
+
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow};
+use calamine::{Data, ExcelDateTime, Range, Reader, open_workbook_auto};
+use chrono::{Duration, NaiveDate, NaiveTime};
+use csv::ByteRecord;
+
+use crate::cli::InputFormatArgs;
+
+/// Returns true when `path` names a spreadsheet workbook.
+pub fn is_excel(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            ["xlsx", "xlsm", "xlsb", "xls", "ods"]
+                .iter()
+                .any(|known| ext.eq_ignore_ascii_case(known))
+        })
+}
+
+/// Opens the selected worksheet range. When `has_headers` is set its first row
+/// is returned as the column names and the remaining rows as records.
+pub(crate) fn open(
+    path: &Path,
+    options: &InputFormatArgs,
+    has_headers: bool,
+) -> Result<(Option<Vec<String>>, ExcelRecords)> {
+    let mut records = ExcelRecords {
+        range: load_range(path, options)?,
+        row: 0,
+    };
+    let headers = if has_headers {
+        let first = records.next().transpose()?.unwrap_or_default();
+        Some(
+            first
+                .iter()
+                .map(|field| String::from_utf8_lossy(field).into_owned())
+                .collect(),
+        )
+    } else {
+        None
+    };
+    Ok((headers, records))
+}
+
+/// Number of columns in the selected worksheet range.
+pub(crate) fn width(path: &Path, options: &InputFormatArgs) -> Result<usize> {
+    Ok(load_range(path, options)?.width())
+}
+
+/// Rows of a worksheet range as UTF-8 byte records.
+pub(crate) struct ExcelRecords {
+    range: Range<Data>,
+    row: usize,
+}
+
+impl Iterator for ExcelRecords {
+    type Item = Result<ByteRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.row >= self.range.height() {
+            return None;
+        }
+        let row = self.row;
+        self.row += 1;
+        let record = (0..self.range.width())
+            .map(|col| {
+                self.range
+                    .get((row, col))
+                    .map(cell_text)
+                    .unwrap_or_default()
+            })
+            .collect::<Vec<_>>();
+        Some(Ok(record.iter().collect()))
+    }
+}
+
+fn load_range(path: &Path, options: &InputFormatArgs) -> Result<Range<Data>> {
+    let mut workbook =
+        open_workbook_auto(path).with_context(|| format!("Opening workbook {path:?}"))?;
+    let names = workbook.sheet_names();
+    let sheet = match &options.sheet {
+        Some(sheet) => names.iter().find(|name| *name == sheet).ok_or_else(|| {
+            anyhow!(
+                "Worksheet '{sheet}' not found in {path:?}; available sheets: {}",
+                names.join(", ")
+            )
+        })?,
+        None => names
+            .first()
+            .ok_or_else(|| anyhow!("Workbook {path:?} has no worksheets"))?,
+    }
+    .clone();
+    let used = workbook
+        .worksheet_range(&sheet)
+        .with_context(|| format!("Reading worksheet '{sheet}' from {path:?}"))?;
+    let Some(spec) = options.range.as_deref() else {
+        return Ok(used);
+    };
+    let ((start_row, start_col), (end_row, end_col)) = parse_range(spec)?;
+    let last_row = used.end().map_or(start_row, |(row, _)| row.max(start_row));
+    Ok(used.range(
+        (start_row, start_col),
+        (end_row.unwrap_or(last_row), end_col),
+    ))
+}
+
+/// Zero-based `(row, column)` of a cell reference; the row is absent for a bare column.
+type CellRef = (Option<u32>, u32);
+
+/// Parses an A1-style range into zero-based `(row, column)` corners. The end
+/// row may be omitted (`A3:H`) to read to the last used row.
+fn parse_range(spec: &str) -> Result<((u32, u32), CellRef)> {
+    let invalid = || anyhow!("Invalid --range '{spec}'; expected a block such as B2:F200 or A3:H");
+    let (start, end) = spec.trim().split_once(':').ok_or_else(invalid)?;
+    let (Some(start_row), start_col) = parse_cell(start).ok_or_else(invalid)? else {
+        return Err(invalid());
+    };
+    let (end_row, end_col) = parse_cell(end).ok_or_else(invalid)?;
+    if end_col < start_col || end_row.is_some_and(|row| row < start_row) {
+        return Err(invalid());
+    }
+    Ok(((start_row, start_col), (end_row, end_col)))
+}
+
+/// Parses a cell reference such as `C12` (or a bare column such as `C`).
+fn parse_cell(cell: &str) -> Option<CellRef> {
+    let cell = cell.trim().to_ascii_uppercase();
+    let digits = cell
+        .find(|c: char| c.is_ascii_digit())
+        .unwrap_or(cell.len());
+    let (letters, number) = cell.split_at(digits);
+    if letters.is_empty() || !letters.chars().all(|c| c.is_ascii_uppercase()) {
+        return None;
+    }
+    let col = letters.chars().try_fold(0u32, |acc, c| {
+        acc.checked_mul(26)?.checked_add(c as u32 - 'A' as u32 + 1)
+    })? - 1;
+    let row = if number.is_empty() {
+        None
+    } else {
+        Some(number.parse::<u32>().ok()?.checked_sub(1)?)
+    };
+    Some((row, col))
+}
+
+fn cell_text(cell: &Data) -> String {
+    match cell {
+        Data::Empty => String::new(),
+        Data::String(text) => text.clone(),
+        Data::Int(value) => value.to_string(),
+        Data::Float(value) => number_text(*value),
+        Data::Bool(flag) => flag.to_string(),
+        Data::DateTime(value) => datetime_text(value),
+        Data::DateTimeIso(text) | Data::DurationIso(text) => text.clone(),
+        Data::Error(err) => err.to_string(),
+    }
+}
+
+/// Renders an Excel number with at most 15 significant digits, the precision
+/// Excel itself displays, so `0.1 + 0.2` reads back as `0.3`.
+fn number_text(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        return format!("{}", value as i64);
+    }
+    format!("{value:.14e}")
+        .parse::<f64>()
+        .unwrap_or(value)
+        .to_string()
+}
+
+fn datetime_text(value: &ExcelDateTime) -> String {
+    const MILLIS_PER_DAY: i64 = 86_400_000;
+    let serial = value.as_f64();
+    let millis = (serial * MILLIS_PER_DAY as f64).round() as i64;
+    if value.is_duration() {
+        let seconds = millis / 1000;
+        return format!(
+            "{:02}:{:02}:{:02}",
+            seconds / 3600,
+            seconds % 3600 / 60,
+            seconds % 60
+        );
+    }
+    // The time of day comes from the serial rounded to whole milliseconds;
+    // only the calendar date is taken from calamine.
+    let time = NaiveTime::MIN + Duration::milliseconds(millis.rem_euclid(MILLIS_PER_DAY));
+    let time_text = time.format("%H:%M:%S%.3f").to_string();
+    let time_text = time_text.trim_end_matches(".000");
+    if millis > 0 && millis < MILLIS_PER_DAY {
+        return time_text.to_string();
+    }
+    let (year, month, day, ..) = value.to_ymd_hms_milli();
+    let mut date = format!("{year:04}-{month:02}-{day:02}");
+    if time == NaiveTime::MIN && serial.fract() != 0.0 {
+        // Rounded up to the following midnight.
+        if let Some(next) = NaiveDate::from_ymd_opt(year.into(), month.into(), day.into())
+            .and_then(|date| date.succ_opt())
+        {
+            date = next.format("%Y-%m-%d").to_string();
+        }
+    }
+    if time == NaiveTime::MIN {
+        date
+    } else {
+        format!("{date} {time_text}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use calamine::ExcelDateTimeType;
+
+    use super::*;
+
+    #[test]
+    fn cells_render_in_csv_text_formats() {
+        let date = |serial| {
+            datetime_text(&ExcelDateTime::new(
+                serial,
+                ExcelDateTimeType::DateTime,
+                false,
+            ))
+        };
+        assert_eq!(date(45352.0), "2024-03-01");
+        assert_eq!(date(45352.5), "2024-03-01 12:00:00");
+        assert_eq!(date(0.25), "06:00:00");
+        assert_eq!(date(45352.354166666664), "2024-03-01 08:30:00");
+        assert_eq!(date(45352.999999999), "2024-03-02");
+        assert_eq!(number_text(42.0), "42");
+        assert_eq!(number_text(0.1 + 0.2), "0.3");
+        assert_eq!(number_text(12.5), "12.5");
+        assert_eq!(cell_text(&Data::String("00123".into())), "00123");
+
+        assert_eq!(parse_range("B2:F200").unwrap(), ((1, 1), (Some(199), 5)));
+        assert_eq!(parse_range("a3:h").unwrap(), ((2, 0), (None, 7)));
+        assert_eq!(parse_cell("AA10"), Some((Some(9), 26)));
+        assert!(parse_range("F2:B9").is_err());
+        assert!(parse_range("B:F").is_err());
+    }
+}
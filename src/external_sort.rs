@@ -0,0 +1,339 @@
+//! Bounded-memory external merge sort for `process --sort`.
+//!
+//! Rows are buffered until their estimated in-memory footprint reaches the
+//! configured `--memory-limit`. The buffer is then sorted with the same
+//! [`compare_rows`] ordering used by the in-memory path and spilled to an
+//! anonymous temporary file as a *run*. Once the input is exhausted the runs are
+//! combined with a k-way heap merge. Because every row carries its input
+//! `ordinal` as the final tie-breaker, the merged output is identical to a
+//! single in-memory stable sort.
+//!
+//! ## Complexity
+//!
+//! Run generation is O(n log r) where r is the number of rows per run; the merge
+//! is O(n log k) for k runs. When more than [`MERGE_FAN_IN`] runs exist they are
+//! merged in passes so the number of simultaneously open files stays bounded.
+
+use std::{
+    cmp::Ordering,
+    collections::BinaryHeap,
+    fs::File,
+    io::{BufReader, BufWriter, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use log::{debug, info};
+
+use crate::process::{RowData, SortInstruction, compare_rows};
+
+/// Maximum number of runs merged at once before an intermediate merge pass is used.
+pub(crate) const MERGE_FAN_IN: usize = 64;
+
+/// Accumulates rows and spills sorted runs to disk once the memory budget is exceeded.
+pub(crate) struct ExternalSorter<'p> {
+    plan: &'p [SortInstruction],
+    memory_limit: usize,
+    spill_dir: Option<PathBuf>,
+    buffer: Vec<RowData>,
+    buffered_bytes: usize,
+    runs: Vec<SortedRun>,
+}
+
+impl<'p> ExternalSorter<'p> {
+    /// Creates a sorter that keeps at most roughly `memory_limit` bytes of rows in memory.
+    pub(crate) fn new(
+        plan: &'p [SortInstruction],
+        memory_limit: usize,
+        spill_dir: Option<&Path>,
+    ) -> Self {
+        Self {
+            plan,
+            memory_limit,
+            spill_dir: spill_dir.map(Path::to_path_buf),
+            buffer: Vec::new(),
+            buffered_bytes: 0,
+            runs: Vec::new(),
+        }
+    }
+
+    /// Adds a row, spilling the current buffer to a sorted run when the budget is reached.
+    pub(crate) fn push(&mut self, row: RowData) -> Result<()> {
+        self.buffered_bytes += row.estimated_size();
+        self.buffer.push(row);
+        if self.buffered_bytes >= self.memory_limit {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    /// Completes the sort and returns the rows in final order.
+    ///
+    /// When nothing was spilled the buffer is sorted in memory and returned
+    /// directly; otherwise the remaining buffer becomes the last run and all
+    /// runs are merged.
+    pub(crate) fn finish(mut self) -> Result<SortedRows<'p>> {
+        if self.runs.is_empty() {
+            let plan = self.plan;
+            self.buffer.sort_by(|a, b| compare_rows(a, b, plan));
+            return Ok(SortedRows::InMemory(self.buffer.into_iter()));
+        }
+        if !self.buffer.is_empty() {
+            self.spill()?;
+        }
+        info!(
+            "External sort spilled {} run(s) to temporary storage",
+            self.runs.len()
+        );
+        while self.runs.len() > MERGE_FAN_IN {
+            let batch = self.runs.drain(..MERGE_FAN_IN).collect::<Vec<_>>();
+            let mut merged = RunMerge::new(batch, self.plan)?;
+            let mut writer = RunWriter::create(self.spill_dir.as_deref())?;
+            while let Some(row) = merged.next_row()? {
+                writer.write(&row)?;
+            }
+            self.runs.push(writer.finish()?);
+            debug!(
+                "Intermediate merge pass reduced pending runs to {}",
+                self.runs.len()
+            );
+        }
+        Ok(SortedRows::Merged(RunMerge::new(self.runs, self.plan)?))
+    }
+
+    fn spill(&mut self) -> Result<()> {
+        let plan = self.plan;
+        self.buffer.sort_by(|a, b| compare_rows(a, b, plan));
+        let mut writer = RunWriter::create(self.spill_dir.as_deref())?;
+        for row in self.buffer.drain(..) {
+            writer.write(&row)?;
+        }
+        let run = writer.finish()?;
+        debug!(
+            "Spilled sorted run {} with {} row(s) (~{} byte(s) buffered)",
+            self.runs.len() + 1,
+            run.remaining,
+            self.buffered_bytes
+        );
+        self.runs.push(run);
+        self.buffered_bytes = 0;
+        Ok(())
+    }
+}
+
+/// Rows produced by [`ExternalSorter::finish`], either from memory or from a run merge.
+pub(crate) enum SortedRows<'p> {
+    InMemory(std::vec::IntoIter<RowData>),
+    Merged(RunMerge<'p>),
+}
+
+impl Iterator for SortedRows<'_> {
+    type Item = Result<RowData>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            SortedRows::InMemory(rows) => rows.next().map(Ok),
+            SortedRows::Merged(merge) => merge.next_row().transpose(),
+        }
+    }
+}
+
+struct SortedRun {
+    reader: BufReader<File>,
+    remaining: usize,
+}
+
+impl SortedRun {
+    fn read_next(&mut self) -> Result<Option<RowData>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        let row: RowData =
+            bincode::serde::decode_from_std_read(&mut self.reader, bincode::config::legacy())
+                .context("Reading row from sort spill file")?;
+        self.remaining -= 1;
+        Ok(Some(row))
+    }
+}
+
+struct RunWriter {
+    writer: BufWriter<File>,
+    rows: usize,
+}
+
+impl RunWriter {
+    fn create(dir: Option<&Path>) -> Result<Self> {
+        let file = match dir {
+            Some(dir) => tempfile::tempfile_in(dir)
+                .with_context(|| format!("Creating sort spill file in {dir:?}"))?,
+            None => tempfile::tempfile().context("Creating sort spill file")?,
+        };
+        Ok(Self {
+            writer: BufWriter::new(file),
+            rows: 0,
+        })
+    }
+
+    fn write(&mut self, row: &RowData) -> Result<()> {
+        bincode::serde::encode_into_std_write(row, &mut self.writer, bincode::config::legacy())
+            .context("Writing row to sort spill file")?;
+        self.rows += 1;
+        Ok(())
+    }
+
+    fn finish(self) -> Result<SortedRun> {
+        let mut file = self
+            .writer
+            .into_inner()
+            .map_err(|err| err.into_error())
+            .context("Flushing sort spill file")?;
+        file.flush().context("Flushing sort spill file")?;
+        file.seek(SeekFrom::Start(0))
+            .context("Rewinding sort spill file")?;
+        Ok(SortedRun {
+            reader: BufReader::new(file),
+            remaining: self.rows,
+        })
+    }
+}
+
+/// K-way merge over sorted runs using a binary heap keyed by [`compare_rows`].
+pub(crate) struct RunMerge<'p> {
+    runs: Vec<SortedRun>,
+    heap: BinaryHeap<MergeHead<'p>>,
+}
+
+impl<'p> RunMerge<'p> {
+    fn new(mut runs: Vec<SortedRun>, plan: &'p [SortInstruction]) -> Result<Self> {
+        let mut heap = BinaryHeap::with_capacity(runs.len());
+        for (run, source) in runs.iter_mut().enumerate() {
+            if let Some(row) = source.read_next()? {
+                heap.push(MergeHead { row, run, plan });
+            }
+        }
+        Ok(Self { runs, heap })
+    }
+
+    fn next_row(&mut self) -> Result<Option<RowData>> {
+        let Some(head) = self.heap.pop() else {
+            return Ok(None);
+        };
+        if let Some(row) = self.runs[head.run].read_next()? {
+            self.heap.push(MergeHead {
+                row,
+                run: head.run,
+                plan: head.plan,
+            });
+        }
+        Ok(Some(head.row))
+    }
+}
+
+struct MergeHead<'p> {
+    row: RowData,
+    run: usize,
+    plan: &'p [SortInstruction],
+}
+
+impl PartialEq for MergeHead<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for MergeHead<'_> {}
+
+impl PartialOrd for MergeHead<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for MergeHead<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // BinaryHeap is a max-heap; reverse so the smallest row is popped first.
+        compare_rows(&other.row, &self.row, self.plan)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::Value;
+
+    fn row(ordinal: usize, key: i64, label: &str) -> RowData {
+        RowData {
+            raw: vec![key.to_string(), label.to_string()],
+            typed: vec![
+                Some(Value::Integer(key)),
+                Some(Value::String(label.to_string())),
+            ],
+            ordinal,
+        }
+    }
+
+    fn sample_rows() -> Vec<RowData> {
+        (0..500)
+            .map(|idx| row(idx, ((idx * 37) % 11) as i64, &format!("row-{idx}")))
+            .collect()
+    }
+
+    fn ordinals(rows: impl Iterator<Item = Result<RowData>>) -> Vec<usize> {
+        rows.map(|row| row.expect("sorted row").ordinal).collect()
+    }
+
+    #[test]
+    fn spilled_sort_matches_in_memory_sort() {
+        let plan = vec![SortInstruction {
+            index: 0,
+            ascending: false,
+        }];
+        let mut expected = sample_rows();
+        expected.sort_by(|a, b| compare_rows(a, b, &plan));
+        let expected = expected.into_iter().map(|r| r.ordinal).collect::<Vec<_>>();
+
+        let mut sorter = ExternalSorter::new(&plan, 2_048, None);
+        for row in sample_rows() {
+            sorter.push(row).expect("push row");
+        }
+        assert!(sorter.runs.len() > 1, "tiny budget should spill runs");
+        let actual = ordinals(sorter.finish().expect("finish sort"));
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn unspilled_sort_stays_in_memory() {
+        let plan = vec![SortInstruction {
+            index: 0,
+            ascending: true,
+        }];
+        let mut sorter = ExternalSorter::new(&plan, usize::MAX, None);
+        for row in sample_rows() {
+            sorter.push(row).expect("push row");
+        }
+        assert_eq!(sorter.runs.len(), 0);
+        let sorted = sorter.finish().expect("finish sort");
+        assert!(matches!(sorted, SortedRows::InMemory(_)));
+        assert_eq!(ordinals(sorted).len(), 500);
+    }
+
+    #[test]
+    fn merge_passes_bound_open_runs() {
+        let plan = vec![SortInstruction {
+            index: 0,
+            ascending: true,
+        }];
+        let mut expected = sample_rows();
+        expected.sort_by(|a, b| compare_rows(a, b, &plan));
+        let expected = expected.into_iter().map(|r| r.ordinal).collect::<Vec<_>>();
+
+        // A budget of one byte spills every row into its own run.
+        let mut sorter = ExternalSorter::new(&plan, 1, None);
+        for row in sample_rows() {
+            sorter.push(row).expect("push row");
+        }
+        assert!(sorter.runs.len() > MERGE_FAN_IN);
+        let actual = ordinals(sorter.finish().expect("finish sort"));
+        assert_eq!(actual, expected);
+    }
+}
@@ -0,0 +1,282 @@
+//! Fixed-width text inputs driven by schema column positions.
+//!
+//! A schema whose columns declare `width` (and optionally `start`) describes a
+//! fixed-width file: each line is one record and each column is the byte slice
+//! `start .. start + width`, counted from 1. A column without `start` begins
+//! where the previous one ends, so a schema listing widths alone lays the
+//! columns out back to back.
+//!
+//! Slices are trimmed of padding spaces but otherwise kept as-is, leading zeros
+//! included. Lines shorter than the layout leave the missing columns empty and
+//! blank lines are skipped. When the schema says
+//! `has_headers: true` the first line is sliced the same way and checked against
+//! the column names.
+//!
+//! `schema infer --fixed-width` suggests a layout by looking for byte positions
+//! that are blank on every sampled line.
+
+use std::{
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+use anyhow::{Context, Result, anyhow, bail, ensure};
+use csv::ByteRecord;
+use encoding_rs::Encoding;
+
+use crate::{
+    io_utils,
+    schema::{self, ColumnMeta, ColumnType, Schema},
+};
+
+/// Byte ranges of each column, zero-based with exclusive ends.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct FixedWidthLayout {
+    ranges: Vec<(usize, usize)>,
+}
+
+impl FixedWidthLayout {
+    /// Builds the layout declared by `schema`, or `None` when no column has a
+    /// `start` or `width`.
+    pub(crate) fn from_schema(schema: &Schema) -> Result<Option<Self>> {
+        if schema
+            .columns
+            .iter()
+            .all(|column| column.start.is_none() && column.width.is_none())
+        {
+            return Ok(None);
+        }
+        let mut ranges = Vec::with_capacity(schema.columns.len());
+        let mut next = 0usize;
+        for column in &schema.columns {
+            let width = column.width.ok_or_else(|| {
+                anyhow!(
+                    "Column '{}' needs a width: every column of a fixed-width schema must declare one",
+                    column.name
+                )
+            })?;
+            ensure!(width > 0, "Column '{}' has a zero width", column.name);
+            let start = match column.start {
+                Some(0) => bail!(
+                    "Column '{}' has start 0; fixed-width positions count from 1",
+                    column.name
+                ),
+                Some(start) => start - 1,
+                None => next,
+            };
+            next = start + width;
+            ranges.push((start, next));
+        }
+        Ok(Some(Self { ranges }))
+    }
+
+    fn slice(&self, line: &[u8]) -> ByteRecord {
+        self.ranges
+            .iter()
+            .map(|&(start, end)| {
+                let field = &line[start.min(line.len())..end.min(line.len())];
+                field.trim_ascii()
+            })
+            .collect()
+    }
+}
+
+/// Opens `path` (stdin when `-`) as fixed-width records. When `has_headers` is
+/// set the first line is returned, decoded, as the column names.
+pub(crate) fn open(
+    path: &Path,
+    layout: FixedWidthLayout,
+    has_headers: bool,
+    encoding: &'static Encoding,
+) -> Result<(Option<Vec<String>>, FixedWidthRecords)> {
+    let mut records = FixedWidthRecords {
+        lines: Lines::open(path)?,
+        layout,
+    };
+    let headers = if has_headers {
+        match records.next().transpose()? {
+            Some(record) => Some(
+                io_utils::decode_record(&record, encoding)
+                    .with_context(|| format!("Decoding header line of {path:?}"))?,
+            ),
+            None => Some(Vec::new()),
+        }
+    } else {
+        None
+    };
+    Ok((headers, records))
+}
+
+/// Lines of a fixed-width input sliced into byte records.
+pub(crate) struct FixedWidthRecords {
+    lines: Lines,
+    layout: FixedWidthLayout,
+}
+
+impl Iterator for FixedWidthRecords {
+    type Item = Result<ByteRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let line = self.lines.next()?;
+        Some(line.map(|line| self.layout.slice(&line)))
+    }
+}
+
+/// Non-blank lines of a file without their line endings.
+struct Lines {
+    reader: Box<dyn BufRead>,
+    number: usize,
+}
+
+impl Lines {
+    fn open(path: &Path) -> Result<Self> {
+        let reader = Box::new(BufReader::new(io_utils::open_input_reader(path)?));
+        Ok(Self { reader, number: 0 })
+    }
+}
+
+impl Iterator for Lines {
+    type Item = Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let mut line = Vec::new();
+            self.number += 1;
+            match self.reader.read_until(b'\n', &mut line) {
+                Ok(0) => return None,
+                Ok(_) => {}
+                Err(err) => {
+                    return Some(Err(
+                        anyhow!(err).context(format!("Reading line {}", self.number))
+                    ));
+                }
+            }
+            while matches!(line.last(), Some(b'\n' | b'\r')) {
+                line.pop();
+            }
+            if !line.trim_ascii().is_empty() {
+                return Some(Ok(line));
+            }
+        }
+    }
+}
+
+/// Suggests a fixed-width layout for `path` from up to `sample_rows` lines (all
+/// when 0). Every column is typed `String`; callers refine the types from the
+/// returned data lines, sliced with the suggested layout, so the input is read once.
+pub(crate) fn suggest_schema(
+    path: &Path,
+    sample_rows: usize,
+    encoding: &'static Encoding,
+    header_override: Option<bool>,
+) -> Result<(Schema, Vec<ByteRecord>)> {
+    let limit = if sample_rows == 0 {
+        usize::MAX
+    } else {
+        sample_rows.saturating_add(1)
+    };
+    let lines = Lines::open(path)?.take(limit).collect::<Result<Vec<_>>>()?;
+    ensure!(
+        !lines.is_empty(),
+        "Cannot suggest fixed-width columns for {path:?}: it has no data lines"
+    );
+    let layout = FixedWidthLayout {
+        ranges: suggest_ranges(&lines),
+    };
+    let decode = |line: &[u8]| io_utils::decode_record(&layout.slice(line), encoding);
+    let first = decode(&lines[0])?;
+    let has_headers = match header_override {
+        Some(flag) => flag,
+        None => {
+            let others = lines[1..]
+                .iter()
+                .map(|line| decode(line))
+                .collect::<Result<Vec<_>>>()?;
+            schema::infer_has_header(&first, &others)
+        }
+    };
+    let generated = schema::generate_field_names(layout.ranges.len());
+    let columns = layout
+        .ranges
+        .iter()
+        .enumerate()
+        .map(|(idx, &(start, end))| {
+            let name = first
+                .get(idx)
+                .filter(|name| has_headers && !name.is_empty())
+                .unwrap_or(&generated[idx]);
+            ColumnMeta {
+                name: name.clone(),
+                datatype: ColumnType::String,
+                rename: None,
+                value_replacements: Vec::new(),
+                datatype_mappings: Vec::new(),
+                lookup: None,
+                start: Some(start + 1),
+                width: Some(end - start),
+            }
+        })
+        .collect();
+    let records = lines
+        .iter()
+        .skip(usize::from(has_headers))
+        .map(|line| layout.slice(line))
+        .collect();
+    let schema = Schema {
+        columns,
+        schema_version: None,
+        has_headers,
+        dialect: None,
+    };
+    Ok((schema, records))
+}
+
+/// Splits lines into columns at each run of characters that follows a byte
+/// position blank on every line. Each column runs up to the start of the next
+/// and the last one to the end of the longest line.
+fn suggest_ranges(lines: &[Vec<u8>]) -> Vec<(usize, usize)> {
+    let max_len = lines.iter().map(Vec::len).max().unwrap_or(0);
+    let blank = (0..max_len)
+        .map(|pos| {
+            lines
+                .iter()
+                .all(|line| line.get(pos).is_none_or(|byte| *byte == b' '))
+        })
+        .collect::<Vec<_>>();
+    let starts = (0..max_len)
+        .filter(|&pos| !blank[pos] && (pos == 0 || blank[pos - 1]))
+        .collect::<Vec<_>>();
+    starts
+        .iter()
+        .enumerate()
+        .map(|(idx, &start)| (start, starts.get(idx + 1).copied().unwrap_or(max_len)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suggested_ranges_follow_blank_columns_and_slices_trim_padding() {
+        let lines = [
+            b"id    name         amount".to_vec(),
+            b"00123 Bolt Set      12.50".to_vec(),
+            b"00007 Gadget Pro     3.00".to_vec(),
+        ];
+        let ranges = suggest_ranges(&lines);
+        assert_eq!(ranges, vec![(0, 6), (6, 19), (19, 25)]);
+
+        let layout = FixedWidthLayout { ranges };
+        let record = layout.slice(&lines[2]);
+        assert_eq!(
+            record.iter().collect::<Vec<_>>(),
+            vec![&b"00007"[..], b"Gadget Pro", b"3.00"]
+        );
+        let short = layout.slice(b"00009 Bolt");
+        assert_eq!(
+            short.iter().collect::<Vec<_>>(),
+            vec![&b"00009"[..], b"Bolt", b""]
+        );
+    }
+}
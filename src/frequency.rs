@@ -16,9 +16,11 @@ use anyhow::{Context, Result};
 use encoding_rs::Encoding;
 
 use crate::{
+    cli::InputFormatArgs,
     data::Value,
     filter::{FilterCondition, evaluate_conditions},
     io_utils,
+    malformed::MalformedRows,
     rows::{evaluate_filter_expressions, parse_typed_row},
     schema::{self, Schema},
 };
@@ -29,6 +31,8 @@ pub struct FrequencyOptions<'a> {
     pub row_limit: Option<usize>,
     pub filters: &'a [FilterCondition],
     pub filter_exprs: &'a [String],
+    pub input_format: &'a InputFormatArgs,
+    pub(crate) malformed: Option<&'a MalformedRows>,
 }
 
 /// Streams a CSV file and returns per-column distinct-value frequency counts as printable rows.
@@ -40,22 +44,28 @@ pub fn compute_frequency_rows(
     columns: &[usize],
     options: &FrequencyOptions,
 ) -> Result<Vec<Vec<String>>> {
-    let expects_headers = schema.expects_headers();
-    let mut reader = io_utils::open_csv_reader_from_path(input, delimiter, expects_headers)?;
-    let headers = if expects_headers {
-        let headers = io_utils::reader_headers(&mut reader, encoding)?;
-        schema
-            .validate_headers(&headers)
-            .with_context(|| format!("Validating headers for {input:?}", input = input))?;
-        headers
-    } else {
-        schema.headers()
+    let (read_headers, records) = io_utils::open_schema_records(
+        input,
+        schema,
+        delimiter,
+        encoding,
+        options.input_format,
+        options.malformed,
+    )?;
+    let headers = match read_headers {
+        Some(headers) => {
+            schema
+                .validate_headers(&headers)
+                .with_context(|| format!("Validating headers for {input:?}", input = input))?;
+            headers
+        }
+        None => schema.headers(),
     };
     let header_aliases = schema.header_alias_sets();
 
     let mut stats = FrequencyAccumulator::new(columns, schema);
 
-    for (row_idx, record) in reader.byte_records().enumerate() {
+    for (row_idx, record) in records.enumerate() {
         if let Some(limit) = options.row_limit
             && row_idx >= limit
         {
@@ -243,8 +253,13 @@ mod tests {
             crate::schema::infer_schema(&path, 200, delimiter, UTF_8, None).expect("infer schema");
         let column_index = schema.column_index(GOALS_COL).expect("column index");
         let mut accumulator = FrequencyAccumulator::new(&[column_index], &schema);
-        let mut reader =
-            crate::io_utils::open_csv_reader_from_path(&path, delimiter, true).expect("open csv");
+        let mut reader = crate::io_utils::open_csv_reader_from_path(
+            &path,
+            delimiter,
+            true,
+            &crate::dialect::Dialect::default(),
+        )
+        .expect("open csv");
         crate::io_utils::reader_headers(&mut reader, UTF_8).expect("headers");
 
         for (idx, record) in reader.byte_records().enumerate() {
@@ -5,25 +5,47 @@
 //! named variants, covering-index expansion, per-column sort direction, versioned
 //! binary serialization via `bincode`, and longest-prefix best-match selection.
 //!
+//! Each index records the size, modification time, and a content digest of its
+//! source file (see [`SourceFingerprint`]) so callers can detect a CSV that
+//! changed after indexing and rebuild instead of seeking to stale offsets. Files
+//! that only grew can instead be brought up to date with [`CsvIndex::update`].
+//!
 //! # Complexity
 //!
 //! Index build is O(n log n) per variant where n is the row count. Variant
 //! selection and ordered-offset iteration are O(v) and O(n) respectively.
 
-use std::{borrow::Cow, collections::BTreeMap, fs::File, io::BufWriter, path::Path};
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    fs::File,
+    io::{BufWriter, Read, Seek, SeekFrom},
+    path::Path,
+    time::SystemTime,
+};
 
-use anyhow::{Context, Result, anyhow};
+use anyhow::{Context, Result, anyhow, bail};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 use crate::{
+    compression::Compression,
     data::{ComparableValue, parse_typed_value},
+    dialect::Dialect,
     io_utils,
     schema::{ColumnMeta, ColumnType, Schema},
 };
 
 use encoding_rs::Encoding;
 
-const INDEX_VERSION: u32 = 2;
+const INDEX_VERSION: u32 = 3;
+
+/// Leading bytes always hashed into a [`SourceFingerprint`]; they hold the header row.
+const FINGERPRINT_HEAD: u64 = 64 * 1024;
+/// Size of each block sampled after the head.
+const FINGERPRINT_BLOCK: u64 = 4 * 1024;
+/// Number of evenly spaced blocks sampled after the head, the last ending at the end of the file.
+const FINGERPRINT_SAMPLES: u64 = 16;
 
 /// Sort order for an indexed column — ascending or descending.
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
@@ -293,6 +315,91 @@ pub struct CsvIndex {
     headers: Vec<String>,
     variants: Vec<IndexVariant>,
     row_count: usize,
+    /// `None` for indexes migrated from versions that did not record their source.
+    source: Option<SourceFingerprint>,
+}
+
+/// The size, modification time, and content digest of an indexed file.
+///
+/// The digest covers the first 64 KiB plus sixteen evenly spaced 4 KiB blocks,
+/// so it stays cheap on large files; the modification time catches edits that
+/// fall between the sampled blocks.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceFingerprint {
+    size: u64,
+    modified: Option<SystemTime>,
+    digest: String,
+}
+
+impl SourceFingerprint {
+    /// Fingerprints `path` as it is now.
+    pub fn of(path: &Path) -> Result<Self> {
+        let metadata =
+            std::fs::metadata(path).with_context(|| format!("Reading metadata of {path:?}"))?;
+        let size = metadata.len();
+        Ok(Self {
+            size,
+            modified: metadata.modified().ok(),
+            digest: digest_prefix(path, size)?,
+        })
+    }
+
+    /// Describes how `current` differs from this fingerprint, or `None` when the
+    /// file is unchanged.
+    pub fn difference(&self, current: &Self) -> Option<String> {
+        if self.size != current.size {
+            return Some(format!(
+                "changed size from {} to {} bytes",
+                self.size, current.size
+            ));
+        }
+        if self.digest != current.digest {
+            return Some("changed content".to_string());
+        }
+        if self.modified != current.modified {
+            return Some("has a new modification time".to_string());
+        }
+        None
+    }
+}
+
+/// Hashes the first `len` bytes of `path`: all of them when short, otherwise the
+/// head and the sampled blocks described on [`SourceFingerprint`].
+fn digest_prefix(path: &Path, len: u64) -> Result<String> {
+    let mut file = File::open(path).with_context(|| format!("Opening {path:?} to fingerprint"))?;
+    let ranges = if len <= FINGERPRINT_HEAD + FINGERPRINT_SAMPLES * FINGERPRINT_BLOCK {
+        vec![(0, len)]
+    } else {
+        let last_start = len - FINGERPRINT_BLOCK;
+        std::iter::once((0, FINGERPRINT_HEAD))
+            .chain((0..FINGERPRINT_SAMPLES).map(|sample| {
+                let start = FINGERPRINT_HEAD
+                    + (last_start - FINGERPRINT_HEAD) * sample / (FINGERPRINT_SAMPLES - 1);
+                (start, start + FINGERPRINT_BLOCK)
+            }))
+            .collect()
+    };
+    let mut hasher = Sha256::new();
+    hasher.update(len.to_le_bytes());
+    let mut buffer = Vec::new();
+    for (start, end) in ranges {
+        buffer.resize((end - start) as usize, 0);
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(&mut buffer)
+            .with_context(|| format!("Reading {path:?} to fingerprint"))?;
+        hasher.update(&buffer);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Whether an index still describes its source file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SourceStatus {
+    Current,
+    /// The index predates source fingerprints, so it cannot be checked.
+    Unrecorded,
+    /// The file changed since indexing, as described.
+    Changed(String),
 }
 
 impl CsvIndex {
@@ -304,6 +411,7 @@ impl CsvIndex {
         limit: Option<usize>,
         delimiter: u8,
         encoding: &'static Encoding,
+        dialect: &Dialect,
     ) -> Result<Self> {
         if definitions.is_empty() {
             return Err(anyhow!(
@@ -311,7 +419,8 @@ impl CsvIndex {
             ));
         }
 
-        let mut reader = io_utils::open_seekable_csv_reader(csv_path, delimiter, true)?;
+        let source = SourceFingerprint::of(csv_path)?;
+        let mut reader = io_utils::open_seekable_csv_reader(csv_path, delimiter, true, dialect)?;
         let headers = io_utils::reader_headers(&mut reader, encoding)?;
 
         let mut builders = definitions
@@ -346,6 +455,183 @@ impl CsvIndex {
             headers,
             row_count: processed,
             variants,
+            source: Some(source),
+        })
+    }
+
+    /// Builds the same variants again over `csv_path` as it is now. Key types
+    /// come from `schema` when given, otherwise from the existing variants.
+    pub fn rebuild(
+        &self,
+        csv_path: &Path,
+        schema: Option<&Schema>,
+        delimiter: u8,
+        encoding: &'static Encoding,
+        dialect: &Dialect,
+    ) -> Result<Self> {
+        let definitions = self
+            .variants
+            .iter()
+            .map(IndexVariant::definition)
+            .collect::<Vec<_>>();
+        let key_schema;
+        let schema = match schema {
+            Some(schema) => schema,
+            None => {
+                key_schema = self.key_schema();
+                &key_schema
+            }
+        };
+        Self::build(
+            csv_path,
+            &definitions,
+            Some(schema),
+            None,
+            delimiter,
+            encoding,
+            dialect,
+        )
+    }
+
+    /// Indexes the rows appended to `csv_path` since this index was built or last
+    /// updated, scanning only the bytes after the last indexed row. The indexed
+    /// part of the file must be unchanged. Key types come from `schema` when
+    /// given, otherwise from the existing variants.
+    pub fn update(
+        mut self,
+        csv_path: &Path,
+        schema: Option<&Schema>,
+        delimiter: u8,
+        encoding: &'static Encoding,
+        dialect: &Dialect,
+    ) -> Result<Self> {
+        let recorded = self.source.as_ref().ok_or_else(|| {
+            anyhow!(
+                "The index does not record the file it was built from; rebuild it without --update"
+            )
+        })?;
+        if Compression::from_path(csv_path) != Compression::None {
+            bail!(
+                "Updating the index of compressed file {csv_path:?} is not supported; rebuild it without --update"
+            );
+        }
+        let source = SourceFingerprint::of(csv_path)?;
+        if source.size < recorded.size {
+            bail!(
+                "{csv_path:?} shrank from {} to {} bytes since it was indexed; rebuild the index without --update",
+                recorded.size,
+                source.size
+            );
+        }
+        if digest_prefix(csv_path, recorded.size)? != recorded.digest {
+            bail!(
+                "The first {} bytes of {csv_path:?} changed since they were indexed; rebuild the index without --update",
+                recorded.size
+            );
+        }
+
+        let mut reader = io_utils::open_seekable_csv_reader(csv_path, delimiter, true, dialect)?;
+        let headers = io_utils::reader_headers(&mut reader, encoding)?;
+        let mut record = csv::ByteRecord::new();
+        // Re-read the last indexed row to find where it ends: rows appended to a
+        // file without a trailing newline would extend it.
+        let mut resume = reader.position().clone();
+        if let Some(last) = self
+            .variants
+            .first()
+            .and_then(|variant| variant.ordered_offsets().max())
+        {
+            resume.set_byte(last);
+            reader.seek(resume.clone())?;
+            reader.read_byte_record(&mut record)?;
+            resume = reader.position().clone();
+        }
+        if resume.byte() > recorded.size {
+            bail!(
+                "Data appended to {csv_path:?} extended its last indexed row; rebuild the index without --update"
+            );
+        }
+
+        let key_schema;
+        let schema = match schema {
+            Some(schema) => schema,
+            None => {
+                key_schema = self.key_schema();
+                &key_schema
+            }
+        };
+        let mut builders = Vec::with_capacity(self.variants.len());
+        for variant in &mut self.variants {
+            let mut builder =
+                IndexVariantBuilder::new(&variant.definition(), &headers, Some(schema), encoding)?;
+            if builder.column_types != variant.column_types {
+                bail!(
+                    "The schema types the key of variant '{}' differently from the index; rebuild it without --update",
+                    variant.describe()
+                );
+            }
+            builder.map = std::mem::take(&mut variant.map);
+            builders.push(builder);
+        }
+
+        reader.seek(resume)?;
+        let mut added = 0usize;
+        loop {
+            let start_offset = reader.position().byte();
+            if !reader.read_byte_record(&mut record)? {
+                break;
+            }
+            for builder in builders.iter_mut() {
+                builder.add_record(&record, start_offset)?;
+            }
+            added += 1;
+        }
+
+        self.variants = builders
+            .into_iter()
+            .map(IndexVariantBuilder::finish)
+            .collect();
+        self.row_count += added;
+        self.source = Some(source);
+        Ok(self)
+    }
+
+    /// A schema typing each key column as the variants recorded it.
+    fn key_schema(&self) -> Schema {
+        let mut columns: Vec<ColumnMeta> = Vec::new();
+        for variant in &self.variants {
+            for (name, datatype) in variant.columns.iter().zip(&variant.column_types) {
+                if columns.iter().all(|column| column.name != *name) {
+                    columns.push(ColumnMeta {
+                        name: name.clone(),
+                        datatype: datatype.clone(),
+                        rename: None,
+                        value_replacements: Vec::new(),
+                        datatype_mappings: Vec::new(),
+                        lookup: None,
+                        start: None,
+                        width: None,
+                    });
+                }
+            }
+        }
+        Schema {
+            columns,
+            schema_version: None,
+            has_headers: true,
+            dialect: None,
+        }
+    }
+
+    /// Compares the recorded source fingerprint with `csv_path` as it is now.
+    pub fn source_status(&self, csv_path: &Path) -> Result<SourceStatus> {
+        let Some(recorded) = &self.source else {
+            return Ok(SourceStatus::Unrecorded);
+        };
+        let current = SourceFingerprint::of(csv_path)?;
+        Ok(match recorded.difference(&current) {
+            Some(reason) => SourceStatus::Changed(reason),
+            None => SourceStatus::Current,
         })
     }
 
@@ -373,6 +659,12 @@ impl CsvIndex {
                 Ok(index)
             }
             Err(err) => {
+                if let Ok((index, _)) =
+                    bincode::serde::decode_from_slice::<CsvIndexV2, _>(&bytes, config)
+                    && index.version == 2
+                {
+                    return Ok(index.into());
+                }
                 let (legacy, _) =
                     bincode::serde::decode_from_slice::<LegacyCsvIndex, _>(&bytes, config)
                         .with_context(|| {
@@ -441,6 +733,14 @@ impl IndexVariant {
         &self.directions
     }
 
+    fn definition(&self) -> IndexDefinition {
+        IndexDefinition {
+            columns: self.columns.clone(),
+            directions: self.directions.clone(),
+            name: self.name.clone(),
+        }
+    }
+
     /// Returns the optional human-readable name assigned to this variant.
     pub fn name(&self) -> Option<&str> {
         self.name.as_deref()
@@ -637,6 +937,27 @@ fn lookup_indices(headers: &[String], columns: &[String]) -> Result<Vec<usize>>
         .collect()
 }
 
+/// Version 2 layout, written before indexes recorded their source file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CsvIndexV2 {
+    version: u32,
+    headers: Vec<String>,
+    variants: Vec<IndexVariant>,
+    row_count: usize,
+}
+
+impl From<CsvIndexV2> for CsvIndex {
+    fn from(index: CsvIndexV2) -> Self {
+        CsvIndex {
+            version: INDEX_VERSION,
+            headers: index.headers,
+            variants: index.variants,
+            row_count: index.row_count,
+            source: None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct LegacyCsvIndex {
     version: u32,
@@ -672,6 +993,7 @@ impl From<LegacyCsvIndex> for CsvIndex {
                 name: None,
             }],
             row_count,
+            source: None,
         }
     }
 }
@@ -768,6 +1090,9 @@ mod tests {
                     rename: None,
                     value_replacements: Vec::new(),
                     datatype_mappings: Vec::new(),
+                    lookup: None,
+                    start: None,
+                    width: None,
                 },
                 ColumnMeta {
                     name: "amount".to_string(),
@@ -777,15 +1102,27 @@ mod tests {
                     rename: None,
                     value_replacements: Vec::new(),
                     datatype_mappings: Vec::new(),
+                    lookup: None,
+                    start: None,
+                    width: None,
                 },
             ],
             schema_version: None,
             has_headers: true,
+            dialect: None,
         };
 
         let definition = IndexDefinition::from_columns(vec!["amount".to_string()]).unwrap();
-        let index = CsvIndex::build(&csv_path, &[definition], Some(&schema), None, b',', UTF_8)
-            .expect("build index");
+        let index = CsvIndex::build(
+            &csv_path,
+            &[definition],
+            Some(&schema),
+            None,
+            b',',
+            UTF_8,
+            &Dialect::default(),
+        )
+        .expect("build index");
 
         let index_path = temp.path().join("decimal.idx");
         index.save(&index_path).expect("save index");
@@ -818,7 +1155,16 @@ mod tests {
             IndexDefinition::parse("descending=a:desc,b:asc").unwrap(),
         ];
 
-        let index = CsvIndex::build(&csv_path, &definitions, None, None, b',', UTF_8).unwrap();
+        let index = CsvIndex::build(
+            &csv_path,
+            &definitions,
+            None,
+            None,
+            b',',
+            UTF_8,
+            &Dialect::default(),
+        )
+        .unwrap();
 
         assert_eq!(index.variants().len(), 2);
 
@@ -869,7 +1215,16 @@ mod tests {
             IndexDefinition::parse("long=a:asc,b:asc").unwrap(),
         ];
 
-        let index = CsvIndex::build(&csv_path, &definitions, None, None, b',', UTF_8).unwrap();
+        let index = CsvIndex::build(
+            &csv_path,
+            &definitions,
+            None,
+            None,
+            b',',
+            UTF_8,
+            &Dialect::default(),
+        )
+        .unwrap();
         assert_eq!(index.variants().len(), 2);
 
         // Sort by (a:asc, b:asc, c:asc) — both variants match as prefix, but
@@ -893,7 +1248,16 @@ mod tests {
         std::fs::write(&csv_path, "a\n1\n2\n").unwrap();
 
         let definition = IndexDefinition::from_columns(vec!["a".to_string()]).unwrap();
-        let mut index = CsvIndex::build(&csv_path, &[definition], None, None, b',', UTF_8).unwrap();
+        let mut index = CsvIndex::build(
+            &csv_path,
+            &[definition],
+            None,
+            None,
+            b',',
+            UTF_8,
+            &Dialect::default(),
+        )
+        .unwrap();
 
         // Tamper with the version to simulate a future incompatible format.
         index.version = INDEX_VERSION + 99;
@@ -908,6 +1272,79 @@ mod tests {
         );
     }
 
+    #[test]
+    fn version_two_indexes_load_without_a_source_fingerprint() {
+        let dir = tempdir().unwrap();
+        let csv_path = dir.path().join("data.csv");
+        std::fs::write(&csv_path, "a\n2\n1\n").unwrap();
+        let definition = IndexDefinition::from_columns(vec!["a".to_string()]).unwrap();
+        let index = CsvIndex::build(
+            &csv_path,
+            &[definition],
+            None,
+            None,
+            b',',
+            UTF_8,
+            &Dialect::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            index.source_status(&csv_path).unwrap(),
+            SourceStatus::Current
+        );
+
+        let v2 = CsvIndexV2 {
+            version: 2,
+            headers: index.headers.clone(),
+            variants: index.variants.clone(),
+            row_count: index.row_count,
+        };
+        let index_path = dir.path().join("v2.idx");
+        let bytes = bincode::serde::encode_to_vec(&v2, bincode::config::legacy()).unwrap();
+        std::fs::write(&index_path, bytes).unwrap();
+
+        let loaded = CsvIndex::load(&index_path).expect("load version 2 index");
+        assert_eq!(loaded.row_count(), 2);
+        assert_eq!(
+            loaded.source_status(&csv_path).unwrap(),
+            SourceStatus::Unrecorded
+        );
+
+        std::fs::write(&csv_path, "a\n2\n1\n3\n").unwrap();
+        assert!(matches!(
+            index.source_status(&csv_path).unwrap(),
+            SourceStatus::Changed(reason) if reason.contains("size")
+        ));
+    }
+
+    #[test]
+    fn update_refuses_rows_appended_to_an_unterminated_last_line() {
+        let dir = tempdir().unwrap();
+        let csv_path = dir.path().join("log.csv");
+        std::fs::write(&csv_path, "a,b\n1,x\n2,y").unwrap();
+        let definitions = vec![IndexDefinition::parse("a:asc").unwrap()];
+        let index = CsvIndex::build(
+            &csv_path,
+            &definitions,
+            None,
+            None,
+            b',',
+            UTF_8,
+            &Dialect::default(),
+        )
+        .unwrap();
+
+        // "2,y" becomes "2,y3", changing a row the index already holds.
+        std::fs::write(&csv_path, "a,b\n1,x\n2,y3\n").unwrap();
+        let err = index
+            .update(&csv_path, None, b',', UTF_8, &Dialect::default())
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("extended its last indexed row"),
+            "{err}"
+        );
+    }
+
     #[test]
     fn expand_covering_spec_rejects_empty_spec() {
         let err =
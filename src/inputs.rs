@@ -0,0 +1,188 @@
+//! Multi-file inputs read as one logical stream.
+//!
+//! `process -i` accepts several paths and glob patterns such as
+//! `drops/2026-10-*.csv`. Patterns expand in sorted order and files are read
+//! back to back. The first file establishes the header layout; each following
+//! file is opened only once the previous one is exhausted and its header row is
+//! checked with [`Schema::validate_headers`] before any of its rows are read.
+//!
+//! Every record carries its line within its own file: CSV records keep the
+//! position the reader gave them, and records from other formats are numbered
+//! by row, counting the header row.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow};
+use csv::ByteRecord;
+use encoding_rs::Encoding;
+
+use crate::{
+    cli::InputFormatArgs,
+    io_utils::{self, ByteRecords},
+    malformed::MalformedRows,
+    schema::Schema,
+};
+
+/// Expands glob patterns among `inputs`, preserving argument order.
+///
+/// Arguments without glob metacharacters are kept as-is (including `-` for
+/// stdin, which must be the only input).
+pub fn resolve_inputs(inputs: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut resolved = Vec::new();
+    for input in inputs {
+        let text = input.to_string_lossy();
+        if io_utils::is_dash(input) || !text.contains(['*', '?', '[']) {
+            resolved.push(input.clone());
+            continue;
+        }
+        let mut matches = glob::glob(&text)
+            .with_context(|| format!("Invalid input pattern '{text}'"))?
+            .collect::<Result<Vec<_>, _>>()
+            .with_context(|| format!("Expanding input pattern '{text}'"))?;
+        if matches.is_empty() {
+            return Err(anyhow!("Input pattern '{text}' matched no files"));
+        }
+        matches.sort();
+        resolved.extend(matches);
+    }
+    if resolved.is_empty() {
+        return Err(anyhow!("At least one input file must be provided"));
+    }
+    if resolved.len() > 1 && resolved.iter().any(|path| io_utils::is_dash(path)) {
+        return Err(anyhow!(
+            "Standard input ('-') cannot be combined with other inputs"
+        ));
+    }
+    Ok(resolved)
+}
+
+/// Records from a list of input files, tagged with the position of their file.
+pub(crate) struct InputRecords<'a> {
+    paths: &'a [PathBuf],
+    file: usize,
+    row: usize,
+    current: Option<ByteRecords>,
+    schema: &'a Schema,
+    delimiter: u8,
+    encoding: &'static Encoding,
+    has_headers: bool,
+    input_format: &'a InputFormatArgs,
+    malformed: Option<&'a MalformedRows>,
+}
+
+impl<'a> InputRecords<'a> {
+    /// Starts from `first`, the records of `paths[0]` after its header row.
+    /// Later files are validated against `schema`.
+    pub(crate) fn new(
+        first: ByteRecords,
+        paths: &'a [PathBuf],
+        schema: &'a Schema,
+        delimiter: u8,
+        encoding: &'static Encoding,
+        has_headers: bool,
+        input_format: &'a InputFormatArgs,
+    ) -> Self {
+        Self {
+            paths,
+            file: 0,
+            row: 0,
+            current: Some(first),
+            schema,
+            delimiter,
+            encoding,
+            has_headers,
+            input_format,
+            malformed: None,
+        }
+    }
+
+    /// Applies the `--on-malformed` policy to the later files too.
+    pub(crate) fn with_malformed(mut self, malformed: Option<&'a MalformedRows>) -> Self {
+        self.malformed = malformed;
+        self
+    }
+
+    fn open(&self, path: &Path) -> Result<ByteRecords> {
+        let (headers, records) = io_utils::open_schema_records(
+            path,
+            self.schema,
+            self.delimiter,
+            self.encoding,
+            self.input_format,
+            self.malformed,
+        )?;
+        if let Some(headers) = headers {
+            self.schema
+                .validate_headers(&headers)
+                .with_context(|| format!("Validating headers for {path:?}"))?;
+        }
+        Ok(records)
+    }
+}
+
+impl Iterator for InputRecords<'_> {
+    type Item = Result<(usize, ByteRecord)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(records) = self.current.as_mut() {
+                match records.next() {
+                    Some(result) => {
+                        self.row += 1;
+                        let row = self.row + usize::from(self.has_headers);
+                        return Some(match result {
+                            Ok(mut record) => {
+                                if record.position().is_none() {
+                                    let mut position = csv::Position::new();
+                                    position.set_line(row as u64);
+                                    record.set_position(Some(position));
+                                }
+                                Ok((self.file, record))
+                            }
+                            Err(err) if self.paths.len() > 1 => Err(err.context(format!(
+                                "Reading row {row} in {:?}",
+                                self.paths[self.file]
+                            ))),
+                            Err(err) => Err(err.context(format!("Reading row {row}"))),
+                        });
+                    }
+                    None => self.current = None,
+                }
+            }
+            let next = self.file + 1;
+            let path = self.paths.get(next)?;
+            self.file = next;
+            self.row = 0;
+            match self.open(path) {
+                Ok(records) => self.current = Some(records),
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_inputs_expands_patterns_in_sorted_order() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        for name in ["b.csv", "a.csv", "c.txt"] {
+            std::fs::write(temp.path().join(name), "id\n1\n").expect("write file");
+        }
+        let pattern = temp.path().join("*.csv");
+        let explicit = temp.path().join("c.txt");
+        let resolved = resolve_inputs(&[pattern.clone(), explicit.clone()]).expect("resolve");
+        assert_eq!(
+            resolved,
+            vec![
+                temp.path().join("a.csv"),
+                temp.path().join("b.csv"),
+                explicit
+            ]
+        );
+        assert!(resolve_inputs(&[temp.path().join("*.json")]).is_err());
+        assert!(resolve_inputs(&[pattern, PathBuf::from("-")]).is_err());
+    }
+}
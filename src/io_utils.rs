@@ -8,19 +8,37 @@
 //!   defaulting to UTF-8.
 //! - **Reader/writer construction**: `open_csv_reader`, `open_csv_writer`,
 //!   and seekable reader variants for index-accelerated reads.
+//! - **Parquet, JSON, and Excel inputs**: `open_records` reads `.parquet`,
+//!   `.json` / `.jsonl` / `.ndjson`, and workbook files through the same record
+//!   stream as CSV.
+//! - **Compression**: `.gz` and `.zst` inputs and outputs are decompressed
+//!   and compressed as streams (see [`crate::compression`]).
 //! - **stdin/stdout**: the `-` path convention routes through standard streams.
-//! - **Quoting**: CSV output uses `QuoteStyle::Always` for round-trip safety.
+//! - **Dialect**: readers and writers are configured from a
+//!   [`Dialect`]; the default quotes every output field (`QuoteStyle::Always`)
+//!   for round-trip safety.
 
 use std::{
     fs::File,
-    io::{self, BufReader, BufWriter, Read, Write},
+    io::{self, BufWriter, Read, Write},
     path::Path,
 };
 
 use anyhow::{Context, Result, anyhow};
-use csv::QuoteStyle;
 use encoding_rs::{Encoding, UTF_8};
 
+use crate::{
+    cli::InputFormatArgs,
+    compression::{self, OutputStream, SeekRead},
+    dialect::Dialect,
+    excel_input,
+    fixed_width::{self, FixedWidthLayout},
+    json_input,
+    malformed::MalformedRows,
+    parquet_input,
+    schema::Schema,
+};
+
 pub const DEFAULT_CSV_DELIMITER: u8 = b',';
 pub const DEFAULT_TSV_DELIMITER: u8 = b'\t';
 
@@ -37,7 +55,10 @@ pub fn resolve_encoding(label: Option<&str>) -> Result<&'static Encoding> {
     }
 }
 
+/// Picks the delimiter from the extension under any compression suffix, so
+/// `orders.tsv.gz` reads as tab-separated.
 pub fn resolve_input_delimiter(path: &Path, provided: Option<u8>) -> u8 {
+    let path = compression::inner_path(path);
     provided.unwrap_or_else(|| match path.extension().and_then(|ext| ext.to_str()) {
         Some(ext) if ext.eq_ignore_ascii_case("tsv") => DEFAULT_TSV_DELIMITER,
         _ => DEFAULT_CSV_DELIMITER,
@@ -48,7 +69,7 @@ pub fn resolve_output_delimiter(path: Option<&Path>, provided: Option<u8>, fallb
     if let Some(delim) = provided {
         return delim;
     }
-    if let Some(path) = path {
+    if let Some(path) = path.map(compression::inner_path) {
         match path.extension().and_then(|ext| ext.to_str()) {
             Some(ext) if ext.eq_ignore_ascii_case("tsv") => return DEFAULT_TSV_DELIMITER,
             Some(ext) if ext.eq_ignore_ascii_case("csv") => return DEFAULT_CSV_DELIMITER,
@@ -58,68 +79,232 @@ pub fn resolve_output_delimiter(path: Option<&Path>, provided: Option<u8>, fallb
     fallback
 }
 
-pub fn open_csv_reader<R>(reader: R, delimiter: u8, has_headers: bool) -> csv::Reader<R>
+pub fn open_csv_reader<R>(
+    reader: R,
+    delimiter: u8,
+    has_headers: bool,
+    dialect: &Dialect,
+) -> csv::Reader<R>
 where
     R: Read,
 {
-    let mut builder = csv::ReaderBuilder::new();
-    builder
-        .has_headers(has_headers)
-        .delimiter(delimiter)
-        .double_quote(true)
-        .flexible(false);
-    builder.from_reader(reader)
+    dialect
+        .reader_builder(delimiter, has_headers)
+        .from_reader(reader)
+}
+
+/// Opens `path` (stdin when `-`) for reading, decompressing `.gz` and `.zst`
+/// files and compressed standard input.
+pub fn open_input_reader(path: &Path) -> Result<Box<dyn Read>> {
+    if is_dash(path) {
+        return compression::decompress_stream(std::io::stdin().lock());
+    }
+    let file = File::open(path).with_context(|| format!("Opening input file {path:?}"))?;
+    compression::decompress_file(path, file)
 }
 
 pub fn open_csv_reader_from_path(
     path: &Path,
     delimiter: u8,
     has_headers: bool,
+    dialect: &Dialect,
 ) -> Result<csv::Reader<Box<dyn Read>>> {
-    let reader: Box<dyn Read> = if is_dash(path) {
-        Box::new(std::io::stdin().lock())
+    let reader = open_input_reader(path)?;
+    Ok(open_csv_reader(reader, delimiter, has_headers, dialect))
+}
+
+/// Row records from an input file, whatever its format.
+pub(crate) type ByteRecords = Box<dyn Iterator<Item = Result<csv::ByteRecord>>>;
+
+/// Opens `path` as a stream of records. Parquet and JSON inputs always report
+/// their column names; CSV and worksheet inputs report the header row only when
+/// `has_headers` is set. Ragged CSV rows are handled by `malformed` when given
+/// and are an error otherwise.
+///
+/// Non-CSV inputs render their cells as text in the formats the CSV readers
+/// already accept, so every format flows through the same mapping, replacement,
+/// typing, filter, derive, and sort stages.
+pub(crate) fn open_records(
+    path: &Path,
+    delimiter: u8,
+    encoding: &'static Encoding,
+    has_headers: bool,
+    dialect: &Dialect,
+    input_format: &InputFormatArgs,
+    malformed: Option<&MalformedRows>,
+) -> Result<(Option<Vec<String>>, ByteRecords)> {
+    if parquet_input::is_parquet(path) {
+        let (headers, records) = parquet_input::open(path)?;
+        return Ok((Some(headers), Box::new(records)));
+    }
+    if json_input::is_json(path) {
+        let (headers, records) = json_input::open(path, input_format)?;
+        return Ok((Some(headers), Box::new(records)));
+    }
+    if excel_input::is_excel(path) {
+        let (headers, records) = excel_input::open(path, input_format, has_headers)?;
+        return Ok((headers, Box::new(records)));
+    }
+    open_csv_records(path, delimiter, encoding, has_headers, dialect, malformed)
+}
+
+/// Opens `path` as CSV records, reporting the header row when `has_headers` is
+/// set. With `malformed` the reader accepts ragged rows and hands them to its
+/// policy.
+pub(crate) fn open_csv_records(
+    path: &Path,
+    delimiter: u8,
+    encoding: &'static Encoding,
+    has_headers: bool,
+    dialect: &Dialect,
+    malformed: Option<&MalformedRows>,
+) -> Result<(Option<Vec<String>>, ByteRecords)> {
+    let mut reader = dialect
+        .reader_builder(delimiter, has_headers)
+        .flexible(malformed.is_some())
+        .from_reader(open_input_reader(path)?);
+    let headers = if has_headers {
+        Some(reader_headers(&mut reader, encoding)?)
     } else {
-        Box::new(BufReader::new(
-            File::open(path).with_context(|| format!("Opening input file {path:?}"))?,
-        ))
+        None
     };
-    Ok(open_csv_reader(reader, delimiter, has_headers))
+    let records: ByteRecords = Box::new(
+        reader
+            .into_byte_records()
+            .map(|record| record.map_err(anyhow::Error::from)),
+    );
+    Ok(match malformed {
+        Some(malformed) => {
+            let width = headers.as_ref().map(Vec::len);
+            (headers, malformed.wrap(path, width, records))
+        }
+        None => (headers, records),
+    })
+}
+
+/// Opens `path` as described by `schema`: sliced by column positions when the
+/// schema declares a fixed-width layout, otherwise as in [`open_records`] with
+/// the schema's dialect.
+pub(crate) fn open_schema_records(
+    path: &Path,
+    schema: &Schema,
+    delimiter: u8,
+    encoding: &'static Encoding,
+    input_format: &InputFormatArgs,
+    malformed: Option<&MalformedRows>,
+) -> Result<(Option<Vec<String>>, ByteRecords)> {
+    if let Some(layout) = FixedWidthLayout::from_schema(schema)? {
+        let (headers, records) =
+            fixed_width::open(path, layout, schema.expects_headers(), encoding)?;
+        return Ok((headers, Box::new(records)));
+    }
+    open_records(
+        path,
+        delimiter,
+        encoding,
+        schema.expects_headers(),
+        &schema.dialect.clone().unwrap_or_default(),
+        input_format,
+        malformed,
+    )
 }
 
+/// Opens `path` for index-driven reads; see [`compression::open_seekable`].
 pub fn open_seekable_csv_reader(
     path: &Path,
     delimiter: u8,
     has_headers: bool,
-) -> Result<csv::Reader<BufReader<File>>> {
-    let reader =
-        BufReader::new(File::open(path).with_context(|| format!("Opening input file {path:?}"))?);
-    Ok(open_csv_reader(reader, delimiter, has_headers))
+    dialect: &Dialect,
+) -> Result<csv::Reader<Box<dyn SeekRead>>> {
+    let reader = compression::open_seekable(path)?;
+    Ok(open_csv_reader(reader, delimiter, has_headers, dialect))
 }
 
-pub fn open_csv_writer(
+/// An output stream that has to be finished to complete its file. Compressed
+/// streams write their last block and trailer in `finish`, so failures there
+/// are reported instead of lost when the writer is dropped.
+pub trait FinishWrite: Write {
+    /// Flushes buffered output and completes the stream.
+    fn finish(&mut self) -> io::Result<()>;
+}
+
+impl<W: FinishWrite + ?Sized> FinishWrite for Box<W> {
+    fn finish(&mut self) -> io::Result<()> {
+        (**self).finish()
+    }
+}
+
+/// Opens `path` (stdout when `None` or `-`) for writing, compressing `.gz` and
+/// `.zst` files and transcoding from UTF-8 when another output encoding is
+/// requested. Call [`FinishWrite::finish`] once everything is written.
+pub fn open_output_writer(
     path: Option<&Path>,
-    delimiter: u8,
     encoding: &'static Encoding,
-) -> Result<csv::Writer<Box<dyn Write>>> {
-    let base: Box<dyn Write> = match path {
-        Some(p) if !is_dash(p) => Box::new(BufWriter::new(
-            File::create(p).with_context(|| format!("Creating output file {p:?}"))?,
-        )),
-        _ => Box::new(std::io::stdout()),
+) -> Result<Box<dyn FinishWrite>> {
+    let base: Box<dyn FinishWrite> = match path {
+        Some(p) if !is_dash(p) => compression::compress(
+            p,
+            BufWriter::new(File::create(p).with_context(|| format!("Creating output file {p:?}"))?),
+        )?,
+        _ => Box::new(OutputStream::Plain(std::io::stdout())),
     };
+    Ok(transcode_output(base, encoding))
+}
 
-    let writer: Box<dyn Write> = if encoding == UTF_8 {
+fn transcode_output(
+    base: Box<dyn FinishWrite>,
+    encoding: &'static Encoding,
+) -> Box<dyn FinishWrite> {
+    if encoding == UTF_8 {
         base
     } else {
         Box::new(TranscodingWriter::new(base, encoding))
-    };
+    }
+}
+
+/// Opens a CSV writer configured by `dialect`. A requested byte-order mark is
+/// written first for UTF-8 output and skipped for other encodings. Close it
+/// with [`finish_csv_writer`].
+pub fn open_csv_writer(
+    path: Option<&Path>,
+    delimiter: u8,
+    encoding: &'static Encoding,
+    dialect: &Dialect,
+) -> Result<csv::Writer<Box<dyn FinishWrite>>> {
+    let mut writer = open_output_writer(path, encoding)?;
+    if dialect.bom && encoding == UTF_8 {
+        writer
+            .write_all("\u{FEFF}".as_bytes())
+            .context("Writing byte-order mark")?;
+    }
+    Ok(dialect.writer_builder(delimiter).from_writer(writer))
+}
 
-    let mut builder = csv::WriterBuilder::new();
-    builder
-        .delimiter(delimiter)
-        .quote_style(QuoteStyle::Always)
-        .double_quote(true);
-    Ok(builder.from_writer(writer))
+/// Reopens a CSV file written by [`open_csv_writer`] to add records at its end.
+/// Compressed files continue with a new gzip member or zstd frame, which
+/// readers decompress as one stream.
+pub(crate) fn reopen_csv_writer(
+    path: &Path,
+    delimiter: u8,
+    encoding: &'static Encoding,
+    dialect: &Dialect,
+) -> Result<csv::Writer<Box<dyn FinishWrite>>> {
+    let file = File::options()
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Reopening output file {path:?}"))?;
+    let base = compression::compress(path, BufWriter::new(file))?;
+    Ok(dialect
+        .writer_builder(delimiter)
+        .from_writer(transcode_output(base, encoding)))
+}
+
+/// Flushes `writer` and finishes its output stream.
+pub fn finish_csv_writer(writer: csv::Writer<Box<dyn FinishWrite>>) -> Result<()> {
+    let mut inner = writer
+        .into_inner()
+        .map_err(|err| anyhow!("Flushing output: {}", err.error()))?;
+    inner.finish().context("Finishing output")
 }
 
 pub fn decode_bytes(bytes: &[u8], encoding: &'static Encoding) -> Result<String> {
@@ -238,6 +423,13 @@ impl<W: Write> TranscodingWriter<W> {
     }
 }
 
+impl<W: FinishWrite> FinishWrite for TranscodingWriter<W> {
+    fn finish(&mut self) -> io::Result<()> {
+        self.flush_buffer(true)?;
+        self.inner.finish()
+    }
+}
+
 impl<W: Write> Write for TranscodingWriter<W> {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         self.buffer.extend_from_slice(buf);
@@ -1,70 +1,104 @@
 //! Join two CSV files on shared key columns.
 //!
-//! Supports inner, left, right, and full outer join strategies. The right-side
-//! file is loaded into memory as a hash map keyed by the join columns.
-
-use std::{collections::HashMap, path::PathBuf};
+//! Supports inner, left, right, and full outer joins, `semi`/`anti` joins that
+//! return the left rows with or without a match, and keyless `cross` joins.
+//! `--right-match` decides how right rows sharing a key are used. By default the
+//! right-side file is loaded into memory as a hash map keyed by the join columns
+//! while the left side streams row by row.
+//!
+//! When both inputs have an index (`--left-index`, `--right-index`) with a
+//! variant whose leading columns are the join keys, the join instead walks both
+//! files in index order and merges them, comparing keys as typed
+//! [`ComparableValue`]s. Only the right rows sharing the current key are held in
+//! memory, so reference tables larger than RAM can be joined. Output then
+//! follows key order rather than left input order.
+//!
+//! The left side may be standard input (`--left -`), which lets `join` sit in
+//! the middle of a `process | join | process` pipeline. Without `--left-schema`
+//! the piped header row becomes a schema with every column typed as `String`;
+//! such inferred key columns are parsed using the right key's datatype, and
+//! `--left-assume-types` can declare stronger types up front.
+
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    io::Read,
+    path::Path,
+    str::FromStr,
+};
 
 use anyhow::{Context, Result, anyhow};
+use csv::{ByteRecord, Position};
 use encoding_rs::Encoding;
 use log::info;
 
 use crate::{
-    data::parse_typed_value,
-    io_utils,
+    cli::{DialectArgs, InputFormatArgs, JoinArgs, JoinKind, RightMatch},
+    compression::SeekRead,
+    data::{ComparableValue, parse_typed_value},
+    dialect::Dialect,
+    index::{CsvIndex, IndexVariant, SortDirection, SourceStatus},
+    io_utils::{self, FinishWrite},
     schema::{self, ColumnType, Schema},
 };
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
-pub enum JoinKind {
-    #[default]
-    Inner,
-    Left,
-    Right,
-    Full,
-}
-
-#[derive(Debug, Clone)]
-pub struct JoinArgs {
-    pub left: PathBuf,
-    pub right: PathBuf,
-    pub output: Option<PathBuf>,
-    pub left_key: String,
-    pub right_key: String,
-    pub kind: JoinKind,
-    pub left_schema: Option<PathBuf>,
-    pub right_schema: Option<PathBuf>,
-    pub delimiter: Option<u8>,
-    pub left_encoding: Option<String>,
-    pub right_encoding: Option<String>,
-    pub output_encoding: Option<String>,
-}
-
 const KEY_SEPARATOR: &str = "\u{1f}";
 
 pub fn execute(args: &JoinArgs) -> Result<()> {
-    if args.left_key.is_empty() || args.right_key.is_empty() {
-        return Err(anyhow!("Join requires --left-key and --right-key"));
-    }
     if io_utils::is_dash(&args.right) {
         return Err(anyhow!(
             "Right input cannot be stdin for join operations; provide a file path"
         ));
     }
-    if io_utils::is_dash(&args.left) && args.left_schema.is_none() {
-        return Err(anyhow!(
-            "Joining from stdin requires --left-schema (or --left-meta) to describe the schema"
-        ));
-    }
-
-    let left_keys = parse_key_list(&args.left_key)?;
-    let right_keys = parse_key_list(&args.right_key)?;
+    // A cross join is a hash join on an empty key: every row shares the same bucket.
+    let (left_keys, right_keys) = match (args.kind, &args.left_key, &args.right_key) {
+        (JoinKind::Cross, None, None) => {
+            if args.right_match != RightMatch::All {
+                return Err(anyhow!("--right-match cannot be used with --type cross"));
+            }
+            (Vec::new(), Vec::new())
+        }
+        (JoinKind::Cross, _, _) => {
+            return Err(anyhow!(
+                "--type cross pairs every row and does not take --left-key or --right-key"
+            ));
+        }
+        (_, Some(left_key), Some(right_key)) => {
+            (parse_key_list(left_key)?, parse_key_list(right_key)?)
+        }
+        _ => return Err(anyhow!("Join requires --left-key and --right-key")),
+    };
     if left_keys.len() != right_keys.len() {
         return Err(anyhow!(
             "Left and right join keys must contain the same number of columns"
         ));
     }
 
+    let merge_indexes = match (&args.left_index, &args.right_index) {
+        (Some(left_index), Some(right_index)) => {
+            if args.kind == JoinKind::Cross {
+                return Err(anyhow!("--type cross cannot use index merge joins"));
+            }
+            if io_utils::is_dash(&args.left) {
+                return Err(anyhow!(
+                    "--left-index cannot be used when the left input is stdin"
+                ));
+            }
+            Some((
+                CsvIndex::load(left_index)
+                    .with_context(|| format!("Loading left index {left_index:?}"))?,
+                CsvIndex::load(right_index)
+                    .with_context(|| format!("Loading right index {right_index:?}"))?,
+            ))
+        }
+        (None, None) => None,
+        _ => {
+            return Err(anyhow!(
+                "Merge joins require both --left-index and --right-index"
+            ));
+        }
+    };
+
     let left_delimiter = io_utils::resolve_input_delimiter(&args.left, args.delimiter);
     let right_delimiter = io_utils::resolve_input_delimiter(&args.right, args.delimiter);
     let output_delimiter =
@@ -73,40 +107,82 @@ pub fn execute(args: &JoinArgs) -> Result<()> {
     let right_encoding = io_utils::resolve_encoding(args.right_encoding.as_deref())?;
     let output_encoding = io_utils::resolve_encoding(args.output_encoding.as_deref())?;
 
-    let left_schema = load_schema(
-        &args.left,
-        args.left_schema.as_ref(),
-        left_delimiter,
-        left_encoding,
-    )?;
+    // A piped left side cannot be read twice, so its schema comes from the header
+    // row alone and every column starts out as `String`.
+    let left_inferred = io_utils::is_dash(&args.left) && args.left_schema.is_none();
+    let left_loaded = if left_inferred {
+        None
+    } else {
+        Some(load_schema(
+            &args.left,
+            args.left_schema.as_deref(),
+            left_delimiter,
+            left_encoding,
+            &args.dialect,
+        )?)
+    };
     let right_schema = load_schema(
         &args.right,
-        args.right_schema.as_ref(),
+        args.right_schema.as_deref(),
         right_delimiter,
         right_encoding,
+        &args.dialect,
     )?;
+    let left_dialect = match &left_loaded {
+        Some(schema) => schema.dialect.clone().unwrap_or_default(),
+        None => args.dialect.apply(Dialect::default()),
+    };
+    let right_dialect = right_schema.dialect.clone().unwrap_or_default();
 
-    let left_indices = column_indices(&left_schema, &left_keys)?;
-    let right_indices = column_indices(&right_schema, &right_keys)?;
-    validate_key_types(&left_schema, &right_schema, &left_indices, &right_indices)?;
-
-    let left_expects_headers = left_schema.expects_headers();
+    let left_expects_headers = left_loaded.as_ref().is_none_or(Schema::expects_headers);
     let right_expects_headers = right_schema.expects_headers();
 
-    let mut left_reader =
-        io_utils::open_csv_reader_from_path(&args.left, left_delimiter, left_expects_headers)?;
-    let mut right_reader =
-        io_utils::open_csv_reader_from_path(&args.right, right_delimiter, right_expects_headers)?;
+    let mut left_reader = io_utils::open_csv_reader_from_path(
+        &args.left,
+        left_delimiter,
+        left_expects_headers,
+        &left_dialect,
+    )?;
+    let mut right_reader = io_utils::open_csv_reader_from_path(
+        &args.right,
+        right_delimiter,
+        right_expects_headers,
+        &right_dialect,
+    )?;
 
-    let left_headers = if left_expects_headers {
-        let headers = io_utils::reader_headers(&mut left_reader, left_encoding)?;
-        left_schema
-            .validate_headers(&headers)
-            .with_context(|| format!("Validating left headers for {:?}", args.left))?;
-        headers
-    } else {
-        left_schema.headers()
+    let (mut left_schema, left_headers) = match left_loaded {
+        None => {
+            let headers = io_utils::reader_headers(&mut left_reader, left_encoding)
+                .context("Reading left headers from stdin")?;
+            (Schema::from_headers(&headers), headers)
+        }
+        Some(schema) if left_expects_headers => {
+            let headers = io_utils::reader_headers(&mut left_reader, left_encoding)?;
+            schema
+                .validate_headers(&headers)
+                .with_context(|| format!("Validating left headers for {:?}", args.left))?;
+            (schema, headers)
+        }
+        Some(schema) => {
+            let headers = schema.headers();
+            (schema, headers)
+        }
     };
+    let assumed = apply_assumed_types(&mut left_schema, &args.left_assume_types)?;
+
+    let left_indices = column_indices(&left_schema, &left_keys)?;
+    let right_indices = column_indices(&right_schema, &right_keys)?;
+    let key_types = resolve_key_types(
+        &left_schema,
+        &right_schema,
+        &left_indices,
+        &right_indices,
+        |idx| left_inferred && !assumed.contains(&idx),
+    )?;
+    let right_key_types = right_indices
+        .iter()
+        .map(|idx| right_schema.columns[*idx].datatype.clone())
+        .collect::<Vec<_>>();
 
     let right_headers = if right_expects_headers {
         let headers = io_utils::reader_headers(&mut right_reader, right_encoding)?;
@@ -118,103 +194,124 @@ pub fn execute(args: &JoinArgs) -> Result<()> {
         right_schema.headers()
     };
 
-    let mut right_lookup = build_right_lookup(
-        &mut right_reader,
-        &right_schema,
-        &right_indices,
-        right_encoding,
-    )?;
-
-    let (output_headers, right_columns) =
-        build_output_headers(&left_headers, &right_headers, &right_indices);
+    let (output_headers, right_columns) = if matches!(args.kind, JoinKind::Semi | JoinKind::Anti) {
+        (left_headers.clone(), Vec::new())
+    } else {
+        build_output_headers(
+            &left_headers,
+            &left_indices,
+            &right_headers,
+            &right_indices,
+            (args.left_prefix.as_deref(), args.right_prefix.as_deref()),
+        )
+    };
 
-    let mut writer =
-        io_utils::open_csv_writer(args.output.as_deref(), output_delimiter, output_encoding)?;
+    // Output follows the left input's dialect.
+    let mut writer = io_utils::open_csv_writer(
+        args.output.as_deref(),
+        output_delimiter,
+        output_encoding,
+        &left_dialect,
+    )?;
     writer
         .write_record(&output_headers)
         .context("Writing joined headers")?;
 
-    let mut output_rows = 0usize;
-    let mut matched_rows = 0usize;
-    let include_unmatched_left = matches!(args.kind, JoinKind::Left | JoinKind::Full);
-    let include_unmatched_right = matches!(args.kind, JoinKind::Right | JoinKind::Full);
-    let key_pairs: Vec<(usize, usize)> = left_indices
-        .iter()
-        .cloned()
-        .zip(right_indices.iter().cloned())
-        .collect();
+    let mut output = JoinOutput {
+        writer: &mut writer,
+        left_width: left_headers.len(),
+        right_columns: &right_columns,
+        key_pairs: left_indices
+            .iter()
+            .copied()
+            .zip(right_indices.iter().copied())
+            .collect(),
+        kind: args.kind,
+        output_rows: 0,
+        matched_rows: 0,
+    };
 
-    for (row_idx, record) in left_reader.byte_records().enumerate() {
-        let record = record.with_context(|| format!("Reading left row {}", row_idx + 2))?;
-        let mut decoded = io_utils::decode_record(&record, left_encoding)?;
-        if left_schema.has_transformations() {
-            left_schema
-                .apply_transformations_to_row(&mut decoded)
-                .with_context(|| {
-                    format!(
-                        "Applying datatype mappings to left row {} in {:?}",
-                        row_idx + 2,
-                        args.left
-                    )
-                })?;
-        }
-        left_schema.apply_replacements_to_row(&mut decoded);
-        let key = build_key(&decoded, &left_schema, &left_indices)?;
-        let mut matched_any = false;
-        if let Some(bucket) = right_lookup.get_mut(&key) {
-            for entry in bucket.iter_mut() {
-                matched_any = true;
-                entry.matched = true;
-                matched_rows += 1;
-                let mut combined = decoded.clone();
-                combined.extend(
-                    right_columns
-                        .iter()
-                        .map(|idx| entry.record.get(*idx).cloned().unwrap_or_default()),
-                );
-                writer
-                    .write_record(&combined)
-                    .context("Writing joined row")?;
-                output_rows += 1;
+    if let Some((left_index, right_index)) = merge_indexes {
+        drop(left_reader);
+        drop(right_reader);
+        let plan = MergePlan::resolve(&left_index, &right_index, &left_keys, &right_keys)?;
+        for (index, input) in [(&left_index, &args.left), (&right_index, &args.right)] {
+            if io_utils::is_dash(input) {
+                continue;
             }
-        }
-
-        if !matched_any && include_unmatched_left {
-            let mut combined = decoded.clone();
-            combined.extend(right_columns.iter().map(|_| String::new()));
-            writer
-                .write_record(&combined)
-                .context("Writing left outer row")?;
-            output_rows += 1;
-        }
-    }
-
-    if include_unmatched_right {
-        for bucket in right_lookup.values() {
-            for entry in bucket.iter() {
-                if entry.matched {
-                    continue;
-                }
-                let mut left_part = vec![String::new(); left_headers.len()];
-                for (left_idx, right_idx) in &key_pairs {
-                    let value = entry.record.get(*right_idx).cloned().unwrap_or_default();
-                    left_part[*left_idx] = value;
-                }
-                let mut combined = left_part;
-                combined.extend(
-                    right_columns
-                        .iter()
-                        .map(|idx| entry.record.get(*idx).cloned().unwrap_or_default()),
-                );
-                writer
-                    .write_record(&combined)
-                    .context("Writing right outer row")?;
-                output_rows += 1;
+            if let SourceStatus::Changed(reason) = index.source_status(input)? {
+                return Err(anyhow!(
+                    "The index for {input:?} is stale: the file {reason} since it was indexed. Rebuild it with `csv-managed index`"
+                ));
             }
         }
+        info!(
+            "Merge joining via left index variant {} and right index variant {}",
+            plan.left.describe(),
+            plan.right.describe()
+        );
+        let left = IndexedSide::open(
+            &args.left,
+            plan.left,
+            JoinSide {
+                label: "left",
+                schema: &left_schema,
+                key_indices: &left_indices,
+                key_types: plan.key_types(),
+                encoding: left_encoding,
+            },
+            left_delimiter,
+            left_expects_headers,
+            &left_dialect,
+        )?;
+        let right = IndexedSide::open(
+            &args.right,
+            plan.right,
+            JoinSide {
+                label: "right",
+                schema: &right_schema,
+                key_indices: &right_indices,
+                key_types: plan.key_types(),
+                encoding: right_encoding,
+            },
+            right_delimiter,
+            right_expects_headers,
+            &right_dialect,
+        )?;
+        merge_join(
+            left,
+            right,
+            plan.directions(),
+            args.right_match,
+            &mut output,
+        )?;
+    } else {
+        let left = JoinSide {
+            label: "left",
+            schema: &left_schema,
+            key_indices: &left_indices,
+            key_types: &key_types,
+            encoding: left_encoding,
+        };
+        let right = JoinSide {
+            label: "right",
+            schema: &right_schema,
+            key_indices: &right_indices,
+            key_types: &right_key_types,
+            encoding: right_encoding,
+        };
+        hash_join(
+            &mut left_reader,
+            &mut right_reader,
+            &left,
+            &right,
+            args.right_match,
+            &mut output,
+        )?;
     }
 
-    writer.flush().context("Flushing join output")?;
+    let (output_rows, matched_rows) = (output.output_rows, output.matched_rows);
+    io_utils::finish_csv_writer(writer).context("Finishing join output")?;
     info!("Join complete: {output_rows} output row(s), {matched_rows} matched row(s)");
     Ok(())
 }
@@ -233,18 +330,32 @@ fn parse_key_list(value: &str) -> Result<Vec<String>> {
     }
 }
 
-fn load_schema(
-    path: &PathBuf,
-    schema_path: Option<&PathBuf>,
+/// Loads `schema_path` when given, otherwise infers a schema from the data in `path`.
+/// The returned schema's dialect has `dialect_args` applied.
+pub(crate) fn load_schema(
+    path: &Path,
+    schema_path: Option<&Path>,
     delimiter: u8,
     encoding: &'static Encoding,
+    dialect_args: &DialectArgs,
 ) -> Result<Schema> {
-    if let Some(schema_path) = schema_path {
-        Schema::load(schema_path).with_context(|| format!("Loading schema from {schema_path:?}"))
+    let mut schema = if let Some(schema_path) = schema_path {
+        Schema::load(schema_path).with_context(|| format!("Loading schema from {schema_path:?}"))?
     } else {
-        schema::infer_schema(path, 0, delimiter, encoding, None)
-            .with_context(|| format!("Inferring schema from {path:?}"))
-    }
+        schema::infer_schema_with_input_format(
+            path,
+            0,
+            delimiter,
+            encoding,
+            None,
+            &InputFormatArgs::default(),
+            dialect_args,
+        )
+        .with_context(|| format!("Inferring schema from {path:?}"))?
+        .0
+    };
+    schema.apply_dialect_args(dialect_args)?;
+    Ok(schema)
 }
 
 fn column_indices(schema: &Schema, columns: &[String]) -> Result<Vec<usize>> {
@@ -258,22 +369,83 @@ fn column_indices(schema: &Schema, columns: &[String]) -> Result<Vec<usize>> {
         .collect()
 }
 
-fn validate_key_types(
+/// Applies `--left-assume-types` overrides (`name:type`, comma separated) to the
+/// left schema and returns the indices of the columns they touched.
+fn apply_assumed_types(schema: &mut Schema, specs: &[String]) -> Result<Vec<usize>> {
+    let mut assumed = Vec::new();
+    for token in specs.iter().flat_map(|spec| split_type_list(spec)) {
+        let (name, type_raw) = token
+            .split_once(':')
+            .ok_or_else(|| anyhow!("Assumed type '{token}' must use the form name:type"))?;
+        let name = name.trim();
+        let index = schema.column_index(name).ok_or_else(|| {
+            anyhow!("Column '{name}' from --left-assume-types not found in left input")
+        })?;
+        let datatype = ColumnType::from_str(type_raw.trim())
+            .map_err(|err| anyhow!("Column '{name}' has invalid type '{type_raw}': {err}"))?;
+        schema.columns[index].datatype = datatype;
+        assumed.push(index);
+    }
+    Ok(assumed)
+}
+
+/// Splits a comma-separated list of `name:type` entries, keeping the commas
+/// inside `decimal(precision,scale)` together.
+fn split_type_list(spec: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0usize;
+    for (idx, ch) in spec.char_indices() {
+        match ch {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                parts.push(&spec[start..idx]);
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&spec[start..]);
+    parts
+        .into_iter()
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
+/// Returns the datatype used to parse each left key column.
+///
+/// Left columns for which `defer_to_right` holds (inferred `String` columns of a
+/// piped left input) take the right key's datatype; every other pair must agree.
+fn resolve_key_types(
     left_schema: &Schema,
     right_schema: &Schema,
     left_indices: &[usize],
     right_indices: &[usize],
-) -> Result<()> {
+    defer_to_right: impl Fn(usize) -> bool,
+) -> Result<Vec<ColumnType>> {
+    let mut key_types = Vec::with_capacity(left_indices.len());
     for (l_idx, r_idx) in left_indices.iter().zip(right_indices.iter()) {
-        let left_type = &left_schema.columns[*l_idx].datatype;
+        let left_column = &left_schema.columns[*l_idx];
+        let left_type = &left_column.datatype;
         let right_type = &right_schema.columns[*r_idx].datatype;
-        if !same_type(left_type, right_type) {
+        if *left_type == ColumnType::String && defer_to_right(*l_idx) {
+            key_types.push(right_type.clone());
+        } else if same_type(left_type, right_type) {
+            key_types.push(left_type.clone());
+        } else {
             return Err(anyhow!(
-                "Type mismatch for join keys: left {left_type:?} vs right {right_type:?}"
+                "Type mismatch for join key '{}': left {} vs right {}. Use --left-assume-types {}:{} or --left-schema to align the left key type",
+                left_column.name,
+                left_type.describe(),
+                right_type.describe(),
+                left_column.name,
+                right_type.cli_token()
             ));
         }
     }
-    Ok(())
+    Ok(key_types)
 }
 
 fn same_type(left: &ColumnType, right: &ColumnType) -> bool {
@@ -283,63 +455,469 @@ fn same_type(left: &ColumnType, right: &ColumnType) -> bool {
     }
 }
 
-struct RightRow {
-    record: Vec<String>,
-    matched: bool,
+/// Key layout and decoding settings for one join input.
+struct JoinSide<'a> {
+    label: &'static str,
+    schema: &'a Schema,
+    key_indices: &'a [usize],
+    key_types: &'a [ColumnType],
+    encoding: &'static Encoding,
 }
 
-fn build_right_lookup(
-    reader: &mut csv::Reader<Box<dyn std::io::Read>>,
-    schema: &Schema,
-    key_indices: &[usize],
-    encoding: &'static Encoding,
-) -> Result<HashMap<String, Vec<RightRow>>> {
-    let mut map: HashMap<String, Vec<RightRow>> = HashMap::new();
-    for (row_idx, record) in reader.byte_records().enumerate() {
-        let record = record.with_context(|| format!("Reading right row {}", row_idx + 2))?;
-        let mut decoded = io_utils::decode_record(&record, encoding)?;
-        if schema.has_transformations() {
-            schema
+impl JoinSide<'_> {
+    /// Decodes a record and applies the side's datatype mappings and replacements.
+    fn prepare_row(&self, record: &ByteRecord, location: &str) -> Result<Vec<String>> {
+        let mut decoded = io_utils::decode_record(record, self.encoding)?;
+        if self.schema.has_transformations() {
+            self.schema
                 .apply_transformations_to_row(&mut decoded)
                 .with_context(|| {
-                    format!("Applying datatype mappings to right row {}", row_idx + 2)
+                    format!(
+                        "Applying datatype mappings to {} row {location}",
+                        self.label
+                    )
                 })?;
         }
-        schema.apply_replacements_to_row(&mut decoded);
-        let key = build_key(&decoded, schema, key_indices)?;
-        map.entry(key).or_default().push(RightRow {
-            record: decoded,
-            matched: false,
-        });
+        self.schema.apply_replacements_to_row(&mut decoded);
+        Ok(decoded)
+    }
+
+    /// Parses the key columns of a prepared row into typed values.
+    fn typed_key(&self, row: &[String], location: &str) -> Result<Vec<ComparableValue>> {
+        self.key_indices
+            .iter()
+            .zip(self.key_types)
+            .map(|(idx, datatype)| {
+                let column = &self.schema.columns[*idx];
+                let raw = row.get(*idx).map(|s| s.as_str()).unwrap_or("");
+                let normalized = column.normalize_value(raw);
+                parse_typed_value(normalized.as_ref(), datatype)
+                    .with_context(|| {
+                        format!(
+                            "Join key column '{}' value '{raw}' is not a valid {}",
+                            column.name,
+                            datatype.describe()
+                        )
+                    })
+                    .map(ComparableValue)
+            })
+            .collect::<Result<Vec<_>>>()
+            .with_context(|| format!("Building join key for {} row {location}", self.label))
     }
-    Ok(map)
 }
 
-fn build_key(record: &[String], schema: &Schema, key_indices: &[usize]) -> Result<String> {
-    let mut parts = Vec::with_capacity(key_indices.len());
-    for idx in key_indices {
-        let column = &schema.columns[*idx];
-        let raw = record.get(*idx).map(|s| s.as_str()).unwrap_or("");
-        let normalized = column.normalize_value(raw);
-        let parsed = parse_typed_value(normalized.as_ref(), &column.datatype)
-            .with_context(|| format!("Parsing join key for column '{}'", column.name))?;
-        if let Some(value) = parsed {
-            parts.push(value.as_display());
-        } else {
-            parts.push(String::new());
+/// Renders a typed key as a hash-map key.
+fn hash_key(key: &[ComparableValue]) -> String {
+    key.iter()
+        .map(|value| {
+            value
+                .0
+                .as_ref()
+                .map(|value| value.as_display())
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>()
+        .join(KEY_SEPARATOR)
+}
+
+/// Adds a right row to the rows sharing its join key according to `--right-match`.
+fn add_right_match<T>(
+    group: &mut Vec<T>,
+    row: T,
+    policy: RightMatch,
+    key: &[ComparableValue],
+    location: &str,
+) -> Result<()> {
+    match policy {
+        RightMatch::All => group.push(row),
+        RightMatch::First => {
+            if group.is_empty() {
+                group.push(row);
+            }
+        }
+        RightMatch::Last => {
+            group.clear();
+            group.push(row);
+        }
+        RightMatch::Error => {
+            if !group.is_empty() {
+                return Err(anyhow!(
+                    "Right row {location} repeats join key ({}); use --right-match first, last, or all to accept duplicate keys",
+                    key.iter()
+                        .map(|value| value.0.as_ref().map(|v| v.as_display()).unwrap_or_default())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ));
+            }
+            group.push(row);
         }
     }
-    Ok(parts.join(KEY_SEPARATOR))
+    Ok(())
+}
+
+/// Writes combined rows and tracks output counts for either join strategy.
+struct JoinOutput<'w> {
+    writer: &'w mut csv::Writer<Box<dyn FinishWrite>>,
+    left_width: usize,
+    right_columns: &'w [usize],
+    key_pairs: Vec<(usize, usize)>,
+    kind: JoinKind,
+    output_rows: usize,
+    matched_rows: usize,
+}
+
+impl<'w> JoinOutput<'w> {
+    /// Writes the output for one left row given the right rows that share its key.
+    fn left_row<'r>(
+        &mut self,
+        left: &[String],
+        matches: impl IntoIterator<Item = &'r Vec<String>>,
+    ) -> Result<()> {
+        let mut matches = matches.into_iter().peekable();
+        let has_match = matches.peek().is_some();
+        match self.kind {
+            JoinKind::Semi | JoinKind::Anti => {
+                if has_match {
+                    self.matched_rows += 1;
+                }
+                if has_match == (self.kind == JoinKind::Semi) {
+                    self.write(left, "Writing left row")?;
+                }
+            }
+            _ if !has_match => {
+                if matches!(self.kind, JoinKind::Left | JoinKind::Full) {
+                    let mut combined = left.to_vec();
+                    combined.extend(self.right_columns.iter().map(|_| String::new()));
+                    self.write(&combined, "Writing left outer row")?;
+                }
+            }
+            _ => {
+                for right in matches {
+                    self.matched_rows += 1;
+                    let mut combined = left.to_vec();
+                    combined.extend(self.right_values(right));
+                    self.write(&combined, "Writing joined row")?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn right_only(&mut self, right: &[String]) -> Result<()> {
+        if !matches!(self.kind, JoinKind::Right | JoinKind::Full) {
+            return Ok(());
+        }
+        let mut combined = vec![String::new(); self.left_width];
+        for (left_idx, right_idx) in &self.key_pairs {
+            combined[*left_idx] = right.get(*right_idx).cloned().unwrap_or_default();
+        }
+        combined.extend(self.right_values(right));
+        self.write(&combined, "Writing right outer row")
+    }
+
+    fn right_values<'r>(&self, right: &'r [String]) -> impl Iterator<Item = String> + use<'w, 'r> {
+        self.right_columns
+            .iter()
+            .map(move |idx| right.get(*idx).cloned().unwrap_or_default())
+    }
+
+    fn write(&mut self, row: &[String], context: &'static str) -> Result<()> {
+        self.writer.write_record(row).context(context)?;
+        self.output_rows += 1;
+        Ok(())
+    }
+}
+
+/// Loads the right input into memory keyed by its join columns, then streams the
+/// left input against it.
+fn hash_join(
+    left_reader: &mut csv::Reader<Box<dyn Read>>,
+    right_reader: &mut csv::Reader<Box<dyn Read>>,
+    left: &JoinSide<'_>,
+    right: &JoinSide<'_>,
+    right_match: RightMatch,
+    output: &mut JoinOutput<'_>,
+) -> Result<()> {
+    let mut right_lookup: HashMap<String, Vec<Vec<String>>> = HashMap::new();
+    for (row_idx, record) in right_reader.byte_records().enumerate() {
+        let location = (row_idx + 2).to_string();
+        let record = record.with_context(|| format!("Reading right row {location}"))?;
+        let decoded = right.prepare_row(&record, &location)?;
+        let key = right.typed_key(&decoded, &location)?;
+        let group = right_lookup.entry(hash_key(&key)).or_default();
+        add_right_match(group, decoded, right_match, &key, &location)?;
+    }
+
+    let mut matched_keys = HashSet::new();
+    for (row_idx, record) in left_reader.byte_records().enumerate() {
+        let location = (row_idx + 2).to_string();
+        let record = record.with_context(|| format!("Reading left row {location}"))?;
+        let decoded = left.prepare_row(&record, &location)?;
+        let key = hash_key(&left.typed_key(&decoded, &location)?);
+        match right_lookup.get(&key) {
+            Some(group) => {
+                output.left_row(&decoded, group)?;
+                matched_keys.insert(key);
+            }
+            None => output.left_row(&decoded, [])?,
+        }
+    }
+
+    for (key, group) in &right_lookup {
+        if !matched_keys.contains(key) {
+            for row in group {
+                output.right_only(row)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The index variants on each side whose leading columns are the join keys and
+/// whose key order and datatypes agree.
+struct MergePlan<'a> {
+    left: &'a IndexVariant,
+    right: &'a IndexVariant,
+    key_len: usize,
 }
 
+impl<'a> MergePlan<'a> {
+    fn resolve(
+        left_index: &'a CsvIndex,
+        right_index: &'a CsvIndex,
+        left_keys: &[String],
+        right_keys: &[String],
+    ) -> Result<Self> {
+        let key_len = left_keys.len();
+        let candidates = |index: &'a CsvIndex, keys: &[String], side: &str| {
+            let variants = index
+                .variants()
+                .iter()
+                .filter(|variant| variant.columns().starts_with(keys))
+                .collect::<Vec<_>>();
+            if variants.is_empty() {
+                Err(anyhow!(
+                    "The {side} index has no variant whose leading columns are the join keys ({}); build one with `csv-managed index --columns {}`",
+                    keys.join(", "),
+                    keys.join(",")
+                ))
+            } else {
+                Ok(variants)
+            }
+        };
+        let left_variants = candidates(left_index, left_keys, "left")?;
+        let right_variants = candidates(right_index, right_keys, "right")?;
+        left_variants
+            .iter()
+            .flat_map(|left| right_variants.iter().map(move |right| (*left, *right)))
+            .find(|(left, right)| {
+                left.directions()[..key_len] == right.directions()[..key_len]
+                    && left.column_types()[..key_len] == right.column_types()[..key_len]
+            })
+            .map(|(left, right)| MergePlan {
+                left,
+                right,
+                key_len,
+            })
+            .ok_or_else(|| {
+                anyhow!(
+                    "Left and right index variants order the join keys differently; rebuild both indexes with the same sort directions and key datatypes (pass --schema to `index`)"
+                )
+            })
+    }
+
+    fn directions(&self) -> &'a [SortDirection] {
+        &self.left.directions()[..self.key_len]
+    }
+
+    fn key_types(&self) -> &'a [ColumnType] {
+        &self.left.column_types()[..self.key_len]
+    }
+}
+
+/// Reads one input in index order, yielding prepared rows with their typed keys.
+struct IndexedSide<'a> {
+    side: JoinSide<'a>,
+    reader: csv::Reader<Box<dyn SeekRead>>,
+    offsets: Box<dyn Iterator<Item = u64> + 'a>,
+    directions: &'a [SortDirection],
+    record: ByteRecord,
+    last_key: Option<Vec<ComparableValue>>,
+    location: String,
+}
+
+type KeyedRow = (Vec<ComparableValue>, Vec<String>);
+
+impl<'a> IndexedSide<'a> {
+    fn open(
+        path: &Path,
+        variant: &'a IndexVariant,
+        side: JoinSide<'a>,
+        delimiter: u8,
+        has_headers: bool,
+        dialect: &Dialect,
+    ) -> Result<Self> {
+        if io_utils::is_dash(path) {
+            return Err(anyhow!(
+                "Merge joins read the {} input through its index and require a regular file",
+                side.label
+            ));
+        }
+        let key_len = side.key_indices.len();
+        Ok(Self {
+            reader: io_utils::open_seekable_csv_reader(path, delimiter, has_headers, dialect)?,
+            offsets: Box::new(variant.ordered_offsets()),
+            directions: &variant.directions()[..key_len],
+            record: ByteRecord::new(),
+            last_key: None,
+            location: String::new(),
+            side,
+        })
+    }
+
+    fn next_row(&mut self) -> Result<Option<KeyedRow>> {
+        let Some(offset) = self.offsets.next() else {
+            return Ok(None);
+        };
+        let mut position = Position::new();
+        position.set_byte(offset);
+        self.reader.seek(position)?;
+        if !self.reader.read_byte_record(&mut self.record)? {
+            return Err(anyhow!(
+                "The {} index points past the end of the file (byte offset {offset}); rebuild the index",
+                self.side.label
+            ));
+        }
+        self.location = format!("at byte offset {offset}");
+        let location = self.location.as_str();
+        let row = self.side.prepare_row(&self.record, location)?;
+        let key = self.side.typed_key(&row, location)?;
+        if let Some(previous) = &self.last_key
+            && compare_keys(previous, &key, self.directions) == Ordering::Greater
+        {
+            return Err(anyhow!(
+                "The {} index is out of order with the file {location}; rebuild the index with the same schema used for the join",
+                self.side.label
+            ));
+        }
+        self.last_key = Some(key.clone());
+        Ok(Some((key, row)))
+    }
+}
+
+fn compare_keys(
+    left: &[ComparableValue],
+    right: &[ComparableValue],
+    directions: &[SortDirection],
+) -> Ordering {
+    left.iter()
+        .zip(right)
+        .zip(directions)
+        .map(|((left, right), direction)| {
+            let ordering = left.cmp(right);
+            if direction.is_ascending() {
+                ordering
+            } else {
+                ordering.reverse()
+            }
+        })
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+/// Walks both inputs in index order. Only the right rows sharing the current key
+/// are held in memory at once.
+fn merge_join(
+    mut left: IndexedSide<'_>,
+    mut right: IndexedSide<'_>,
+    directions: &[SortDirection],
+    right_match: RightMatch,
+    output: &mut JoinOutput<'_>,
+) -> Result<()> {
+    let mut left_next = left.next_row()?;
+    let mut right_next = right.next_row()?;
+    loop {
+        let ordering = match (&left_next, &right_next) {
+            (None, None) => break,
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some((left_key, _)), Some((right_key, _))) => {
+                compare_keys(left_key, right_key, directions)
+            }
+        };
+        match ordering {
+            Ordering::Less => {
+                if let Some((_, row)) = left_next.take() {
+                    output.left_row(&row, [])?;
+                }
+                left_next = left.next_row()?;
+            }
+            Ordering::Greater => {
+                if let Some((_, row)) = right_next.take() {
+                    output.right_only(&row)?;
+                }
+                right_next = right.next_row()?;
+            }
+            Ordering::Equal => {
+                let Some((key, first)) = right_next.take() else {
+                    break;
+                };
+                let mut group = vec![first];
+                right_next = right.next_row()?;
+                while let Some((next_key, _)) = &right_next
+                    && compare_keys(next_key, &key, directions).is_eq()
+                {
+                    if let Some((_, row)) = right_next.take() {
+                        add_right_match(&mut group, row, right_match, &key, &right.location)?;
+                    }
+                    right_next = right.next_row()?;
+                }
+                while let Some((next_key, _)) = &left_next
+                    && compare_keys(next_key, &key, directions).is_eq()
+                {
+                    if let Some((_, row)) = left_next.take() {
+                        output.left_row(&row, &group)?;
+                    }
+                    left_next = left.next_row()?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Builds the joined header row and the right column indices it includes.
+///
+/// A right non-key column whose name matches a left column is prefixed with
+/// `right_prefix` and the matching left non-key column with `left_prefix`. Names
+/// that still collide fall back to `right_<name>_<n>`.
 fn build_output_headers(
     left_headers: &[String],
+    left_key_indices: &[usize],
     right_headers: &[String],
     right_key_indices: &[usize],
+    (left_prefix, right_prefix): (Option<&str>, Option<&str>),
 ) -> (Vec<String>, Vec<usize>) {
-    use std::collections::HashSet;
+    let right_names: HashSet<&str> = right_headers
+        .iter()
+        .enumerate()
+        .filter(|(idx, _)| !right_key_indices.contains(idx))
+        .map(|(_, name)| name.as_str())
+        .collect();
+    let left_names: HashSet<&str> = left_headers.iter().map(String::as_str).collect();
 
-    let mut headers = left_headers.to_vec();
+    let mut headers = left_headers
+        .iter()
+        .enumerate()
+        .map(|(idx, name)| match left_prefix {
+            Some(prefix)
+                if !left_key_indices.contains(&idx) && right_names.contains(name.as_str()) =>
+            {
+                format!("{prefix}{name}")
+            }
+            _ => name.clone(),
+        })
+        .collect::<Vec<_>>();
     let mut seen: HashSet<String> = headers.iter().cloned().collect();
     let mut right_columns = Vec::new();
 
@@ -347,14 +925,14 @@ fn build_output_headers(
         if right_key_indices.contains(&idx) {
             continue;
         }
-        let mut candidate = name.clone();
-        if seen.contains(&candidate) {
-            let mut counter = 1usize;
-            let base = candidate.clone();
-            while seen.contains(&candidate) {
-                candidate = format!("right_{base}_{counter}");
-                counter += 1;
-            }
+        let mut candidate = match right_prefix {
+            Some(prefix) if left_names.contains(name.as_str()) => format!("{prefix}{name}"),
+            _ => name.clone(),
+        };
+        let mut counter = 1usize;
+        while seen.contains(&candidate) {
+            candidate = format!("right_{name}_{counter}");
+            counter += 1;
         }
         seen.insert(candidate.clone());
         headers.push(candidate);
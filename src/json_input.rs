@@ -0,0 +1,331 @@
+//! JSON and JSON Lines inputs for `process`, `stats`, `schema probe/infer`, and
+//! `schema verify`.
+//!
+//! Files ending in `.json`, `.jsonl`, or `.ndjson` hold a stream of JSON values:
+//! one object per line, a single top-level array of objects, or both. Each
+//! object becomes one row:
+//!
+//! - Nested objects flatten to column names joined by `--json-separator`
+//!   (`{"address": {"city": "Oslo"}}` → `address.city`).
+//! - Arrays are joined into one `|`-separated cell, or with
+//!   `--json-arrays explode` repeat the row once per element (several arrays
+//!   in one object multiply out). Objects inside exploded arrays flatten under
+//!   the array's key.
+//! - Strings, numbers, and booleans keep their text; `null` becomes an empty cell.
+//!
+//! Columns are the union of every object's flattened keys in first-seen order,
+//! so the file is scanned once for columns before rows are streamed. Both passes
+//! parse one object at a time, elements of a top-level array included, so memory
+//! stays bounded by the largest object. Rows missing a column leave it empty, and
+//! an object whose keys flatten to the same column twice (`{"a.b": 1, "a": {"b":
+//! 2}}`) is an error. Values are text, so types are inferred the same way as for CSV.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use csv::ByteRecord;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::cli::{InputFormatArgs, JsonArrays};
+
+/// One flattened row: column names paired with cell text.
+type Fields = Vec<(String, String)>;
+
+/// Returns true when `path` names a JSON or JSON Lines file.
+pub fn is_json(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            ["json", "jsonl", "ndjson"]
+                .iter()
+                .any(|known| ext.eq_ignore_ascii_case(known))
+        })
+}
+
+/// Opens `path`, collecting its flattened column names, and returns them with an
+/// iterator over its rows.
+pub(crate) fn open(path: &Path, options: &InputFormatArgs) -> Result<(Vec<String>, JsonRecords)> {
+    let mut headers = Vec::new();
+    let mut positions = HashMap::new();
+    for object in JsonObjects::open(path)? {
+        let (number, object) = object?;
+        for row in flatten_object(&object, number, options)? {
+            for (name, _) in row {
+                if !positions.contains_key(&name) {
+                    positions.insert(name.clone(), headers.len());
+                    headers.push(name);
+                }
+            }
+        }
+    }
+    let records = JsonRecords {
+        objects: JsonObjects::open(path)?,
+        positions,
+        width: headers.len(),
+        options: options.clone(),
+        pending: Vec::new().into_iter(),
+    };
+    Ok((headers, records))
+}
+
+/// Rows of a JSON input as UTF-8 byte records aligned to the discovered columns.
+pub(crate) struct JsonRecords {
+    objects: JsonObjects,
+    positions: HashMap<String, usize>,
+    width: usize,
+    options: InputFormatArgs,
+    pending: std::vec::IntoIter<ByteRecord>,
+}
+
+impl JsonRecords {
+    fn records_for(&self, object: &Value, number: usize) -> Result<Vec<ByteRecord>> {
+        flatten_object(object, number, &self.options)?
+            .into_iter()
+            .map(|fields| {
+                let mut cells = vec![String::new(); self.width];
+                for (name, text) in fields {
+                    let idx = self.positions.get(&name).ok_or_else(|| {
+                        anyhow!("JSON object {number} has column '{name}' that was not seen while scanning the file")
+                    })?;
+                    cells[*idx] = text;
+                }
+                Ok(cells.iter().collect())
+            })
+            .collect()
+    }
+}
+
+impl Iterator for JsonRecords {
+    type Item = Result<ByteRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(record) = self.pending.next() {
+                return Some(Ok(record));
+            }
+            let (number, object) = match self.objects.next()? {
+                Ok(next) => next,
+                Err(err) => return Some(Err(err)),
+            };
+            match self.records_for(&object, number) {
+                Ok(records) => self.pending = records.into_iter(),
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+/// Top-level JSON values of a file, with the elements of top-level arrays
+/// parsed and yielded one by one. Each object is numbered from 1.
+struct JsonObjects {
+    path: PathBuf,
+    reader: BufReader<File>,
+    array: ArrayState,
+    count: usize,
+}
+
+/// Position relative to a top-level array.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ArrayState {
+    Outside,
+    Opened,
+    AfterElement,
+}
+
+impl JsonObjects {
+    fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path).with_context(|| format!("Opening JSON file {path:?}"))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            reader: BufReader::new(file),
+            array: ArrayState::Outside,
+            count: 0,
+        })
+    }
+
+    fn next_value(&mut self) -> Result<Option<Value>> {
+        loop {
+            let next = self.peek()?;
+            match (self.array, next) {
+                (ArrayState::Outside, None) => return Ok(None),
+                (ArrayState::Outside, Some(b'[')) => {
+                    self.reader.consume(1);
+                    self.array = ArrayState::Opened;
+                }
+                (ArrayState::Opened | ArrayState::AfterElement, Some(b']')) => {
+                    self.reader.consume(1);
+                    self.array = ArrayState::Outside;
+                }
+                (ArrayState::AfterElement, Some(b',')) => {
+                    self.reader.consume(1);
+                    if matches!(self.peek()?, Some(b']') | None) {
+                        bail!("expected a value after ','");
+                    }
+                    return self.parse_value().map(Some);
+                }
+                (ArrayState::AfterElement, _) => {
+                    bail!("expected ',' or ']' after an array element")
+                }
+                (ArrayState::Opened, None) => bail!("unterminated array"),
+                (ArrayState::Opened, Some(_)) => {
+                    self.array = ArrayState::AfterElement;
+                    return self.parse_value().map(Some);
+                }
+                (ArrayState::Outside, Some(_)) => return self.parse_value().map(Some),
+            }
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Value> {
+        let mut deserializer = serde_json::Deserializer::from_reader(&mut self.reader);
+        Ok(Value::deserialize(&mut deserializer)?)
+    }
+
+    /// Skips whitespace and returns the next byte without consuming it.
+    fn peek(&mut self) -> Result<Option<u8>> {
+        loop {
+            let buffer = self.reader.fill_buf()?;
+            if buffer.is_empty() {
+                return Ok(None);
+            }
+            let skipped = buffer.iter().position(|byte| !byte.is_ascii_whitespace());
+            let next = skipped.map(|idx| buffer[idx]);
+            let len = buffer.len();
+            self.reader.consume(skipped.unwrap_or(len));
+            if next.is_some() {
+                return Ok(next);
+            }
+        }
+    }
+}
+
+impl Iterator for JsonObjects {
+    type Item = Result<(usize, Value)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_value() {
+            Ok(value) => {
+                let value = value?;
+                self.count += 1;
+                Some(Ok((self.count, value)))
+            }
+            Err(err) => Some(Err(err.context(format!(
+                "Parsing JSON after object {} in {:?}",
+                self.count, self.path
+            )))),
+        }
+    }
+}
+
+fn flatten_object(object: &Value, number: usize, options: &InputFormatArgs) -> Result<Vec<Fields>> {
+    let rows = match object {
+        Value::Object(map) if map.is_empty() => vec![Fields::new()],
+        Value::Object(_) => flatten(object, "", options),
+        _ => bail!("JSON value {number} is not an object; each row must be a JSON object"),
+    };
+    for row in &rows {
+        let mut names = HashSet::with_capacity(row.len());
+        if let Some((name, _)) = row.iter().find(|(name, _)| !names.insert(name)) {
+            bail!(
+                "JSON object {number} has more than one value for column '{name}' once its keys are flattened"
+            );
+        }
+    }
+    Ok(rows)
+}
+
+/// Expands `value` under the column name `prefix` into one or more rows.
+fn flatten(value: &Value, prefix: &str, options: &InputFormatArgs) -> Vec<Fields> {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            let mut rows = vec![Fields::new()];
+            for (key, child) in map {
+                let name = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{prefix}{}{key}", options.json_separator)
+                };
+                let expanded = flatten(child, &name, options);
+                rows = rows
+                    .iter()
+                    .flat_map(|row| {
+                        expanded.iter().map(move |extra| {
+                            let mut combined = row.clone();
+                            combined.extend(extra.iter().cloned());
+                            combined
+                        })
+                    })
+                    .collect();
+            }
+            rows
+        }
+        Value::Array(items) if options.json_arrays == JsonArrays::Explode && !items.is_empty() => {
+            items
+                .iter()
+                .flat_map(|item| flatten(item, prefix, options))
+                .collect()
+        }
+        Value::Array(items) if options.json_arrays == JsonArrays::Join => {
+            let joined = items.iter().map(cell_text).collect::<Vec<_>>().join("|");
+            vec![vec![(prefix.to_string(), joined)]]
+        }
+        other => vec![vec![(prefix.to_string(), cell_text(other))]],
+    }
+}
+
+/// Text of a single cell: strings unquoted, `null` and empty containers empty,
+/// nested values as compact JSON.
+fn cell_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        Value::Bool(flag) => flag.to_string(),
+        Value::Number(number) => number.to_string(),
+        Value::Array(items) if items.is_empty() => String::new(),
+        Value::Object(map) if map.is_empty() => String::new(),
+        nested => nested.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flatten_nests_keys_and_joins_or_explodes_arrays() {
+        let object = serde_json::json!({
+            "id": 7,
+            "address": {"city": "Oslo", "zip": null},
+            "tags": ["a", "b"],
+            "items": [{"sku": "x", "qty": 1}, {"sku": "y", "qty": 2}]
+        });
+        let joined = flatten(&object, "", &InputFormatArgs::default());
+        assert_eq!(joined.len(), 1);
+        let row = &joined[0];
+        assert!(row.contains(&("address.city".to_string(), "Oslo".to_string())));
+        assert!(row.contains(&("address.zip".to_string(), String::new())));
+        assert!(row.contains(&("tags".to_string(), "a|b".to_string())));
+        assert!(row.contains(&(
+            "items".to_string(),
+            r#"{"sku":"x","qty":1}|{"sku":"y","qty":2}"#.to_string()
+        )));
+
+        let options = InputFormatArgs {
+            json_separator: "_".to_string(),
+            json_arrays: JsonArrays::Explode,
+            ..InputFormatArgs::default()
+        };
+        let exploded = flatten(&object, "", &options);
+        assert_eq!(exploded.len(), 4);
+        assert!(exploded[0].contains(&("address_city".to_string(), "Oslo".to_string())));
+        assert!(exploded[0].contains(&("items_sku".to_string(), "x".to_string())));
+        assert!(exploded[3].contains(&("tags".to_string(), "b".to_string())));
+        assert!(exploded[3].contains(&("items_qty".to_string(), "2".to_string())));
+    }
+}
@@ -0,0 +1,166 @@
+//! JSON and JSON Lines output for `process`.
+//!
+//! With `--output-format json` the rows are written as one array of objects, and
+//! with `--output-format jsonl` as one object per line. Objects are keyed by the
+//! output column names. Cells are rendered from the typed row values rather than
+//! the raw text:
+//!
+//! - Integer and Float columns become JSON numbers, Boolean columns JSON booleans.
+//! - Decimal and Currency columns become exact strings (`"12.50"`), or bare
+//!   numbers with `--json-decimals number`.
+//! - Dates, times, GUIDs, and strings become JSON strings.
+//! - Empty cells become `null`.
+//!
+//! Derived columns keep the type their expression evaluates to.
+
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use encoding_rs::Encoding;
+use evalexpr::Value as EvalValue;
+
+use crate::{
+    cli::JsonDecimals,
+    data::Value,
+    io_utils::{self, FinishWrite},
+};
+
+/// Streams rows to a JSON array or JSON Lines file (stdout when `None` or `-`).
+pub(crate) struct JsonOutput {
+    writer: Box<dyn FinishWrite>,
+    keys: Vec<String>,
+    lines: bool,
+    rows: usize,
+}
+
+impl JsonOutput {
+    pub(crate) fn open(
+        path: Option<&Path>,
+        encoding: &'static Encoding,
+        headers: &[String],
+        lines: bool,
+    ) -> Result<Self> {
+        Ok(JsonOutput {
+            writer: io_utils::open_output_writer(path, encoding)?,
+            keys: headers.iter().map(|header| quote(header)).collect(),
+            lines,
+            rows: 0,
+        })
+    }
+
+    /// Writes one row whose cells were rendered with [`value_token`] or [`eval_token`].
+    pub(crate) fn write_record(&mut self, tokens: &[String]) -> Result<()> {
+        let mut line = String::new();
+        if !self.lines {
+            line.push_str(if self.rows == 0 { "[\n" } else { ",\n" });
+        }
+        line.push('{');
+        for (idx, (key, token)) in self.keys.iter().zip(tokens).enumerate() {
+            if idx > 0 {
+                line.push(',');
+            }
+            line.push_str(key);
+            line.push(':');
+            line.push_str(token);
+        }
+        line.push('}');
+        if self.lines {
+            line.push('\n');
+        }
+        self.rows += 1;
+        self.writer
+            .write_all(line.as_bytes())
+            .context("Writing output row")
+    }
+
+    pub(crate) fn finish(mut self) -> Result<()> {
+        if !self.lines {
+            let closing = if self.rows == 0 { "[]\n" } else { "\n]\n" };
+            self.writer
+                .write_all(closing.as_bytes())
+                .context("Writing output row")?;
+        }
+        self.writer.finish().context("Finishing output")
+    }
+}
+
+/// Renders a column value as a JSON token.
+pub(crate) fn value_token(raw: &str, typed: Option<&Value>, decimals: JsonDecimals) -> String {
+    let Some(value) = typed else {
+        return if raw.trim().is_empty() {
+            "null".to_string()
+        } else {
+            quote(raw)
+        };
+    };
+    match value {
+        Value::Integer(i) => i.to_string(),
+        Value::Float(f) => serde_json::Value::from(*f).to_string(),
+        Value::Boolean(b) => b.to_string(),
+        Value::Decimal(_) | Value::Currency(_) if decimals == JsonDecimals::Number => {
+            value.as_display()
+        }
+        Value::String(s) => quote(s),
+        other => quote(&other.as_display()),
+    }
+}
+
+/// Renders a derived expression result as a JSON token.
+pub(crate) fn eval_token(value: &EvalValue) -> String {
+    match value {
+        EvalValue::String(s) => quote(s),
+        EvalValue::Int(i) => i.to_string(),
+        EvalValue::Float(f) => serde_json::Value::from(*f).to_string(),
+        EvalValue::Boolean(b) => b.to_string(),
+        EvalValue::Tuple(values) => quote(
+            &values
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+                .join("|"),
+        ),
+        EvalValue::Empty => "null".to_string(),
+    }
+}
+
+fn quote(text: &str) -> String {
+    serde_json::Value::from(text).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{CurrencyValue, parse_typed_value};
+    use crate::schema::ColumnType;
+
+    #[test]
+    fn value_token_keeps_types_and_nulls_empty_cells() {
+        let typed = |raw: &str, ty: &ColumnType| parse_typed_value(raw, ty).expect("parse");
+        let integer = typed("42", &ColumnType::Integer);
+        assert_eq!(
+            value_token("42", integer.as_ref(), JsonDecimals::String),
+            "42"
+        );
+        let boolean = typed("yes", &ColumnType::Boolean);
+        assert_eq!(
+            value_token("yes", boolean.as_ref(), JsonDecimals::String),
+            "true"
+        );
+        assert_eq!(value_token("", None, JsonDecimals::String), "null");
+        assert_eq!(
+            value_token("say \"hi\"", None, JsonDecimals::String),
+            r#""say \"hi\"""#
+        );
+
+        let amount = Value::Currency(CurrencyValue::parse("$12.50").expect("currency"));
+        assert_eq!(
+            value_token("$12.50", Some(&amount), JsonDecimals::String),
+            "\"12.50\""
+        );
+        assert_eq!(
+            value_token("$12.50", Some(&amount), JsonDecimals::Number),
+            "12.50"
+        );
+    }
+}
@@ -13,29 +13,50 @@
 //! - `schema` — probe, infer, verify, columns, or create schemas
 //! - `index` — build B-tree index files for sort acceleration
 //! - `process` — filter, sort, project, derive, and transform CSV data
+//! - `aggregate` — group rows and compute typed per-group aggregates
 //! - `append` — concatenate multiple CSV files with header validation
 //! - `stats` — summary statistics and frequency analysis
+//! - `join` — hash join two CSV inputs, streaming the left side (stdin supported)
 //! - `install` — self-install via `cargo install`
 
+pub mod aggregate;
 pub mod append;
 pub mod cli;
 pub mod columns;
+pub mod compression;
 pub mod data;
 pub mod derive;
+pub mod dialect;
+pub mod distinct;
+pub mod excel_input;
 pub mod expr;
+pub mod external_sort;
 pub mod filter;
+pub mod fixed_width;
 pub mod frequency;
 pub mod index;
+pub mod inputs;
 pub mod install;
 pub mod io_utils;
 pub mod join;
+pub mod json_input;
+pub mod json_output;
+pub mod lookup;
+pub mod malformed;
+pub mod parquet_input;
+pub mod parquet_output;
 pub mod process;
 pub mod rows;
+pub mod sample;
 pub mod schema;
 pub mod schema_cmd;
+pub mod split;
 pub mod stats;
 pub mod table;
+pub mod top_n;
+pub mod type_errors;
 pub mod verify;
+pub mod window;
 
 use std::{env, ffi::OsString, sync::OnceLock, time::Instant};
 
@@ -44,7 +65,10 @@ use chrono::{SecondsFormat, Utc};
 use clap::Parser;
 use log::{LevelFilter, debug, error, info};
 
-use crate::cli::{Cli, Commands};
+use crate::{
+    cli::{Cli, Commands},
+    malformed::MalformedSummary,
+};
 
 static LOGGER: OnceLock<()> = OnceLock::new();
 
@@ -64,10 +88,11 @@ pub fn run() -> Result<()> {
     match cli.command {
         Commands::Index(args) => run_operation("index", || handle_index(&args)),
         Commands::Schema(args) => run_operation("schema", || schema_cmd::execute(&args)),
-        Commands::Process(args) => run_operation("process", || process::execute(&args)),
-        Commands::Append(args) => run_operation("append", || append::execute(&args)),
-        Commands::Stats(args) => run_operation("stats", || stats::execute(&args)),
-        // Commands::Join(args) => run_operation("join", || join::execute(&args)),
+        Commands::Process(args) => run_counted_operation("process", || process::execute(&args)),
+        Commands::Aggregate(args) => run_operation("aggregate", || aggregate::execute(&args)),
+        Commands::Append(args) => run_counted_operation("append", || append::execute(&args)),
+        Commands::Stats(args) => run_counted_operation("stats", || stats::execute(&args)),
+        Commands::Join(args) => run_operation("join", || join::execute(&args)),
         Commands::Install(args) => run_operation("install", || install::execute(&args)),
     }
 }
@@ -95,10 +120,23 @@ where
 fn run_operation<F>(name: &str, op: F) -> Result<()>
 where
     F: FnOnce() -> Result<()>,
+{
+    run_counted_operation(name, || op().map(|()| MalformedSummary::default()))
+}
+
+/// Runs a command that reports the malformed rows it repaired or dropped.
+fn run_counted_operation<F>(name: &str, op: F) -> Result<()>
+where
+    F: FnOnce() -> Result<MalformedSummary>,
 {
     let start_clock = Utc::now();
     let start_instant = Instant::now();
     let result = op();
+    if let Ok(malformed_rows) = &result
+        && !malformed_rows.is_empty()
+    {
+        info!("Malformed rows: {malformed_rows}");
+    }
     let end_clock = Utc::now();
     let duration_secs = start_instant.elapsed().as_secs_f64();
     let start_str = start_clock.to_rfc3339_opts(SecondsFormat::Millis, true);
@@ -113,23 +151,78 @@ where
         ),
     }
 
-    result
+    result.map(|_| ())
 }
 
 fn handle_index(args: &cli::IndexArgs) -> Result<()> {
     let delimiter = io_utils::resolve_input_delimiter(&args.input, args.delimiter);
     let encoding = io_utils::resolve_encoding(args.input_encoding.as_deref())?;
-    info!(
-        "Building index for '{}' using delimiter '{}'",
-        args.input.display(),
-        printable_delimiter(delimiter)
-    );
     let schema = match &args.schema {
         Some(path) => Some(
             schema::Schema::load(path).with_context(|| format!("Loading schema from {path:?}"))?,
         ),
         None => None,
     };
+    let dialect = args.dialect.apply(
+        schema
+            .as_ref()
+            .and_then(|schema| schema.dialect.clone())
+            .unwrap_or_default(),
+    );
+    dialect.validate()?;
+    let index = if args.update {
+        info!(
+            "Updating index {:?} with rows appended to '{}'",
+            args.index,
+            args.input.display()
+        );
+        let existing = index::CsvIndex::load(&args.index)
+            .with_context(|| format!("Loading index {:?} to update", args.index))?;
+        let previous_rows = existing.row_count();
+        let index = existing
+            .update(&args.input, schema.as_ref(), delimiter, encoding, &dialect)
+            .with_context(|| format!("Updating index for {:?}", args.input))?;
+        info!(
+            "Indexed {} appended row(s)",
+            index.row_count() - previous_rows
+        );
+        index
+    } else {
+        info!(
+            "Building index for '{}' using delimiter '{}'",
+            args.input.display(),
+            printable_delimiter(delimiter)
+        );
+        let definitions = index_definitions(args)?;
+        debug!("Index definitions: {:?}", definitions.len());
+        index::CsvIndex::build(
+            &args.input,
+            &definitions,
+            schema.as_ref(),
+            args.limit,
+            delimiter,
+            encoding,
+            &dialect,
+        )
+        .with_context(|| format!("Building index for {:?}", args.input))?
+    };
+    let row_count = index.row_count();
+    index
+        .save(&args.index)
+        .with_context(|| format!("Writing index to {:?}", args.index))?;
+    info!(
+        "Index with {} variant(s) for {} row(s) written to {:?}",
+        index.variants().len(),
+        row_count,
+        args.index
+    );
+    for variant in index.variants() {
+        info!("  • {}", variant.describe());
+    }
+    Ok(())
+}
+
+fn index_definitions(args: &cli::IndexArgs) -> Result<Vec<index::IndexDefinition>> {
     let mut definitions = Vec::new();
     for spec in &args.specs {
         let definition = index::IndexDefinition::parse(spec)
@@ -153,30 +246,7 @@ fn handle_index(args: &cli::IndexArgs) -> Result<()> {
             .context("Parsing --columns for index build")?;
         definitions.push(definition);
     }
-    debug!("Index definitions: {:?}", definitions.len());
-    let index = index::CsvIndex::build(
-        &args.input,
-        &definitions,
-        schema.as_ref(),
-        args.limit,
-        delimiter,
-        encoding,
-    )
-    .with_context(|| format!("Building index for {:?}", args.input))?;
-    let row_count = index.row_count();
-    index
-        .save(&args.index)
-        .with_context(|| format!("Writing index to {:?}", args.index))?;
-    info!(
-        "Index with {} variant(s) for {} row(s) written to {:?}",
-        index.variants().len(),
-        row_count,
-        args.index
-    );
-    for variant in index.variants() {
-        info!("  • {}", variant.describe());
-    }
-    Ok(())
+    Ok(definitions)
 }
 
 pub(crate) fn printable_delimiter(delimiter: u8) -> String {
@@ -0,0 +1,306 @@
+//! Schema-declared lookup enrichment for `process`.
+//!
+//! A column in a `-schema.yml` may declare a `lookup` block naming a reference
+//! table, the table column to match on, and the table columns to bring back:
+//!
+//! ```yaml
+//! - name: country_code
+//!   datatype: String
+//!   lookup:
+//!     table: countries.csv
+//!     key: iso2
+//!     columns: [country_name, region]
+//! ```
+//!
+//! Each table is loaded into memory once. The requested columns are appended to
+//! every row after datatype mappings and replacements, before typing and
+//! filters, so filters, derives, windows, and sorts can all use them. Keys match
+//! as typed values using the declaring column's datatype. A blank key, or one
+//! absent from the table, is a miss and is handled by `--on-miss`.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow};
+
+use crate::{
+    cli::{DialectArgs, OnMiss},
+    data::parse_typed_value,
+    io_utils, join,
+    schema::{ColumnMeta, ColumnType, LookupSpec, Schema},
+};
+
+/// Loaded lookup tables for a process run.
+#[derive(Debug)]
+pub(crate) struct Lookups {
+    tables: Vec<LookupTable>,
+    on_miss: OnMiss,
+}
+
+#[derive(Debug)]
+struct LookupTable {
+    column: String,
+    key_index: usize,
+    key_type: ColumnType,
+    path: PathBuf,
+    rows: HashMap<String, Vec<String>>,
+    defaults: Vec<String>,
+}
+
+impl Lookups {
+    /// Loads every lookup declared in `schema` and appends the enriched columns to
+    /// `schema` and `headers`. Relative table paths resolve against `base_dir`.
+    pub(crate) fn load(
+        schema: &mut Schema,
+        headers: &mut Vec<String>,
+        base_dir: &Path,
+        on_miss: OnMiss,
+    ) -> Result<Option<Self>> {
+        let declared = schema
+            .columns
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, column)| column.lookup.clone().map(|spec| (idx, spec)))
+            .collect::<Vec<_>>();
+        if declared.is_empty() {
+            return Ok(None);
+        }
+        let mut tables = Vec::with_capacity(declared.len());
+        for (key_index, spec) in declared {
+            let column = &schema.columns[key_index];
+            let name = column.name.clone();
+            let key_type = column.datatype.clone();
+            let (table, metas) = LookupTable::load(&spec, &name, key_index, key_type, base_dir)
+                .with_context(|| format!("Loading lookup for column '{name}'"))?;
+            for meta in metas {
+                if headers.contains(&meta.name) {
+                    return Err(anyhow!(
+                        "Lookup column '{}' for '{name}' conflicts with an existing column; set a lookup `prefix` to rename it",
+                        meta.name
+                    ));
+                }
+                headers.push(meta.name.clone());
+                schema.columns.push(meta);
+            }
+            tables.push(table);
+        }
+        Ok(Some(Self { tables, on_miss }))
+    }
+
+    /// Appends the enriched values for `row`, which holds the input columns after
+    /// mappings and replacements.
+    pub(crate) fn enrich(&self, row: &mut Vec<String>) -> Result<()> {
+        for table in &self.tables {
+            let raw = row.get(table.key_index).map(String::as_str).unwrap_or("");
+            let key = table.key(raw)?;
+            match key.as_ref().and_then(|key| table.rows.get(key)) {
+                Some(values) => row.extend(values.iter().cloned()),
+                None => match self.on_miss {
+                    OnMiss::Empty => row.extend(table.defaults.iter().map(|_| String::new())),
+                    OnMiss::Default => row.extend(table.defaults.iter().cloned()),
+                    OnMiss::Error => {
+                        return Err(anyhow!(
+                            "No match in lookup table {:?} for {} = '{raw}'",
+                            table.path,
+                            table.column
+                        ));
+                    }
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+impl LookupTable {
+    fn load(
+        spec: &LookupSpec,
+        column: &str,
+        key_index: usize,
+        key_type: ColumnType,
+        base_dir: &Path,
+    ) -> Result<(Self, Vec<ColumnMeta>)> {
+        if spec.columns.is_empty() {
+            return Err(anyhow!(
+                "Lookup must list at least one column to bring back"
+            ));
+        }
+        let path = base_dir.join(&spec.table);
+        let schema_path = spec.schema.as_ref().map(|schema| base_dir.join(schema));
+        let delimiter = io_utils::resolve_input_delimiter(&path, None);
+        let encoding = io_utils::resolve_encoding(spec.encoding.as_deref())?;
+        let schema = join::load_schema(
+            &path,
+            schema_path.as_deref(),
+            delimiter,
+            encoding,
+            &DialectArgs::default(),
+        )?;
+        let dialect = schema.dialect.clone().unwrap_or_default();
+
+        let index_of = |name: &str| {
+            schema
+                .column_index(name)
+                .ok_or_else(|| anyhow!("Column '{name}' not found in lookup table {path:?}"))
+        };
+        let table_key = index_of(&spec.key)?;
+        let value_indices = spec
+            .columns
+            .iter()
+            .map(|name| index_of(name))
+            .collect::<Result<Vec<_>>>()?;
+        for name in spec.defaults.keys() {
+            if !spec.columns.contains(name) {
+                return Err(anyhow!(
+                    "Lookup default '{name}' is not one of the lookup columns"
+                ));
+            }
+        }
+
+        let prefix = spec.prefix.as_deref().unwrap_or("");
+        let mut metas = Vec::with_capacity(value_indices.len());
+        let mut defaults = Vec::with_capacity(value_indices.len());
+        for (name, idx) in spec.columns.iter().zip(&value_indices) {
+            let datatype = schema.columns[*idx].datatype.clone();
+            let default = spec.defaults.get(name).cloned().unwrap_or_default();
+            parse_typed_value(&default, &datatype).with_context(|| {
+                format!(
+                    "Lookup default for '{name}' is not a valid {}",
+                    datatype.describe()
+                )
+            })?;
+            defaults.push(default);
+            metas.push(ColumnMeta {
+                name: format!("{prefix}{name}"),
+                datatype,
+                rename: None,
+                value_replacements: Vec::new(),
+                datatype_mappings: Vec::new(),
+                lookup: None,
+                start: None,
+                width: None,
+            });
+        }
+
+        let mut table = LookupTable {
+            column: column.to_string(),
+            key_index,
+            key_type,
+            path,
+            rows: HashMap::new(),
+            defaults,
+        };
+        let mut reader = io_utils::open_csv_reader_from_path(
+            &table.path,
+            delimiter,
+            schema.expects_headers(),
+            &dialect,
+        )?;
+        if schema.expects_headers() {
+            let headers = io_utils::reader_headers(&mut reader, encoding)?;
+            schema
+                .validate_headers(&headers)
+                .with_context(|| format!("Validating headers for {:?}", table.path))?;
+        }
+        for (row_idx, record) in reader.byte_records().enumerate() {
+            let row_number = row_idx + 2;
+            let record = record.with_context(|| format!("Reading lookup row {row_number}"))?;
+            let mut decoded = io_utils::decode_record(&record, encoding)?;
+            if schema.has_transformations() {
+                schema
+                    .apply_transformations_to_row(&mut decoded)
+                    .with_context(|| {
+                        format!("Applying datatype mappings to lookup row {row_number}")
+                    })?;
+            }
+            schema.apply_replacements_to_row(&mut decoded);
+            let raw_key = decoded.get(table_key).map(String::as_str).unwrap_or("");
+            let Some(key) = table
+                .key(raw_key)
+                .with_context(|| format!("Lookup row {row_number} in {:?}", table.path))?
+            else {
+                continue;
+            };
+            let values = value_indices
+                .iter()
+                .map(|idx| decoded.get(*idx).cloned().unwrap_or_default())
+                .collect();
+            if table.rows.insert(key, values).is_some() {
+                return Err(anyhow!(
+                    "Lookup table {:?} has more than one row for key '{raw_key}' (row {row_number})",
+                    table.path
+                ));
+            }
+        }
+        Ok((table, metas))
+    }
+
+    /// Normalizes a key through the declaring column's datatype; blank keys yield `None`.
+    fn key(&self, raw: &str) -> Result<Option<String>> {
+        let parsed = parse_typed_value(raw.trim(), &self.key_type).with_context(|| {
+            format!(
+                "Lookup key '{raw}' for column '{}' is not a valid {}",
+                self.column,
+                self.key_type.describe()
+            )
+        })?;
+        Ok(parsed.map(|value| value.as_display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    fn schema_with_lookup(defaults: BTreeMap<String, String>) -> Schema {
+        let mut schema = Schema::from_headers(&["id".to_string(), "code".to_string()]);
+        schema.columns[1].datatype = ColumnType::Integer;
+        schema.columns[1].lookup = Some(LookupSpec {
+            table: PathBuf::from("codes.csv"),
+            key: "code".to_string(),
+            columns: vec!["label".to_string()],
+            schema: None,
+            prefix: Some("code_".to_string()),
+            defaults,
+            encoding: None,
+        });
+        schema
+    }
+
+    #[test]
+    fn enrich_matches_typed_keys_and_applies_miss_policy() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        std::fs::write(temp.path().join("codes.csv"), "code,label\n1,one\n2,two\n")
+            .expect("write table");
+
+        let mut schema =
+            schema_with_lookup(BTreeMap::from([("label".to_string(), "n/a".to_string())]));
+        let mut headers = schema.headers();
+        let lookups = Lookups::load(&mut schema, &mut headers, temp.path(), OnMiss::Default)
+            .expect("load")
+            .expect("lookups declared");
+        assert_eq!(headers, vec!["id", "code", "code_label"]);
+        assert_eq!(schema.columns.len(), 3);
+
+        let mut row = vec!["a".to_string(), "02".to_string()];
+        lookups.enrich(&mut row).expect("enrich");
+        assert_eq!(row[2], "two");
+
+        let mut row = vec!["b".to_string(), "9".to_string()];
+        lookups.enrich(&mut row).expect("enrich");
+        assert_eq!(row[2], "n/a");
+
+        let mut schema = schema_with_lookup(BTreeMap::new());
+        let mut headers = schema.headers();
+        let strict = Lookups::load(&mut schema, &mut headers, temp.path(), OnMiss::Error)
+            .expect("load")
+            .expect("lookups declared");
+        let mut row = vec!["c".to_string(), "9".to_string()];
+        let err = strict.enrich(&mut row).expect_err("miss is an error");
+        assert!(err.to_string().contains("code = '9'"), "{err}");
+    }
+}
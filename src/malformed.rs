@@ -0,0 +1,254 @@
+//! Recovery from CSV rows whose field count differs from the header.
+//!
+//! CSV readers reject ragged rows, so a single bad line stops a whole run.
+//! `--on-malformed` chooses what happens instead:
+//!
+//! - `fail` (default): stop with the reader's error.
+//! - `skip`: drop the row.
+//! - `pad`: fill short rows with empty fields and truncate long ones.
+//! - `quarantine`: drop the row and append it to `--reject-file` as its
+//!   `source`, `line`, and `reason`, followed by the row's raw fields as further
+//!   columns.
+//!
+//! The expected width is the header row's, or the first row's for headerless
+//! input. Counts are collected across every input of a command and returned by
+//! [`MalformedRows::finish`], so the command can report them once.
+
+use std::{
+    cell::{Cell, RefCell},
+    fmt,
+    path::Path,
+    rc::Rc,
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use csv::ByteRecord;
+use encoding_rs::UTF_8;
+use log::debug;
+
+use crate::{
+    cli::{MalformedArgs, OnMalformed},
+    dialect::Dialect,
+    io_utils::{self, ByteRecords, FinishWrite},
+};
+
+/// Rows repaired or dropped during the current command.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MalformedSummary {
+    pub skipped: usize,
+    pub padded: usize,
+    pub truncated: usize,
+    pub quarantined: usize,
+}
+
+impl MalformedSummary {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl fmt::Display for MalformedSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts = [
+            (self.skipped, "skipped"),
+            (self.padded, "padded"),
+            (self.truncated, "truncated"),
+            (self.quarantined, "quarantined"),
+        ]
+        .into_iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, label)| format!("{count} {label}"))
+        .collect::<Vec<_>>();
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// The reject file, taken when it is finished.
+type RejectWriter = Rc<RefCell<Option<csv::Writer<Box<dyn FinishWrite>>>>>;
+
+/// The malformed-row policy for one command, shared by all of its inputs.
+/// Clones share the reject file and the counts.
+#[derive(Clone)]
+pub(crate) struct MalformedRows {
+    policy: OnMalformed,
+    reject: Option<RejectWriter>,
+    summary: Rc<Cell<MalformedSummary>>,
+}
+
+impl MalformedRows {
+    /// Returns `None` for the default `fail` policy, which keeps readers strict.
+    pub(crate) fn from_args(args: &MalformedArgs) -> Result<Option<Self>> {
+        let reject = match (args.on_malformed, &args.reject_file) {
+            (OnMalformed::Quarantine, None) => {
+                bail!("--on-malformed quarantine requires --reject-file")
+            }
+            (OnMalformed::Quarantine, Some(path)) => {
+                // Rejected rows keep their own field count after the leading columns.
+                let mut writer = Dialect::default()
+                    .writer_builder(b',')
+                    .flexible(true)
+                    .from_writer(io_utils::open_output_writer(Some(path), UTF_8)?);
+                writer
+                    .write_record(["source", "line", "reason"])
+                    .with_context(|| format!("Writing reject file header to {path:?}"))?;
+                Some(Rc::new(RefCell::new(Some(writer))))
+            }
+            (_, Some(_)) => bail!("--reject-file requires --on-malformed quarantine"),
+            (OnMalformed::Fail, None) => return Ok(None),
+            (_, None) => None,
+        };
+        Ok(Some(Self {
+            policy: args.on_malformed,
+            reject,
+            summary: Rc::default(),
+        }))
+    }
+
+    /// The same policy for sampling rows during schema inference: rows the
+    /// command would quarantine are skipped, and nothing is counted, so the
+    /// command's own read reports each row once.
+    pub(crate) fn for_inference(&self) -> Self {
+        Self {
+            policy: match self.policy {
+                OnMalformed::Quarantine => OnMalformed::Skip,
+                policy => policy,
+            },
+            reject: None,
+            summary: Rc::default(),
+        }
+    }
+
+    /// Applies the policy to `records` read from `source`. `width` is the
+    /// header's field count, or `None` to take it from the first row.
+    pub(crate) fn wrap(
+        &self,
+        source: &Path,
+        width: Option<usize>,
+        records: ByteRecords,
+    ) -> ByteRecords {
+        Box::new(CheckedRecords {
+            rows: self.clone(),
+            source: source.display().to_string(),
+            width,
+            inner: records,
+        })
+    }
+
+    /// Finishes the reject file and returns the rows handled by every input.
+    pub(crate) fn finish(&self) -> Result<MalformedSummary> {
+        if let Some(writer) = self.reject.as_ref().and_then(|reject| reject.take()) {
+            io_utils::finish_csv_writer(writer).context("Finishing reject file")?;
+        }
+        Ok(self.summary.get())
+    }
+
+    /// Repairs `record`, or returns `None` when it is dropped.
+    fn handle(
+        &self,
+        source: &str,
+        width: usize,
+        mut record: ByteRecord,
+    ) -> Result<Option<ByteRecord>> {
+        let line = record.position().map_or(0, csv::Position::line);
+        let reason = format!("expected {width} fields, found {}", record.len());
+        let count = |update: fn(&mut MalformedSummary)| {
+            let mut summary = self.summary.get();
+            update(&mut summary);
+            self.summary.set(summary);
+        };
+        match self.policy {
+            OnMalformed::Fail => Err(anyhow!("{source} line {line}: {reason}")),
+            OnMalformed::Skip => {
+                count(|summary| summary.skipped += 1);
+                Ok(None)
+            }
+            OnMalformed::Pad => {
+                debug!("{source} line {line}: {reason}; padding to {width}");
+                if record.len() < width {
+                    while record.len() < width {
+                        record.push_field(b"");
+                    }
+                    count(|summary| summary.padded += 1);
+                } else {
+                    record.truncate(width);
+                    count(|summary| summary.truncated += 1);
+                }
+                Ok(Some(record))
+            }
+            OnMalformed::Quarantine => {
+                if let Some(reject) = &self.reject
+                    && let Some(writer) = reject.borrow_mut().as_mut()
+                {
+                    let line = line.to_string();
+                    let leading = [source, line.as_str(), reason.as_str()];
+                    writer
+                        .write_record(leading.iter().map(|field| field.as_bytes()).chain(&record))
+                        .context("Writing reject file")?;
+                }
+                count(|summary| summary.quarantined += 1);
+                Ok(None)
+            }
+        }
+    }
+}
+
+struct CheckedRecords {
+    rows: MalformedRows,
+    source: String,
+    width: Option<usize>,
+    inner: ByteRecords,
+}
+
+impl Iterator for CheckedRecords {
+    type Item = Result<ByteRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let record = match self.inner.next()? {
+                Ok(record) => record,
+                Err(err) => return Some(Err(err)),
+            };
+            let width = *self.width.get_or_insert(record.len());
+            if record.len() == width {
+                return Some(Ok(record));
+            }
+            match self.rows.handle(&self.source, width, record) {
+                Ok(Some(record)) => return Some(Ok(record)),
+                Ok(None) => continue,
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pads_short_rows_truncates_long_ones_and_counts_them() {
+        let rows = MalformedRows {
+            policy: OnMalformed::Pad,
+            reject: None,
+            summary: Rc::default(),
+        };
+        let records: ByteRecords = Box::new(
+            [
+                ByteRecord::from(vec!["1", "a"]),
+                ByteRecord::from(vec!["2"]),
+                ByteRecord::from(vec!["3", "c", "extra"]),
+            ]
+            .into_iter()
+            .map(Ok),
+        );
+        let repaired = rows
+            .wrap(Path::new("in.csv"), Some(2), records)
+            .collect::<Result<Vec<_>>>()
+            .expect("records");
+        assert!(repaired.iter().all(|record| record.len() == 2));
+        assert_eq!(&repaired[1][1], b"");
+        let summary = rows.finish().expect("finish");
+        assert_eq!((summary.padded, summary.truncated), (1, 1));
+        assert_eq!(summary.to_string(), "1 padded, 1 truncated");
+    }
+}
@@ -0,0 +1,451 @@
+//! Parquet inputs for `process`, `stats`, `schema infer`, and `schema verify`.
+//!
+//! Files ending in `.parquet` (or `.pq`) are read column by column, one row group
+//! at a time, and each row is rendered as text cells (`2024-03-01`,
+//! `2024-03-01 08:30:00.250`, `12.50`, ...). Null values become empty cells.
+//!
+//! Column types come from the Parquet schema instead of sampling:
+//!
+//! - `BOOLEAN` → `Boolean`; `INT32` / `INT64` → `Integer`; `FLOAT` / `DOUBLE` → `Float`
+//! - `DATE` → `Date`; `TIMESTAMP` and legacy `INT96` → `DateTime`; `TIME` → `Time`
+//! - `DECIMAL(p,s)` → `decimal(p,s)` up to 28 digits; wider decimals → `String`,
+//!   keeping the exact digits
+//! - `UUID` → `Guid`; other byte arrays → `String`
+//!
+//! Only flat schemas are supported; nested groups, lists, and maps are rejected.
+
+use std::{fs::File, path::Path};
+
+use anyhow::{Context, Result, anyhow};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime};
+use csv::ByteRecord;
+use parquet::{
+    basic::{ConvertedType, LogicalType, TimeUnit, Type as PhysicalType},
+    column::reader::{ColumnReader, ColumnReaderImpl},
+    data_type::DataType,
+    file::reader::{FileReader, SerializedFileReader},
+    schema::types::{ColumnDescPtr, ColumnDescriptor},
+};
+use uuid::Uuid;
+
+use crate::schema::{ColumnMeta, ColumnType, DecimalSpec, Schema};
+
+/// Returns true when `path` names a Parquet file.
+pub fn is_parquet(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("parquet") || ext.eq_ignore_ascii_case("pq"))
+}
+
+/// Builds a schema from the Parquet file's column types without reading any rows.
+pub fn read_schema(path: &Path) -> Result<Schema> {
+    let reader = open_reader(path)?;
+    let columns = leaf_columns(&reader, path)?
+        .iter()
+        .map(|descr| ColumnMeta {
+            name: descr.name().to_string(),
+            datatype: column_type(descr),
+            rename: None,
+            value_replacements: Vec::new(),
+            datatype_mappings: Vec::new(),
+            lookup: None,
+            start: None,
+            width: None,
+        })
+        .collect();
+    Ok(Schema {
+        columns,
+        schema_version: None,
+        has_headers: true,
+        dialect: None,
+    })
+}
+
+/// Opens `path` and returns its column names and an iterator over its rows.
+pub(crate) fn open(path: &Path) -> Result<(Vec<String>, ParquetRecords)> {
+    let reader = open_reader(path)?;
+    let columns = leaf_columns(&reader, path)?;
+    let headers = columns
+        .iter()
+        .map(|descr| descr.name().to_string())
+        .collect();
+    Ok((
+        headers,
+        ParquetRecords {
+            reader,
+            columns,
+            next_group: 0,
+            pending: Vec::new().into_iter(),
+        },
+    ))
+}
+
+/// Rows of a Parquet file as UTF-8 byte records, decoded a row group at a time.
+pub(crate) struct ParquetRecords {
+    reader: SerializedFileReader<File>,
+    columns: Vec<ColumnDescPtr>,
+    next_group: usize,
+    pending: std::vec::IntoIter<ByteRecord>,
+}
+
+impl ParquetRecords {
+    fn read_group(&self, index: usize) -> Result<Vec<ByteRecord>> {
+        let group = self.reader.get_row_group(index)?;
+        let rows = usize::try_from(group.metadata().num_rows())?;
+        let cells = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(idx, descr)| {
+                read_column(group.get_column_reader(idx)?, descr, rows)
+                    .with_context(|| format!("Reading Parquet column '{}'", descr.name()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok((0..rows)
+            .map(|row| {
+                let mut record = ByteRecord::new();
+                for column in &cells {
+                    record.push_field(column[row].as_bytes());
+                }
+                record
+            })
+            .collect())
+    }
+}
+
+impl Iterator for ParquetRecords {
+    type Item = Result<ByteRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(record) = self.pending.next() {
+                return Some(Ok(record));
+            }
+            if self.next_group >= self.reader.num_row_groups() {
+                return None;
+            }
+            let index = self.next_group;
+            self.next_group += 1;
+            match self.read_group(index) {
+                Ok(records) => self.pending = records.into_iter(),
+                Err(err) => {
+                    self.next_group = usize::MAX;
+                    return Some(Err(
+                        err.context(format!("Reading Parquet row group {index}"))
+                    ));
+                }
+            }
+        }
+    }
+}
+
+fn open_reader(path: &Path) -> Result<SerializedFileReader<File>> {
+    let file = File::open(path).with_context(|| format!("Opening Parquet file {path:?}"))?;
+    SerializedFileReader::new(file)
+        .with_context(|| format!("Reading Parquet metadata from {path:?}"))
+}
+
+fn leaf_columns(reader: &SerializedFileReader<File>, path: &Path) -> Result<Vec<ColumnDescPtr>> {
+    let descriptor = reader.metadata().file_metadata().schema_descr();
+    descriptor
+        .columns()
+        .iter()
+        .map(|descr| {
+            if descr.path().parts().len() > 1 || descr.max_rep_level() > 0 {
+                return Err(anyhow!(
+                    "Parquet column '{}' in {path:?} is nested; only flat schemas are supported",
+                    descr.path()
+                ));
+            }
+            Ok(descr.clone())
+        })
+        .collect()
+}
+
+/// Logical type of a column, falling back to the legacy converted type.
+fn logical_type(descr: &ColumnDescriptor) -> Option<LogicalType> {
+    descr.logical_type().or_else(|| {
+        let millis = TimeUnit::MILLIS(Default::default());
+        let micros = TimeUnit::MICROS(Default::default());
+        Some(match descr.converted_type() {
+            ConvertedType::UTF8 | ConvertedType::ENUM | ConvertedType::JSON => LogicalType::String,
+            ConvertedType::DATE => LogicalType::Date,
+            ConvertedType::DECIMAL => LogicalType::Decimal {
+                scale: descr.type_scale(),
+                precision: descr.type_precision(),
+            },
+            ConvertedType::TIMESTAMP_MILLIS => LogicalType::Timestamp {
+                is_adjusted_to_u_t_c: true,
+                unit: millis,
+            },
+            ConvertedType::TIMESTAMP_MICROS => LogicalType::Timestamp {
+                is_adjusted_to_u_t_c: true,
+                unit: micros,
+            },
+            ConvertedType::TIME_MILLIS => LogicalType::Time {
+                is_adjusted_to_u_t_c: true,
+                unit: millis,
+            },
+            ConvertedType::TIME_MICROS => LogicalType::Time {
+                is_adjusted_to_u_t_c: true,
+                unit: micros,
+            },
+            ConvertedType::UINT_8 | ConvertedType::UINT_16 | ConvertedType::UINT_32 => {
+                LogicalType::Integer {
+                    bit_width: 32,
+                    is_signed: false,
+                }
+            }
+            ConvertedType::UINT_64 => LogicalType::Integer {
+                bit_width: 64,
+                is_signed: false,
+            },
+            _ => return None,
+        })
+    })
+}
+
+fn column_type(descr: &ColumnDescriptor) -> ColumnType {
+    let logical = logical_type(descr);
+    match (descr.physical_type(), logical) {
+        (_, Some(LogicalType::Decimal { scale, precision })) => {
+            DecimalSpec::new(precision as u32, scale as u32)
+                .map(ColumnType::Decimal)
+                .unwrap_or(ColumnType::String)
+        }
+        (PhysicalType::BOOLEAN, _) => ColumnType::Boolean,
+        (_, Some(LogicalType::Date)) => ColumnType::Date,
+        (_, Some(LogicalType::Timestamp { .. })) | (PhysicalType::INT96, _) => ColumnType::DateTime,
+        (_, Some(LogicalType::Time { .. })) => ColumnType::Time,
+        (PhysicalType::INT32 | PhysicalType::INT64, _) => ColumnType::Integer,
+        (PhysicalType::FLOAT | PhysicalType::DOUBLE, _) => ColumnType::Float,
+        (PhysicalType::FIXED_LEN_BYTE_ARRAY, Some(LogicalType::Uuid)) => ColumnType::Guid,
+        _ => ColumnType::String,
+    }
+}
+
+fn read_column(column: ColumnReader, descr: &ColumnDescriptor, rows: usize) -> Result<Vec<String>> {
+    let logical = logical_type(descr);
+    match column {
+        ColumnReader::BoolColumnReader(mut reader) => {
+            read_cells(&mut reader, descr, rows, |value| Ok(value.to_string()))
+        }
+        ColumnReader::Int32ColumnReader(mut reader) => {
+            read_cells(&mut reader, descr, rows, |value| {
+                render_integer(i64::from(*value), logical.as_ref())
+            })
+        }
+        ColumnReader::Int64ColumnReader(mut reader) => {
+            read_cells(&mut reader, descr, rows, |value| {
+                render_integer(*value, logical.as_ref())
+            })
+        }
+        ColumnReader::Int96ColumnReader(mut reader) => {
+            read_cells(&mut reader, descr, rows, |value| {
+                Ok(render_datetime(
+                    DateTime::from_timestamp_nanos(value.to_nanos()).naive_utc(),
+                ))
+            })
+        }
+        ColumnReader::FloatColumnReader(mut reader) => {
+            read_cells(&mut reader, descr, rows, |value| Ok(value.to_string()))
+        }
+        ColumnReader::DoubleColumnReader(mut reader) => {
+            read_cells(&mut reader, descr, rows, |value| Ok(value.to_string()))
+        }
+        ColumnReader::ByteArrayColumnReader(mut reader) => {
+            read_cells(&mut reader, descr, rows, |value| {
+                render_bytes(value.data(), logical.as_ref())
+            })
+        }
+        ColumnReader::FixedLenByteArrayColumnReader(mut reader) => {
+            read_cells(&mut reader, descr, rows, |value| {
+                render_bytes(value.data(), logical.as_ref())
+            })
+        }
+    }
+}
+
+/// Reads `rows` values of a flat column, rendering nulls as empty cells.
+fn read_cells<T: DataType>(
+    reader: &mut ColumnReaderImpl<T>,
+    descr: &ColumnDescriptor,
+    rows: usize,
+    render: impl Fn(&T::T) -> Result<String>,
+) -> Result<Vec<String>> {
+    let mut values = Vec::with_capacity(rows);
+    let mut levels = Vec::with_capacity(rows);
+    reader.read_records(rows, Some(&mut levels), None, &mut values)?;
+    if descr.max_def_level() == 0 {
+        return values.iter().map(render).collect();
+    }
+    let mut values = values.iter();
+    levels
+        .iter()
+        .map(|level| {
+            if *level < descr.max_def_level() {
+                return Ok(String::new());
+            }
+            let value = values
+                .next()
+                .ok_or_else(|| anyhow!("Column has fewer values than definition levels"))?;
+            render(value)
+        })
+        .collect()
+}
+
+fn render_integer(value: i64, logical: Option<&LogicalType>) -> Result<String> {
+    Ok(match logical {
+        Some(LogicalType::Date) => {
+            let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).expect("valid epoch");
+            epoch
+                .checked_add_signed(chrono::Duration::days(value))
+                .ok_or_else(|| anyhow!("Date value {value} is out of range"))?
+                .format("%Y-%m-%d")
+                .to_string()
+        }
+        Some(LogicalType::Timestamp { unit, .. }) => {
+            let datetime = match unit {
+                TimeUnit::MILLIS(_) => DateTime::from_timestamp_millis(value),
+                TimeUnit::MICROS(_) => DateTime::from_timestamp_micros(value),
+                TimeUnit::NANOS(_) => Some(DateTime::from_timestamp_nanos(value)),
+            }
+            .ok_or_else(|| anyhow!("Timestamp value {value} is out of range"))?;
+            render_datetime(datetime.naive_utc())
+        }
+        Some(LogicalType::Time { unit, .. }) => {
+            let nanos = match unit {
+                TimeUnit::MILLIS(_) => value * 1_000_000,
+                TimeUnit::MICROS(_) => value * 1_000,
+                TimeUnit::NANOS(_) => value,
+            };
+            let time = NaiveTime::from_num_seconds_from_midnight_opt(
+                u32::try_from(nanos / 1_000_000_000)?,
+                u32::try_from(nanos % 1_000_000_000)?,
+            )
+            .ok_or_else(|| anyhow!("Time value {value} is out of range"))?;
+            time.format("%H:%M:%S%.f").to_string()
+        }
+        Some(LogicalType::Decimal { scale, .. }) => render_decimal(i128::from(value), *scale)?,
+        Some(LogicalType::Integer {
+            is_signed: false,
+            bit_width,
+        }) if *bit_width < 64 => (value as u32).to_string(),
+        Some(LogicalType::Integer {
+            is_signed: false, ..
+        }) => (value as u64).to_string(),
+        _ => value.to_string(),
+    })
+}
+
+fn render_bytes(data: &[u8], logical: Option<&LogicalType>) -> Result<String> {
+    Ok(match logical {
+        Some(LogicalType::Decimal { scale, .. }) => {
+            if data.is_empty() || data.len() > 16 {
+                return Err(anyhow!(
+                    "Decimal values wider than 16 bytes are not supported"
+                ));
+            }
+            let fill = if data[0] & 0x80 != 0 { 0xff } else { 0 };
+            let mut bytes = [fill; 16];
+            bytes[16 - data.len()..].copy_from_slice(data);
+            render_decimal(i128::from_be_bytes(bytes), *scale)?
+        }
+        Some(LogicalType::Uuid) => Uuid::from_slice(data)
+            .context("Reading UUID value")?
+            .to_string(),
+        _ => String::from_utf8_lossy(data).into_owned(),
+    })
+}
+
+/// Places the decimal point `scale` digits from the right of `unscaled`. Working on
+/// the digits keeps values wider than `rust_decimal`'s 28 digits, whose columns
+/// are read as `String`, exact.
+fn render_decimal(unscaled: i128, scale: i32) -> Result<String> {
+    let scale = usize::try_from(scale).context("Negative decimal scale")?;
+    let digits = unscaled.unsigned_abs().to_string();
+    let sign = if unscaled < 0 { "-" } else { "" };
+    if scale == 0 {
+        return Ok(format!("{sign}{digits}"));
+    }
+    let digits = format!("{digits:0>width$}", width = scale + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - scale);
+    Ok(format!("{sign}{whole}.{fraction}"))
+}
+
+fn render_datetime(datetime: NaiveDateTime) -> String {
+    datetime.format("%Y-%m-%d %H:%M:%S%.f").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_values_in_csv_text_formats() {
+        let decimal = LogicalType::Decimal {
+            scale: 2,
+            precision: 10,
+        };
+        assert_eq!(
+            render_bytes(&[0xfb, 0x2e], Some(&decimal)).unwrap(),
+            "-12.34"
+        );
+        assert_eq!(render_integer(1250, Some(&decimal)).unwrap(), "12.50");
+        let wide = LogicalType::Decimal {
+            scale: 18,
+            precision: 38,
+        };
+        assert_eq!(
+            column_type(&ColumnDescriptor::new(
+                std::sync::Arc::new(
+                    parquet::schema::types::Type::primitive_type_builder(
+                        "wide",
+                        PhysicalType::FIXED_LEN_BYTE_ARRAY
+                    )
+                    .with_length(16)
+                    .with_logical_type(Some(wide.clone()))
+                    .with_precision(38)
+                    .with_scale(18)
+                    .build()
+                    .unwrap()
+                ),
+                0,
+                0,
+                parquet::schema::types::ColumnPath::new(Vec::new()),
+            )),
+            ColumnType::String
+        );
+        let unscaled = -123_456_789_012_345_678_901_234_567_890_123_456_i128;
+        assert_eq!(
+            render_bytes(&unscaled.to_be_bytes(), Some(&wide)).unwrap(),
+            "-123456789012345678.901234567890123456"
+        );
+        assert_eq!(
+            render_integer(-5, Some(&wide)).unwrap(),
+            "-0.000000000000000005"
+        );
+        assert_eq!(
+            render_integer(19_783, Some(&LogicalType::Date)).unwrap(),
+            "2024-03-01"
+        );
+        let micros = TimeUnit::MICROS(Default::default());
+        let timestamp = LogicalType::Timestamp {
+            is_adjusted_to_u_t_c: false,
+            unit: micros.clone(),
+        };
+        assert_eq!(
+            render_integer(1_709_281_800_250_000, Some(&timestamp)).unwrap(),
+            "2024-03-01 08:30:00.250"
+        );
+        let time = LogicalType::Time {
+            is_adjusted_to_u_t_c: false,
+            unit: micros,
+        };
+        assert_eq!(
+            render_integer(30_600_000_000, Some(&time)).unwrap(),
+            "08:30:00"
+        );
+        assert_eq!(render_bytes(b"plain", None).unwrap(), "plain");
+    }
+}
@@ -0,0 +1,385 @@
+//! Parquet output for `process` and `append`.
+//!
+//! `--output-format parquet` writes the emitted rows to a Parquet file whose column
+//! types come from the schema rather than being re-inferred downstream:
+//!
+//! | Column type      | Parquet physical type          | Logical type                  |
+//! |------------------|--------------------------------|-------------------------------|
+//! | `String`         | `BYTE_ARRAY`                   | `STRING`                      |
+//! | `Integer`        | `INT64`                        | —                             |
+//! | `Float`          | `DOUBLE`                       | —                             |
+//! | `Boolean`        | `BOOLEAN`                      | —                             |
+//! | `Date`           | `INT32`                        | `DATE`                        |
+//! | `DateTime`       | `INT64`                        | `TIMESTAMP(MICROS, local)`    |
+//! | `Time`           | `INT64`                        | `TIME(MICROS, local)`         |
+//! | `Guid`           | `FIXED_LEN_BYTE_ARRAY(16)`     | `UUID`                        |
+//! | `Decimal(p,s)`   | `INT32` / `INT64` / `FLBA(16)` | `DECIMAL(p,s)` (by precision) |
+//! | `Currency`       | `INT64`                        | `DECIMAL(18,4)`               |
+//!
+//! Every column is optional; empty cells are written as nulls. Rows are buffered
+//! per column and written one row group at a time (`--row-group-size`, default
+//! 1,048,576 rows) with the codec chosen by `--parquet-compression`.
+//!
+//! `process` hands over the values it has already parsed, so text rendering such as
+//! `--boolean-format` does not reach the file; `append` parses each cell, trimming all
+//! but `String` cells.
+
+use std::{fs::File, path::Path, sync::Arc};
+
+use anyhow::{Context, Result, anyhow};
+use chrono::NaiveDate;
+use parquet::{
+    basic::{
+        Compression, GzipLevel, LogicalType, Repetition, TimeUnit, Type as PhysicalType, ZstdLevel,
+    },
+    column::writer::ColumnWriter,
+    data_type::{ByteArray, FixedLenByteArray},
+    file::{properties::WriterProperties, writer::SerializedFileWriter},
+    format::MicroSeconds,
+    schema::types::Type,
+};
+use rust_decimal::Decimal;
+
+use crate::{
+    cli::{ParquetArgs, ParquetCompression},
+    data::{Value, parse_typed_value},
+    io_utils,
+    schema::{ColumnType, DecimalSpec},
+};
+
+const DEFAULT_ROW_GROUP_SIZE: usize = 1_048_576;
+const CURRENCY_PRECISION: u32 = 18;
+const CURRENCY_SCALE: u32 = 4;
+
+/// Streams rows into a Parquet file, one row group per `row_group_size` rows.
+pub(crate) struct ParquetOutput {
+    writer: SerializedFileWriter<File>,
+    columns: Vec<ParquetColumn>,
+    row_group_size: usize,
+    buffered: usize,
+}
+
+struct ParquetColumn {
+    name: String,
+    datatype: ColumnType,
+    values: ColumnValues,
+    def_levels: Vec<i16>,
+}
+
+enum ColumnValues {
+    Boolean(Vec<bool>),
+    Int32(Vec<i32>),
+    Int64(Vec<i64>),
+    Double(Vec<f64>),
+    Bytes(Vec<ByteArray>),
+    Fixed(Vec<FixedLenByteArray>),
+}
+
+impl ParquetOutput {
+    /// Creates `path` with one optional Parquet column per `(name, type)` pair.
+    pub(crate) fn open(
+        path: Option<&Path>,
+        columns: &[(String, ColumnType)],
+        args: &ParquetArgs,
+    ) -> Result<Self> {
+        let path = path
+            .filter(|path| !io_utils::is_dash(path))
+            .ok_or_else(|| anyhow!("Parquet output requires --output with a file path"))?;
+        let row_group_size = args.row_group_size.unwrap_or(DEFAULT_ROW_GROUP_SIZE);
+        if row_group_size == 0 {
+            return Err(anyhow!("--row-group-size must be greater than zero"));
+        }
+        let fields = columns
+            .iter()
+            .map(|(name, datatype)| parquet_field(name, datatype).map(Arc::new))
+            .collect::<Result<Vec<_>>>()?;
+        let schema = Type::group_type_builder("schema")
+            .with_fields(fields)
+            .build()
+            .context("Building Parquet schema")?;
+        let properties = WriterProperties::builder()
+            .set_compression(compression(args.compression))
+            .set_max_row_group_size(row_group_size)
+            .build();
+        let file = File::create(path).with_context(|| format!("Creating output file {path:?}"))?;
+        let writer = SerializedFileWriter::new(file, Arc::new(schema), Arc::new(properties))
+            .context("Opening Parquet writer")?;
+        Ok(ParquetOutput {
+            writer,
+            columns: columns
+                .iter()
+                .map(|(name, datatype)| ParquetColumn {
+                    name: name.clone(),
+                    datatype: datatype.clone(),
+                    values: ColumnValues::for_type(datatype),
+                    def_levels: Vec::new(),
+                })
+                .collect(),
+            row_group_size,
+            buffered: 0,
+        })
+    }
+
+    /// Parses `record` with the column types and buffers it, writing a row group when full.
+    /// String cells are kept as read; other cells are trimmed before parsing.
+    pub(crate) fn write_record(&mut self, record: &[String]) -> Result<()> {
+        for (idx, column) in self.columns.iter_mut().enumerate() {
+            let raw = record.get(idx).map(String::as_str).unwrap_or("");
+            let cell = match column.datatype {
+                ColumnType::String => raw,
+                _ => raw.trim(),
+            };
+            let typed = parse_typed_value(cell, &column.datatype)
+                .with_context(|| format!("Column '{}'", column.name))?;
+            column
+                .push(typed)
+                .with_context(|| format!("Column '{}' value '{raw}'", column.name))?;
+        }
+        self.end_row()
+    }
+
+    /// Buffers a row of already-typed values, one per column; `None` is written as null.
+    pub(crate) fn write_values(&mut self, values: Vec<Option<Value>>) -> Result<()> {
+        let mut values = values.into_iter();
+        for column in &mut self.columns {
+            let value = values.next().flatten();
+            column
+                .push(value)
+                .with_context(|| format!("Column '{}'", column.name))?;
+        }
+        self.end_row()
+    }
+
+    fn end_row(&mut self) -> Result<()> {
+        self.buffered += 1;
+        if self.buffered >= self.row_group_size {
+            self.flush_row_group()?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn finish(mut self) -> Result<()> {
+        if self.buffered > 0 {
+            self.flush_row_group()?;
+        }
+        self.writer.close().context("Finishing Parquet file")?;
+        Ok(())
+    }
+
+    fn flush_row_group(&mut self) -> Result<()> {
+        let mut row_group = self
+            .writer
+            .next_row_group()
+            .context("Starting Parquet row group")?;
+        for column in &mut self.columns {
+            let mut writer = row_group
+                .next_column()?
+                .ok_or_else(|| anyhow!("Parquet writer has no column for '{}'", column.name))?;
+            column
+                .write(writer.untyped())
+                .with_context(|| format!("Writing Parquet column '{}'", column.name))?;
+            writer.close()?;
+            column.clear();
+        }
+        row_group.close().context("Closing Parquet row group")?;
+        self.buffered = 0;
+        Ok(())
+    }
+}
+
+impl ParquetColumn {
+    fn push(&mut self, value: Option<Value>) -> Result<()> {
+        let Some(value) = value else {
+            self.def_levels.push(0);
+            return Ok(());
+        };
+        self.def_levels.push(1);
+        match (&mut self.values, value) {
+            (ColumnValues::Boolean(values), Value::Boolean(b)) => values.push(b),
+            (ColumnValues::Int64(values), Value::Integer(i)) => values.push(i),
+            (ColumnValues::Double(values), Value::Float(f)) => values.push(f),
+            (ColumnValues::Double(values), Value::Integer(i)) => values.push(i as f64),
+            (ColumnValues::Int32(values), Value::Date(date)) => {
+                let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).expect("valid epoch");
+                values.push(i32::try_from(date.signed_duration_since(epoch).num_days())?);
+            }
+            (ColumnValues::Int64(values), Value::DateTime(datetime)) => {
+                values.push(datetime.and_utc().timestamp_micros());
+            }
+            (ColumnValues::Int64(values), Value::Time(time)) => {
+                let micros = time.signed_duration_since(chrono::NaiveTime::MIN);
+                values.push(micros.num_microseconds().unwrap_or_default());
+            }
+            (ColumnValues::Fixed(values), Value::Guid(guid)) => {
+                values.push(FixedLenByteArray::from(guid.as_bytes().to_vec()));
+            }
+            (values, Value::Decimal(decimal)) => {
+                let scale = match &self.datatype {
+                    ColumnType::Decimal(spec) => spec.scale,
+                    _ => decimal.scale(),
+                };
+                push_decimal(values, *decimal.amount(), scale)?
+            }
+            (values, Value::Currency(currency)) => {
+                push_decimal(values, *currency.amount(), CURRENCY_SCALE)?
+            }
+            (ColumnValues::Bytes(values), other) => {
+                values.push(ByteArray::from(other.as_display().into_bytes()));
+            }
+            (_, other) => {
+                return Err(anyhow!(
+                    "Value '{}' does not match the Parquet column type",
+                    other.as_display()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn write(&self, writer: &mut ColumnWriter<'_>) -> Result<()> {
+        let levels = Some(self.def_levels.as_slice());
+        match (writer, &self.values) {
+            (ColumnWriter::BoolColumnWriter(w), ColumnValues::Boolean(v)) => {
+                w.write_batch(v, levels, None)?
+            }
+            (ColumnWriter::Int32ColumnWriter(w), ColumnValues::Int32(v)) => {
+                w.write_batch(v, levels, None)?
+            }
+            (ColumnWriter::Int64ColumnWriter(w), ColumnValues::Int64(v)) => {
+                w.write_batch(v, levels, None)?
+            }
+            (ColumnWriter::DoubleColumnWriter(w), ColumnValues::Double(v)) => {
+                w.write_batch(v, levels, None)?
+            }
+            (ColumnWriter::ByteArrayColumnWriter(w), ColumnValues::Bytes(v)) => {
+                w.write_batch(v, levels, None)?
+            }
+            (ColumnWriter::FixedLenByteArrayColumnWriter(w), ColumnValues::Fixed(v)) => {
+                w.write_batch(v, levels, None)?
+            }
+            _ => return Err(anyhow!("Buffered values do not match the Parquet column")),
+        };
+        Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.def_levels.clear();
+        self.values = ColumnValues::for_type(&self.datatype);
+    }
+}
+
+impl ColumnValues {
+    fn for_type(datatype: &ColumnType) -> Self {
+        match datatype {
+            ColumnType::String => Self::Bytes(Vec::new()),
+            ColumnType::Integer | ColumnType::DateTime | ColumnType::Time => {
+                Self::Int64(Vec::new())
+            }
+            ColumnType::Float => Self::Double(Vec::new()),
+            ColumnType::Boolean => Self::Boolean(Vec::new()),
+            ColumnType::Date => Self::Int32(Vec::new()),
+            ColumnType::Guid => Self::Fixed(Vec::new()),
+            ColumnType::Currency => Self::Int64(Vec::new()),
+            ColumnType::Decimal(spec) => match decimal_physical_type(spec.precision) {
+                PhysicalType::INT32 => Self::Int32(Vec::new()),
+                PhysicalType::INT64 => Self::Int64(Vec::new()),
+                _ => Self::Fixed(Vec::new()),
+            },
+        }
+    }
+}
+
+/// Stores `amount` as an unscaled integer at `scale`, sized to the column's physical type.
+fn push_decimal(values: &mut ColumnValues, mut amount: Decimal, scale: u32) -> Result<()> {
+    amount.rescale(scale);
+    let unscaled = amount.mantissa();
+    match values {
+        ColumnValues::Int32(values) => values.push(i32::try_from(unscaled)?),
+        ColumnValues::Int64(values) => values.push(i64::try_from(unscaled)?),
+        ColumnValues::Fixed(values) => {
+            values.push(FixedLenByteArray::from(unscaled.to_be_bytes().to_vec()))
+        }
+        _ => return Err(anyhow!("Decimal value in a non-decimal Parquet column")),
+    }
+    Ok(())
+}
+
+fn decimal_physical_type(precision: u32) -> PhysicalType {
+    match precision {
+        0..=9 => PhysicalType::INT32,
+        10..=18 => PhysicalType::INT64,
+        _ => PhysicalType::FIXED_LEN_BYTE_ARRAY,
+    }
+}
+
+fn parquet_field(name: &str, datatype: &ColumnType) -> Result<Type> {
+    let micros = TimeUnit::MICROS(MicroSeconds::new());
+    let (physical, logical, decimal) = match datatype {
+        ColumnType::String => (PhysicalType::BYTE_ARRAY, Some(LogicalType::String), None),
+        ColumnType::Integer => (PhysicalType::INT64, None, None),
+        ColumnType::Float => (PhysicalType::DOUBLE, None, None),
+        ColumnType::Boolean => (PhysicalType::BOOLEAN, None, None),
+        ColumnType::Date => (PhysicalType::INT32, Some(LogicalType::Date), None),
+        ColumnType::DateTime => (
+            PhysicalType::INT64,
+            Some(LogicalType::Timestamp {
+                is_adjusted_to_u_t_c: false,
+                unit: micros,
+            }),
+            None,
+        ),
+        ColumnType::Time => (
+            PhysicalType::INT64,
+            Some(LogicalType::Time {
+                is_adjusted_to_u_t_c: false,
+                unit: micros,
+            }),
+            None,
+        ),
+        ColumnType::Guid => (
+            PhysicalType::FIXED_LEN_BYTE_ARRAY,
+            Some(LogicalType::Uuid),
+            None,
+        ),
+        ColumnType::Currency => (
+            PhysicalType::INT64,
+            None,
+            Some(DecimalSpec {
+                precision: CURRENCY_PRECISION,
+                scale: CURRENCY_SCALE,
+            }),
+        ),
+        ColumnType::Decimal(spec) => (
+            decimal_physical_type(spec.precision),
+            None,
+            Some(spec.clone()),
+        ),
+    };
+    let mut builder = Type::primitive_type_builder(name, physical)
+        .with_repetition(Repetition::OPTIONAL)
+        .with_logical_type(logical);
+    if physical == PhysicalType::FIXED_LEN_BYTE_ARRAY {
+        builder = builder.with_length(16);
+    }
+    if let Some(spec) = decimal {
+        builder = builder
+            .with_logical_type(Some(LogicalType::Decimal {
+                scale: spec.scale as i32,
+                precision: spec.precision as i32,
+            }))
+            .with_precision(spec.precision as i32)
+            .with_scale(spec.scale as i32);
+    }
+    builder
+        .build()
+        .with_context(|| format!("Mapping column '{name}' to Parquet"))
+}
+
+fn compression(codec: ParquetCompression) -> Compression {
+    match codec {
+        ParquetCompression::None => Compression::UNCOMPRESSED,
+        ParquetCompression::Snappy => Compression::SNAPPY,
+        ParquetCompression::Gzip => Compression::GZIP(GzipLevel::default()),
+        ParquetCompression::Zstd => Compression::ZSTD(ZstdLevel::default()),
+    }
+}
@@ -3,7 +3,7 @@
 //! Implements the `process` subcommand, which applies a streaming transformation
 //! pipeline to CSV data: schema loading → delimiter/encoding resolution →
 //! index selection (optional) → datatype mapping → value replacement →
-//! typed parsing → row filtering → column projection → derived columns →
+//! lookup enrichment (see [`crate::lookup`]) → typed parsing → row filtering → column projection → derived columns →
 //! output writing (CSV or ASCII table).
 //!
 //! ## Sort Strategy
@@ -11,31 +11,60 @@
 //! When `--sort` is specified, the engine first checks for a matching index
 //! variant (longest column prefix match). If found, rows are read via
 //! seek-based I/O without buffering. Otherwise, an in-memory sort fallback
-//! is used.
+//! is used. When `--memory-limit` is set, the fallback spills sorted runs to
+//! temporary files and merges them (see [`crate::external_sort`]) so memory
+//! stays bounded regardless of input size. When `--limit` accompanies `--sort`,
+//! only the best rows are retained in a bounded heap (see [`crate::top_n`]).
+//!
+//! ## Parallelism
+//!
+//! `--threads N` runs decoding, mappings, typed parsing, filters, and derived
+//! columns on a worker pool in fixed-size batches. Batches are reassembled by
+//! input position, so output is identical to a single-threaded run.
 
-use std::fs::File;
+use std::path::Path;
 
 use anyhow::{Context, Result, anyhow};
 use csv::{ByteRecord, Position};
 use itertools::Itertools;
-use log::{debug, info};
+use log::{debug, info, warn};
+use rayon::{ThreadPool, prelude::*};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    cli::{BooleanFormat, ProcessArgs},
+    cli::{BooleanFormat, DistinctKeep, JsonDecimals, OnTypeError, OutputFormat, ProcessArgs},
+    compression::SeekRead,
     data::{ComparableValue, Value},
     derive::{DerivedColumn, parse_derived_columns},
+    dialect::Dialect,
+    distinct::{AdjacentDistinct, DistinctPlan, SeenKeys},
+    excel_input,
+    external_sort::{ExternalSorter, SortedRows},
     filter::{evaluate_conditions, parse_filters},
-    index::{CsvIndex, IndexVariant, SortDirection},
-    io_utils,
-    rows::{evaluate_filter_expressions, parse_typed_row},
+    index::{CsvIndex, IndexVariant, SortDirection, SourceStatus},
+    inputs::{InputRecords, resolve_inputs},
+    io_utils, json_input,
+    json_output::{self, JsonOutput},
+    lookup::Lookups,
+    malformed::{MalformedRows, MalformedSummary},
+    parquet_input,
+    parquet_output::ParquetOutput,
+    rows::evaluate_filter_expressions,
+    sample::{SamplePlan, Sampler},
     schema::{ColumnMeta, ColumnType, Schema},
+    split::CsvOutput,
     table,
+    top_n::TopRows,
+    type_errors::{TypeErrors, TypeRejection, TypeRejects},
+    window::{WindowEvaluator, WindowPlan, WindowedRows, parse_windows},
 };
 
 use encoding_rs::Encoding;
 
-pub fn execute(args: &ProcessArgs) -> Result<()> {
-    let delimiter = io_utils::resolve_input_delimiter(&args.input, args.delimiter);
+pub fn execute(args: &ProcessArgs) -> Result<MalformedSummary> {
+    let inputs = resolve_inputs(&args.inputs)?;
+    let input = inputs[0].as_path();
+    let delimiter = io_utils::resolve_input_delimiter(input, args.delimiter);
     let input_encoding = io_utils::resolve_encoding(args.input_encoding.as_deref())?;
     let output_path = args.output.as_deref();
     let writing_to_stdout = output_path.is_none_or(io_utils::is_dash);
@@ -43,6 +72,21 @@ pub fn execute(args: &ProcessArgs) -> Result<()> {
     if args.preview && args.output.is_some() {
         return Err(anyhow!("--preview cannot be combined with --output"));
     }
+    if args.split.is_enabled() && (args.preview || args.table) {
+        return Err(anyhow!(
+            "Splitting output cannot be combined with --preview or --table"
+        ));
+    }
+    if args.output_format != OutputFormat::Csv {
+        if args.preview || args.table {
+            return Err(anyhow!(
+                "Non-CSV output cannot be combined with --preview or --table"
+            ));
+        }
+        if args.split.is_enabled() {
+            return Err(anyhow!("Splitting output is only supported for CSV output"));
+        }
+    }
     let mut limit = args.limit;
     if args.preview && limit.is_none() {
         limit = Some(10);
@@ -56,15 +100,19 @@ pub fn execute(args: &ProcessArgs) -> Result<()> {
         io_utils::resolve_output_delimiter(output_path, args.output_delimiter, delimiter);
     let output_encoding = io_utils::resolve_encoding(args.output_encoding.as_deref())?;
     info!(
-        "Processing '{}' -> {:?} (delimiter '{}', output '{}')",
-        args.input.display(),
+        "Processing '{}'{} -> {:?} (delimiter '{}', output '{}')",
+        input.display(),
+        match inputs.len() {
+            1 => String::new(),
+            count => format!(" and {} more file(s)", count - 1),
+        },
         output_path
             .map(|p| p.display().to_string())
             .unwrap_or_else(|| "stdout".into()),
         crate::printable_delimiter(delimiter),
         crate::printable_delimiter(output_delimiter)
     );
-    let sorts = args
+    let mut sorts = args
         .sort
         .iter()
         .flat_map(|s| s.split(','))
@@ -88,36 +136,158 @@ pub fn execute(args: &ProcessArgs) -> Result<()> {
         .filter(|s| !s.is_empty())
         .map(|s| s.to_string())
         .collect();
+    let distinct_columns = args
+        .distinct_on
+        .iter()
+        .flat_map(|s| s.split(','))
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .collect::<Vec<_>>();
     let derived_columns = parse_derived_columns(&args.derives)?;
+    let window_specs = parse_windows(&args.windows)?;
+    let sample_plan = SamplePlan::from_args(args.sample, args.sample_fraction, args.seed)?;
     let filters = parse_filters(&args.filters)?;
 
-    let mut reader;
-    let headers: Vec<String>;
+    let malformed = MalformedRows::from_args(&args.malformed)?;
+
+    let reader;
+    let mut headers: Vec<String>;
     let mut schema: Schema;
+    let has_headers: bool;
 
     if let Some(schema_path) = &args.schema {
         schema = Schema::load(schema_path)?;
+        schema.apply_dialect_args(&args.dialect)?;
         let expects_headers = schema.expects_headers();
-        reader = io_utils::open_csv_reader_from_path(&args.input, delimiter, expects_headers)?;
-        headers = if expects_headers {
-            io_utils::reader_headers(&mut reader, input_encoding)?
-        } else {
-            schema.headers()
-        };
+        has_headers = expects_headers;
+        let (read_headers, records) = io_utils::open_schema_records(
+            input,
+            &schema,
+            delimiter,
+            input_encoding,
+            &args.input_format,
+            malformed.as_ref(),
+        )?;
+        reader = records;
+        headers = read_headers.unwrap_or_else(|| schema.headers());
+    } else if parquet_input::is_parquet(input) {
+        // Parquet files carry their own column types, so no sampling is needed.
+        schema = parquet_input::read_schema(input)?;
+        has_headers = true;
+        let (read_headers, records) = io_utils::open_records(
+            input,
+            delimiter,
+            input_encoding,
+            true,
+            &Dialect::default(),
+            &args.input_format,
+            malformed.as_ref(),
+        )?;
+        reader = records;
+        headers = read_headers.unwrap_or_else(|| schema.headers());
+    } else if json_input::is_json(input) || excel_input::is_excel(input) {
+        has_headers = true;
+        let (read_headers, records) = io_utils::open_records(
+            input,
+            delimiter,
+            input_encoding,
+            true,
+            &Dialect::default(),
+            &args.input_format,
+            malformed.as_ref(),
+        )?;
+        reader = records;
+        headers = read_headers.unwrap_or_default();
+        schema = Schema::from_headers(&headers);
     } else {
-        let layout =
-            crate::schema::detect_csv_layout(&args.input, delimiter, input_encoding, None)?;
-        reader = io_utils::open_csv_reader_from_path(&args.input, delimiter, layout.has_headers)?;
-        headers = if layout.has_headers {
-            io_utils::reader_headers(&mut reader, input_encoding)?
-        } else {
-            layout.headers.clone()
-        };
+        let dialect = args.dialect.apply(Dialect::default());
+        let layout = crate::schema::detect_csv_layout(
+            input,
+            delimiter,
+            input_encoding,
+            None,
+            &dialect,
+            malformed.is_some(),
+        )?;
+        has_headers = layout.has_headers;
+        let (read_headers, records) = io_utils::open_records(
+            input,
+            delimiter,
+            input_encoding,
+            layout.has_headers,
+            &dialect,
+            &args.input_format,
+            malformed.as_ref(),
+        )?;
+        reader = records;
+        headers = read_headers.unwrap_or_else(|| layout.headers.clone());
         schema = Schema::from_headers(&headers);
         schema.has_headers = layout.has_headers;
+        schema.dialect = Some(dialect);
     }
 
     reconcile_schema_with_headers(&mut schema, &headers)?;
+    // Parquet, JSON, and Excel inputs have no dialect of their own, but CSV output
+    // still honours the dialect flags.
+    let dialect = schema
+        .dialect
+        .clone()
+        .unwrap_or_else(|| args.dialect.apply(Dialect::default()));
+    // Later inputs are checked against the layout of the files themselves, before the
+    // source column is added.
+    let input_schema = schema.clone();
+    let source_labels = match args.source_column.as_deref().map(str::trim) {
+        Some(name) => {
+            if name.is_empty() {
+                return Err(anyhow!("--source-column requires a column name"));
+            }
+            if headers.iter().any(|header| header == name) {
+                return Err(anyhow!(
+                    "--source-column '{name}' conflicts with an existing column"
+                ));
+            }
+            headers.push(name.to_string());
+            schema.columns.push(ColumnMeta {
+                name: name.to_string(),
+                datatype: ColumnType::String,
+                rename: None,
+                value_replacements: Vec::new(),
+                datatype_mappings: Vec::new(),
+                lookup: None,
+                start: None,
+                width: None,
+            });
+            Some(
+                inputs
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect::<Vec<_>>(),
+            )
+        }
+        None => None,
+    };
+    // Quarantined rows are written as read, before lookups add their columns.
+    let read_headers = headers.clone();
+    let lookups = match &args.schema {
+        Some(schema_path) => Lookups::load(
+            &mut schema,
+            &mut headers,
+            schema_path.parent().unwrap_or(Path::new("")),
+            args.on_miss,
+        )?,
+        None => None,
+    };
+    let records = InputRecords::new(
+        reader,
+        &inputs,
+        &input_schema,
+        delimiter,
+        input_encoding,
+        has_headers,
+        &args.input_format,
+    )
+    .with_malformed(malformed.as_ref());
 
     if args.apply_mappings && args.skip_mappings {
         return Err(anyhow!(
@@ -136,12 +306,101 @@ pub fn execute(args: &ProcessArgs) -> Result<()> {
         schema_has_mappings
     };
 
+    let column_map = build_column_map(&headers, &schema);
+    let distinct_plan = if args.distinct {
+        Some(DistinctPlan {
+            // The source column would make rows from different files distinct.
+            columns: (0..input_schema.columns.len()).collect(),
+            keep: args.keep,
+        })
+    } else if !distinct_columns.is_empty() {
+        Some(DistinctPlan {
+            columns: resolve_columns(&distinct_columns, &schema, &column_map, "Distinct")?,
+            keep: args.keep,
+        })
+    } else {
+        None
+    };
+    let window_plan = if window_specs.is_empty() {
+        None
+    } else {
+        Some(WindowPlan::resolve(&window_specs, &schema, |name| {
+            resolve_columns(&[name.to_string()], &schema, &column_map, "Window")
+                .map(|columns| columns[0])
+        })?)
+    };
+    if sorts.is_empty()
+        && let Some(plan) = window_plan.as_ref()
+    {
+        sorts = plan
+            .implied_sort()
+            .into_iter()
+            .map(|instruction| SortDirective {
+                column: headers[instruction.index].clone(),
+                ascending: instruction.ascending,
+            })
+            .collect();
+    }
+
+    if args.index.is_some() && inputs.len() > 1 {
+        return Err(anyhow!("--index can only be used with a single input file"));
+    }
+    if args.index.is_some() && args.on_type_error == OnTypeError::Quarantine {
+        return Err(anyhow!(
+            "--on-type-error quarantine cannot be used with --index; indexed reads have no row numbers"
+        ));
+    }
+    let type_errors = TypeErrors::new(args.on_type_error);
+    let mut type_rejects = TypeRejects::open(
+        args.on_type_error,
+        args.type_reject_file.as_deref(),
+        &inputs,
+        &read_headers,
+        &schema,
+    )?;
+    if args.index.is_some() {
+        let format = if parquet_input::is_parquet(input) {
+            Some("Parquet")
+        } else if json_input::is_json(input) {
+            Some("JSON")
+        } else if excel_input::is_excel(input) {
+            Some("Excel")
+        } else if input_schema
+            .columns
+            .iter()
+            .any(|column| column.width.is_some())
+        {
+            Some("fixed-width")
+        } else {
+            None
+        };
+        if let Some(format) = format {
+            return Err(anyhow!("--index cannot be used with {format} input"));
+        }
+    }
     let maybe_index = if let Some(index_path) = &args.index {
-        Some(CsvIndex::load(index_path)?)
+        Some(load_current_index(
+            index_path,
+            input,
+            args.rebuild_stale_index,
+            args.schema.is_some().then_some(&input_schema),
+            delimiter,
+            input_encoding,
+            &dialect,
+        )?)
     } else {
         None
     };
 
+    if sorts.is_empty()
+        && args.index_variant.is_none()
+        && let (Some(index), Some(plan)) = (maybe_index.as_ref(), distinct_plan.as_ref())
+        && let Some(directives) = distinct_sort_directives(index, &headers, &plan.columns)
+    {
+        debug!("Reading in index order so duplicate keys arrive adjacently");
+        sorts = directives;
+    }
+
     let requested_variant = args
         .index_variant
         .as_ref()
@@ -197,11 +456,20 @@ pub fn execute(args: &ProcessArgs) -> Result<()> {
         None
     };
 
-    let column_map = build_column_map(&headers, &schema);
     let sort_plan = build_sort_plan(&sorts, &schema, &column_map)?;
+    if let Some(plan) = window_plan.as_ref() {
+        plan.validate_order(&sort_plan)?;
+    }
+    let matching_variant = match (matching_variant, distinct_plan.as_ref()) {
+        (Some(_), Some(plan)) if !leads_with_columns(&sort_plan, &plan.columns) => {
+            debug!("Index not used because the sort order does not group distinct keys together");
+            None
+        }
+        (variant, _) => variant,
+    };
     let filter_conditions = filters;
 
-    let output_plan = OutputPlan::new(
+    let mut output_plan = OutputPlan::new(
         &headers,
         &schema,
         &selected_columns,
@@ -210,6 +478,29 @@ pub fn execute(args: &ProcessArgs) -> Result<()> {
         args.row_numbers,
         args.boolean_format,
     )?;
+    if let Some(plan) = window_plan.as_ref() {
+        output_plan.insert_window_columns(headers.len(), plan.names());
+    }
+    if matches!(args.output_format, OutputFormat::Json | OutputFormat::Jsonl) {
+        output_plan.json = Some(args.json_decimals);
+    }
+    output_plan.typed = args.output_format == OutputFormat::Parquet && !use_table_output;
+
+    // Derived expressions may reference window columns, which follow the input columns.
+    let expression_headers = headers
+        .iter()
+        .cloned()
+        .chain(
+            window_plan
+                .iter()
+                .flat_map(|plan| plan.names().map(str::to_string)),
+        )
+        .collect::<Vec<_>>();
+
+    let pool = build_thread_pool(args.threads)?;
+    if pool.is_some() && matching_variant.is_some() {
+        warn!("--threads is ignored for index-accelerated processing; rows are read sequentially");
+    }
 
     if args.table && !use_table_output && !args.preview {
         debug!("--table requested but output will remain CSV because a file path was provided");
@@ -228,16 +519,27 @@ pub fn execute(args: &ProcessArgs) -> Result<()> {
                 sink: OutputSink::Table(&mut rows_for_table),
                 limit,
                 apply_mappings,
+                memory_limit: args.memory_limit,
+                spill_dir: args.temp_dir.as_deref(),
+                pool: pool.as_ref(),
+                source_labels: source_labels.as_deref(),
+                lookups: lookups.as_ref(),
+                distinct: distinct_plan.as_ref(),
+                sample: sample_plan.as_ref(),
+                windows: window_plan.as_ref(),
+                expression_headers: &expression_headers,
+                type_errors: &type_errors,
+                type_rejects: type_rejects.as_mut(),
             };
 
             if let Some(variant) = matching_variant {
-                if io_utils::is_dash(&args.input) {
+                if io_utils::is_dash(input) {
                     return Err(anyhow!(
                         "Index accelerated processing requires a regular file input"
                     ));
                 }
                 let mut seek_reader =
-                    io_utils::open_seekable_csv_reader(&args.input, delimiter, true)?;
+                    io_utils::open_seekable_csv_reader(input, delimiter, true, &dialect)?;
                 // Read and discard headers to align reader position with data start.
                 seek_reader.byte_headers()?;
                 let covered = variant.columns().len();
@@ -257,22 +559,64 @@ pub fn execute(args: &ProcessArgs) -> Result<()> {
                 if maybe_index.is_some() {
                     debug!("Index present but not used due to incompatible sort signature");
                 }
-                engine.process_in_memory(reader, input_encoding, sort_plan)?;
+                engine.process_in_memory(records, input_encoding, sort_plan)?;
             }
         }
 
         table::print_table(output_plan.headers(), &rows_for_table);
         if args.preview {
-            info!(
-                "Displayed {} row(s) from {:?}",
-                rows_for_table.len(),
-                args.input
-            );
+            info!("Displayed {} row(s) from {:?}", rows_for_table.len(), input);
         }
-        Ok(())
+        let malformed_rows = malformed
+            .as_ref()
+            .map(MalformedRows::finish)
+            .transpose()?
+            .unwrap_or_default();
+        type_errors.finish();
+        type_rejects.map_or(Ok(()), TypeRejects::finish)?;
+        Ok(malformed_rows)
     } else {
-        let mut writer = io_utils::open_csv_writer(output_path, output_delimiter, output_encoding)?;
-        write_headers(&mut writer, &output_plan)?;
+        let mut csv_output = None;
+        let mut json_output = None;
+        let mut parquet_output = None;
+        let sink = match args.output_format {
+            OutputFormat::Csv => {
+                let output = csv_output.insert(CsvOutput::open(
+                    output_path,
+                    output_delimiter,
+                    output_encoding,
+                    &dialect,
+                    &args.split,
+                )?);
+                output.start(output_plan.headers(), true)?;
+                OutputSink::Csv(output)
+            }
+            OutputFormat::Json | OutputFormat::Jsonl => {
+                OutputSink::Json(json_output.insert(JsonOutput::open(
+                    output_path,
+                    output_encoding,
+                    output_plan.headers(),
+                    args.output_format == OutputFormat::Jsonl,
+                )?))
+            }
+            OutputFormat::Parquet => {
+                let window_types = window_plan
+                    .iter()
+                    .flat_map(|plan| plan.column_types(&schema))
+                    .collect::<Vec<_>>();
+                let columns = output_plan
+                    .headers()
+                    .iter()
+                    .cloned()
+                    .zip(output_plan.column_types(&schema, &window_types))
+                    .collect::<Vec<_>>();
+                OutputSink::Parquet(parquet_output.insert(ParquetOutput::open(
+                    output_path,
+                    &columns,
+                    &args.parquet,
+                )?))
+            }
+        };
         {
             let mut engine = ProcessEngine {
                 schema: &schema,
@@ -281,19 +625,30 @@ pub fn execute(args: &ProcessArgs) -> Result<()> {
                 filter_exprs: &args.filter_exprs,
                 derived_columns: &derived_columns,
                 output_plan: &output_plan,
-                sink: OutputSink::Csv(&mut writer),
+                sink,
                 limit,
                 apply_mappings,
+                memory_limit: args.memory_limit,
+                spill_dir: args.temp_dir.as_deref(),
+                pool: pool.as_ref(),
+                source_labels: source_labels.as_deref(),
+                lookups: lookups.as_ref(),
+                distinct: distinct_plan.as_ref(),
+                sample: sample_plan.as_ref(),
+                windows: window_plan.as_ref(),
+                expression_headers: &expression_headers,
+                type_errors: &type_errors,
+                type_rejects: type_rejects.as_mut(),
             };
 
             if let Some(variant) = matching_variant {
-                if io_utils::is_dash(&args.input) {
+                if io_utils::is_dash(input) {
                     return Err(anyhow!(
                         "Index accelerated processing requires a regular file input"
                     ));
                 }
                 let mut seek_reader =
-                    io_utils::open_seekable_csv_reader(&args.input, delimiter, true)?;
+                    io_utils::open_seekable_csv_reader(input, delimiter, true, &dialect)?;
                 // Read and discard headers to align reader position with data start.
                 seek_reader.byte_headers()?;
                 let covered = variant.columns().len();
@@ -313,11 +668,65 @@ pub fn execute(args: &ProcessArgs) -> Result<()> {
                 if maybe_index.is_some() {
                     debug!("Index present but not used due to incompatible sort signature");
                 }
-                engine.process_in_memory(reader, input_encoding, sort_plan)?;
+                engine.process_in_memory(records, input_encoding, sort_plan)?;
             }
         }
-        writer.flush().context("Flushing output")
+        if let Some(output) = csv_output {
+            output.finish()?;
+        }
+        if let Some(output) = json_output {
+            output.finish()?;
+        }
+        if let Some(output) = parquet_output {
+            output.finish()?;
+        }
+        let malformed_rows = malformed
+            .as_ref()
+            .map(MalformedRows::finish)
+            .transpose()?
+            .unwrap_or_default();
+        type_errors.finish();
+        type_rejects.map_or(Ok(()), TypeRejects::finish)?;
+        Ok(malformed_rows)
+    }
+}
+
+/// Loads the index at `index_path`, refusing (or, with `rebuild`, rebuilding)
+/// one whose input changed after it was built.
+fn load_current_index(
+    index_path: &Path,
+    input: &Path,
+    rebuild: bool,
+    schema: Option<&Schema>,
+    delimiter: u8,
+    encoding: &'static Encoding,
+    dialect: &Dialect,
+) -> Result<CsvIndex> {
+    let index = CsvIndex::load(index_path)?;
+    let reason = match index.source_status(input)? {
+        SourceStatus::Current => return Ok(index),
+        SourceStatus::Unrecorded if !rebuild => {
+            warn!(
+                "Index {index_path:?} does not record the file it was built from, so it cannot be checked for staleness; rebuild it to enable the check"
+            );
+            return Ok(index);
+        }
+        SourceStatus::Unrecorded => "is not recorded in the index".to_string(),
+        SourceStatus::Changed(reason) => reason,
+    };
+    if !rebuild {
+        return Err(anyhow!(
+            "Index {index_path:?} is stale: {input:?} {reason} since it was indexed. Rebuild it with `csv-managed index` or pass --rebuild-stale-index"
+        ));
     }
+    info!("Rebuilding index {index_path:?}: {input:?} {reason}");
+    let rebuilt = index
+        .rebuild(input, schema, delimiter, encoding, dialect)
+        .with_context(|| format!("Rebuilding index for {input:?}"))?;
+    rebuilt
+        .save(index_path)
+        .with_context(|| format!("Writing index to {index_path:?}"))?;
+    Ok(rebuilt)
 }
 
 fn reconcile_schema_with_headers(schema: &mut Schema, headers: &[String]) -> Result<()> {
@@ -330,6 +739,9 @@ fn reconcile_schema_with_headers(schema: &mut Schema, headers: &[String]) -> Res
                 rename: None,
                 value_replacements: Vec::new(),
                 datatype_mappings: Vec::new(),
+                lookup: None,
+                start: None,
+                width: None,
             })
             .collect();
         return Ok(());
@@ -358,6 +770,79 @@ fn build_column_map(
     map
 }
 
+fn resolve_columns(
+    names: &[String],
+    schema: &Schema,
+    column_map: &std::collections::HashMap<String, usize>,
+    purpose: &str,
+) -> Result<Vec<usize>> {
+    names
+        .iter()
+        .map(|name| {
+            column_map
+                .get(name)
+                .copied()
+                .or_else(|| schema.column_index(name))
+                .ok_or_else(|| anyhow!("{purpose} column '{name}' not found"))
+        })
+        .collect()
+}
+
+/// Returns `true` when the first `columns.len()` sort instructions cover exactly `columns`,
+/// so rows sharing those values are emitted consecutively.
+fn leads_with_columns(sort_plan: &[SortInstruction], columns: &[usize]) -> bool {
+    if sort_plan.len() < columns.len() {
+        return false;
+    }
+    let leading = sort_plan[..columns.len()]
+        .iter()
+        .map(|instruction| instruction.index)
+        .collect::<std::collections::BTreeSet<_>>();
+    leading == columns.iter().copied().collect()
+}
+
+/// Derives sort directives from the index variant whose leading columns best cover the
+/// distinct key, so an unsorted `--distinct-on` run can stream through the index.
+fn distinct_sort_directives(
+    index: &CsvIndex,
+    headers: &[String],
+    key_columns: &[usize],
+) -> Option<Vec<SortDirective>> {
+    let header_index = |name: &String| headers.iter().position(|header| header == name);
+    let variant = index
+        .variants()
+        .iter()
+        .filter_map(|variant| {
+            let covered = variant.columns().len().min(key_columns.len());
+            let leads = variant.columns()[..covered]
+                .iter()
+                .all(|column| header_index(column).is_some_and(|idx| key_columns.contains(&idx)));
+            (covered > 0 && leads).then_some((covered, variant))
+        })
+        .max_by_key(|(covered, _)| *covered)
+        .map(|(_, variant)| variant)?;
+
+    let mut directives = variant
+        .columns()
+        .iter()
+        .zip(variant.directions())
+        .map(|(column, direction)| SortDirective {
+            column: column.clone(),
+            ascending: *direction == SortDirection::Asc,
+        })
+        .collect::<Vec<_>>();
+    for idx in key_columns {
+        let name = &headers[*idx];
+        if !variant.columns().contains(name) {
+            directives.push(SortDirective {
+                column: name.clone(),
+                ascending: true,
+            });
+        }
+    }
+    Some(directives)
+}
+
 fn build_sort_plan(
     directives: &[SortDirective],
     schema: &Schema,
@@ -379,17 +864,10 @@ fn build_sort_plan(
         .collect()
 }
 
-fn write_headers(
-    writer: &mut csv::Writer<Box<dyn std::io::Write>>,
-    plan: &OutputPlan,
-) -> Result<()> {
-    writer
-        .write_record(plan.headers.iter())
-        .context("Writing output headers")
-}
-
 enum OutputSink<'a> {
-    Csv(&'a mut csv::Writer<Box<dyn std::io::Write>>),
+    Csv(&'a mut CsvOutput),
+    Json(&'a mut JsonOutput),
+    Parquet(&'a mut ParquetOutput),
     Table(&'a mut Vec<Vec<String>>),
 }
 
@@ -403,72 +881,177 @@ struct ProcessEngine<'a, 'b> {
     sink: OutputSink<'b>,
     limit: Option<usize>,
     apply_mappings: bool,
+    memory_limit: Option<usize>,
+    spill_dir: Option<&'a Path>,
+    pool: Option<&'a ThreadPool>,
+    source_labels: Option<&'a [String]>,
+    lookups: Option<&'a Lookups>,
+    distinct: Option<&'a DistinctPlan>,
+    sample: Option<&'a SamplePlan>,
+    windows: Option<&'a WindowPlan>,
+    expression_headers: &'a [String],
+    type_errors: &'a TypeErrors,
+    type_rejects: Option<&'b mut TypeRejects>,
 }
 
 impl<'a, 'b> ProcessEngine<'a, 'b> {
     fn process_in_memory(
         &mut self,
-        reader: csv::Reader<Box<dyn std::io::Read>>,
+        records: InputRecords<'_>,
         encoding: &'static Encoding,
         sort_plan: Vec<SortInstruction>,
     ) -> Result<()> {
-        let mut rows: Vec<RowData> = Vec::new();
+        let mut buffer = if sort_plan.is_empty() {
+            RowBuffer::Unsorted(Vec::new())
+        } else if let Some(limit) = self.limit {
+            debug!("Selecting top {limit} row(s) with a bounded heap");
+            // `lead` windows look past the last emitted row, so keep those rows too.
+            let lookahead = self.windows.map_or(0, WindowPlan::lookahead);
+            RowBuffer::TopN(TopRows::new(limit + lookahead, &sort_plan))
+        } else if let Some(memory_limit) = self.memory_limit {
+            RowBuffer::External(ExternalSorter::new(
+                &sort_plan,
+                memory_limit,
+                self.spill_dir,
+            ))
+        } else {
+            RowBuffer::Sorted(Vec::new())
+        };
 
-        for (ordinal, result) in reader.into_byte_records().enumerate() {
-            let record = result.with_context(|| format!("Reading row {}", ordinal + 2))?;
-            let mut raw = io_utils::decode_record(&record, encoding)?;
-            if self.apply_mappings {
-                self.schema
-                    .apply_transformations_to_row(&mut raw)
-                    .with_context(|| {
-                        format!("Applying datatype mappings to row {}", ordinal + 2)
-                    })?;
-            }
-            self.schema.apply_replacements_to_row(&mut raw);
-            let typed = parse_typed_row(self.schema, &raw)?;
+        if let Some(pool) = self.pool {
+            return self.process_in_memory_parallel(pool, records, encoding, buffer, &sort_plan);
+        }
 
-            if !self.filters.is_empty()
-                && !evaluate_conditions(self.filters, self.schema, self.headers, &raw, &typed)?
-            {
-                continue;
+        let stages = self.row_stages();
+        let mut selection = self.row_selection();
+        for (ordinal, result) in records.enumerate() {
+            let (file, record) = result?;
+            let row = match stages.prepare(&record, file, encoding, ordinal)? {
+                Prepared::Row(row) => row,
+                Prepared::Filtered => continue,
+                Prepared::Rejected(rejection) => {
+                    self.reject(&rejection)?;
+                    continue;
+                }
+            };
+            if let Some(row) = selection.offer(row) {
+                buffer.push(row)?;
             }
+        }
+        for row in selection.finish() {
+            buffer.push(row)?;
+        }
 
-            if !self.filter_exprs.is_empty()
-                && !evaluate_filter_expressions(
-                    self.filter_exprs,
-                    self.headers,
-                    &raw,
-                    &typed,
-                    Some(ordinal + 1),
-                )?
-            {
-                continue;
+        let rows = WindowedRows::new(self.windows, buffer.finish(&sort_plan)?);
+        for (written, row) in rows.enumerate() {
+            if self.limit.is_some_and(|limit| written >= limit) {
+                break;
             }
+            let row = row?;
+            self.emit_row(&row.raw, &row.typed, written + 1)?;
+        }
 
-            rows.push(RowData {
-                raw,
-                typed,
-                ordinal,
+        Ok(())
+    }
+
+    /// Runs the per-row stages on `pool` in batches of [`PARALLEL_BATCH_ROWS`].
+    ///
+    /// Batches are read sequentially, prepared and derived in parallel, and
+    /// reassembled by position so output order matches the single-threaded path.
+    /// Unsorted output streams batch by batch; sorted output is buffered as usual
+    /// and only the derive/format stage of emission is parallelised.
+    fn process_in_memory_parallel(
+        &mut self,
+        pool: &ThreadPool,
+        records: InputRecords<'_>,
+        encoding: &'static Encoding,
+        mut buffer: RowBuffer<'_>,
+        sort_plan: &[SortInstruction],
+    ) -> Result<()> {
+        let stages = self.row_stages();
+        let mut selection = self.row_selection();
+        // `--keep last` and `--sample N` only settle once the input is exhausted, so they
+        // cannot stream. Windows carry state from row to row and are evaluated sequentially
+        // after buffering.
+        let streaming = matches!(buffer, RowBuffer::Unsorted(_))
+            && self.windows.is_none()
+            && self.sample.is_none_or(SamplePlan::is_streaming)
+            && self
+                .distinct
+                .is_none_or(|plan| plan.keep == DistinctKeep::First);
+        let mut records = records.enumerate();
+        let mut written = 0usize;
+
+        loop {
+            if streaming && self.limit.is_some_and(|limit| written >= limit) {
+                return Ok(());
+            }
+            let batch = records
+                .by_ref()
+                .take(PARALLEL_BATCH_ROWS)
+                .map(|(ordinal, result)| result.map(|(file, record)| (ordinal, file, record)))
+                .collect::<Result<Vec<_>>>()?;
+            if batch.is_empty() {
+                break;
+            }
+            let prepared: Vec<Result<Prepared>> = pool.install(|| {
+                batch
+                    .par_iter()
+                    .map(|(ordinal, file, record)| {
+                        stages.prepare(record, *file, encoding, *ordinal)
+                    })
+                    .collect()
             });
+            let mut rows = Vec::with_capacity(prepared.len());
+            for row in prepared {
+                let row = match row? {
+                    Prepared::Row(row) => row,
+                    Prepared::Filtered => continue,
+                    // Rejected rows are written here so the reject file keeps input order.
+                    Prepared::Rejected(rejection) => {
+                        self.reject(&rejection)?;
+                        continue;
+                    }
+                };
+                rows.extend(selection.offer(row));
+            }
+            if streaming {
+                if let Some(limit) = self.limit {
+                    rows.truncate(limit - written);
+                }
+                self.emit_batch(pool, &rows, written + 1)?;
+                written += rows.len();
+            } else {
+                for row in rows {
+                    buffer.push(row)?;
+                }
+            }
         }
 
-        if !sort_plan.is_empty() {
-            rows.sort_by(|a, b| compare_rows(a, b, &sort_plan));
+        if streaming {
+            return Ok(());
         }
-
-        for (written, row) in rows.into_iter().enumerate() {
-            if self.limit.is_some_and(|limit| written >= limit) {
+        for row in selection.finish() {
+            buffer.push(row)?;
+        }
+        let mut sorted = WindowedRows::new(self.windows, buffer.finish(sort_plan)?);
+        loop {
+            let take = self.limit.map_or(PARALLEL_BATCH_ROWS, |limit| {
+                (limit - written).min(PARALLEL_BATCH_ROWS)
+            });
+            let rows = sorted.by_ref().take(take).collect::<Result<Vec<_>>>()?;
+            if rows.is_empty() {
                 break;
             }
-            self.emit_row(&row.raw, &row.typed, written + 1)?;
+            self.emit_batch(pool, &rows, written + 1)?;
+            written += rows.len();
         }
-
         Ok(())
     }
 
     fn process_with_index(
         &mut self,
-        reader: &mut csv::Reader<std::io::BufReader<File>>,
+        reader: &mut csv::Reader<Box<dyn SeekRead>>,
         encoding: &'static Encoding,
         variant: &IndexVariant,
         sort_plan: &[SortInstruction],
@@ -479,6 +1062,11 @@ impl<'a, 'b> ProcessEngine<'a, 'b> {
         let prefix_len = variant.columns().len();
         let mut current_prefix: Option<Vec<Option<Value>>> = None;
         let mut bucket: Vec<RowData> = Vec::new();
+        let mut stages = StreamStages {
+            distinct: self.distinct.map(AdjacentDistinct::new),
+            sample: self.sample.map(Sampler::new),
+            windows: self.windows.map(WindowEvaluator::new),
+        };
 
         for offset in variant.ordered_offsets() {
             if self.limit.is_some_and(|limit| emitted >= limit) {
@@ -491,9 +1079,14 @@ impl<'a, 'b> ProcessEngine<'a, 'b> {
                 break;
             }
             let mut raw = io_utils::decode_record(&record, encoding)?;
+            if let Some(labels) = self.source_labels {
+                raw.push(labels[0].clone());
+            }
+            let mut errors = Vec::new();
             if self.apply_mappings {
-                self.schema
-                    .apply_transformations_to_row(&mut raw)
+                errors = self
+                    .type_errors
+                    .map_row(self.schema, &mut raw)
                     .with_context(|| {
                         format!(
                             "Applying datatype mappings to indexed row at byte offset {}",
@@ -502,7 +1095,16 @@ impl<'a, 'b> ProcessEngine<'a, 'b> {
                     })?;
             }
             self.schema.apply_replacements_to_row(&mut raw);
-            let typed = parse_typed_row(self.schema, &raw)?;
+            if let Some(lookups) = self.lookups {
+                lookups.enrich(&mut raw).with_context(|| {
+                    format!("Looking up values for indexed row at byte offset {offset}")
+                })?;
+            }
+            let typed = self.type_errors.type_row(self.schema, &raw, &mut errors)?;
+            // Quarantine needs row numbers, which indexed reads lack, so it is refused
+            // before reaching here.
+            self.type_errors
+                .settle(&mut raw, errors, None, (0, ordinal + 1));
             if !self.filters.is_empty()
                 && !evaluate_conditions(self.filters, self.schema, self.headers, &raw, &typed)?
             {
@@ -525,7 +1127,13 @@ impl<'a, 'b> ProcessEngine<'a, 'b> {
             match current_prefix.as_ref() {
                 Some(existing) if *existing == prefix_key => {}
                 Some(_) => {
-                    if self.flush_bucket(&mut bucket, sort_plan, prefix_len, &mut emitted)? {
+                    if self.flush_bucket(
+                        &mut bucket,
+                        sort_plan,
+                        prefix_len,
+                        &mut emitted,
+                        &mut stages,
+                    )? {
                         return Ok(());
                     }
                     current_prefix = Some(prefix_key.clone());
@@ -538,12 +1146,29 @@ impl<'a, 'b> ProcessEngine<'a, 'b> {
             bucket.push(RowData {
                 raw,
                 typed,
-                ordinal,
+                // Byte offsets follow input order and stay comparable across buckets,
+                // which `--keep first|last` relies on.
+                ordinal: offset as usize,
             });
             ordinal += 1;
         }
 
-        self.flush_bucket(&mut bucket, sort_plan, prefix_len, &mut emitted)?;
+        if self.flush_bucket(
+            &mut bucket,
+            sort_plan,
+            prefix_len,
+            &mut emitted,
+            &mut stages,
+        )? {
+            return Ok(());
+        }
+        for row in stages.finish()? {
+            if self.limit.is_some_and(|limit| emitted >= limit) {
+                break;
+            }
+            self.emit_row(&row.raw, &row.typed, emitted + 1)?;
+            emitted += 1;
+        }
 
         Ok(())
     }
@@ -554,6 +1179,7 @@ impl<'a, 'b> ProcessEngine<'a, 'b> {
         sort_plan: &[SortInstruction],
         prefix_len: usize,
         emitted: &mut usize,
+        stages: &mut StreamStages<'_>,
     ) -> Result<bool> {
         if bucket.is_empty() {
             return Ok(false);
@@ -570,6 +1196,9 @@ impl<'a, 'b> ProcessEngine<'a, 'b> {
         }
 
         for row in bucket.drain(..) {
+            let Some(row) = stages.offer(row)? else {
+                continue;
+            };
             if self.limit.is_some_and(|limit| *emitted >= limit) {
                 return Ok(true);
             }
@@ -590,21 +1219,287 @@ impl<'a, 'b> ProcessEngine<'a, 'b> {
             raw,
             typed,
             row_number,
-            self.headers,
+            self.expression_headers,
             self.derived_columns,
             self.output_plan,
         )?;
+        self.write_record(record)
+    }
+
+    /// Builds output records for `rows` in parallel and writes them in order.
+    fn emit_batch(
+        &mut self,
+        pool: &ThreadPool,
+        rows: &[RowData],
+        first_row_number: usize,
+    ) -> Result<()> {
+        let (headers, derived_columns, output_plan) = (
+            self.expression_headers,
+            self.derived_columns,
+            self.output_plan,
+        );
+        let records: Vec<Result<OutputRecord>> = pool.install(|| {
+            rows.par_iter()
+                .enumerate()
+                .map(|(offset, row)| {
+                    build_output_record(
+                        &row.raw,
+                        &row.typed,
+                        first_row_number + offset,
+                        headers,
+                        derived_columns,
+                        output_plan,
+                    )
+                })
+                .collect()
+        });
+        for record in records {
+            self.write_record(record?)?;
+        }
+        Ok(())
+    }
 
-        match &mut self.sink {
-            OutputSink::Csv(writer) => writer
-                .write_record(record.iter())
-                .context("Writing output row"),
-            OutputSink::Table(rows) => {
+    fn reject(&mut self, rejection: &TypeRejection) -> Result<()> {
+        match self.type_rejects.as_deref_mut() {
+            Some(rejects) => rejects.write(rejection),
+            None => Err(anyhow!(
+                "Row {}: {}",
+                rejection.row_number,
+                rejection.error.reason
+            )),
+        }
+    }
+
+    fn row_selection(&self) -> RowSelection<'a> {
+        RowSelection {
+            distinct: self.distinct.map(SeenKeys::new),
+            sample: self.sample.map(Sampler::new),
+        }
+    }
+
+    fn row_stages(&self) -> RowStages<'a> {
+        RowStages {
+            schema: self.schema,
+            headers: self.headers,
+            filters: self.filters,
+            filter_exprs: self.filter_exprs,
+            apply_mappings: self.apply_mappings,
+            source_labels: self.source_labels,
+            lookups: self.lookups,
+            type_errors: self.type_errors,
+        }
+    }
+
+    fn write_record(&mut self, record: OutputRecord) -> Result<()> {
+        match (&mut self.sink, record) {
+            (OutputSink::Csv(output), OutputRecord::Text(record)) => output.write_record(&record),
+            (OutputSink::Json(output), OutputRecord::Text(record)) => output.write_record(&record),
+            (OutputSink::Parquet(output), OutputRecord::Typed(values)) => {
+                output.write_values(values)
+            }
+            (OutputSink::Table(rows), OutputRecord::Text(record)) => {
                 rows.push(record);
                 Ok(())
             }
+            _ => Err(anyhow!("Output record does not match the output format")),
+        }
+    }
+}
+
+/// De-duplication and sampling applied to filtered rows before they are buffered.
+struct RowSelection<'p> {
+    distinct: Option<SeenKeys<'p>>,
+    sample: Option<Sampler>,
+}
+
+impl RowSelection<'_> {
+    /// Returns the row if it is selected now; held rows are released by [`finish`](Self::finish).
+    fn offer(&mut self, row: RowData) -> Option<RowData> {
+        let row = match self.distinct.as_mut() {
+            Some(distinct) => distinct.offer(row)?,
+            None => row,
+        };
+        match self.sample.as_mut() {
+            Some(sample) => sample.offer(row),
+            None => Some(row),
         }
     }
+
+    fn finish(self) -> Vec<RowData> {
+        let held = self.distinct.map(SeenKeys::finish).unwrap_or_default();
+        let Some(mut sample) = self.sample else {
+            return held;
+        };
+        let mut rows = held
+            .into_iter()
+            .filter_map(|row| sample.offer(row))
+            .collect::<Vec<_>>();
+        rows.extend(sample.finish());
+        rows
+    }
+}
+
+/// Row-to-row stages applied to the ordered stream read through an index.
+struct StreamStages<'p> {
+    distinct: Option<AdjacentDistinct<'p>>,
+    sample: Option<Sampler>,
+    windows: Option<WindowEvaluator<'p>>,
+}
+
+impl StreamStages<'_> {
+    /// Returns the next row ready for output, if the stages release one.
+    fn offer(&mut self, row: RowData) -> Result<Option<RowData>> {
+        let row = match self.distinct.as_mut() {
+            Some(distinct) => match distinct.offer(row) {
+                Some(row) => row,
+                None => return Ok(None),
+            },
+            None => row,
+        };
+        let row = match self.sample.as_mut() {
+            Some(sample) => match sample.offer(row) {
+                Some(row) => row,
+                None => return Ok(None),
+            },
+            None => row,
+        };
+        match self.windows.as_mut() {
+            Some(windows) => windows.push(row),
+            None => Ok(Some(row)),
+        }
+    }
+
+    /// Releases rows still held back once the stream is exhausted.
+    fn finish(self) -> Result<Vec<RowData>> {
+        let mut held = self
+            .distinct
+            .and_then(AdjacentDistinct::finish)
+            .into_iter()
+            .collect::<Vec<_>>();
+        if let Some(mut sample) = self.sample {
+            held = held
+                .into_iter()
+                .filter_map(|row| sample.offer(row))
+                .collect();
+            held.extend(sample.finish());
+        }
+        let Some(mut windows) = self.windows else {
+            return Ok(held);
+        };
+        let mut rows = Vec::new();
+        for row in held {
+            rows.extend(windows.push(row)?);
+        }
+        rows.extend(windows.finish());
+        Ok(rows)
+    }
+}
+
+/// Number of records handed to the worker pool at a time when `--threads` is set.
+const PARALLEL_BATCH_ROWS: usize = 8_192;
+
+fn build_thread_pool(threads: Option<usize>) -> Result<Option<ThreadPool>> {
+    match threads {
+        None | Some(1) => Ok(None),
+        Some(count) => {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(count)
+                .build()
+                .context("Building worker thread pool")?;
+            debug!(
+                "Processing rows on {} worker thread(s)",
+                pool.current_num_threads()
+            );
+            Ok(Some(pool))
+        }
+    }
+}
+
+/// Per-row decode, mapping, typing, and filter stages.
+///
+/// Holds only shared references so rows can be prepared on worker threads.
+#[derive(Clone, Copy)]
+struct RowStages<'a> {
+    schema: &'a Schema,
+    headers: &'a [String],
+    filters: &'a [crate::filter::FilterCondition],
+    filter_exprs: &'a [String],
+    apply_mappings: bool,
+    source_labels: Option<&'a [String]>,
+    lookups: Option<&'a Lookups>,
+    type_errors: &'a TypeErrors,
+}
+
+/// Outcome of the per-row stages for one record.
+enum Prepared {
+    Row(RowData),
+    /// Rejected by a filter.
+    Filtered,
+    /// Quarantined by `--on-type-error`.
+    Rejected(TypeRejection),
+}
+
+impl RowStages<'_> {
+    fn prepare(
+        &self,
+        record: &ByteRecord,
+        file: usize,
+        encoding: &'static Encoding,
+        ordinal: usize,
+    ) -> Result<Prepared> {
+        let mut raw = io_utils::decode_record(record, encoding)?;
+        if let Some(labels) = self.source_labels {
+            raw.push(labels[file].clone());
+        }
+        let line = record
+            .position()
+            .map_or(ordinal + 2, |position| position.line() as usize);
+        let original = (self.type_errors.policy() == OnTypeError::Quarantine).then(|| raw.clone());
+        let mut errors = Vec::new();
+        if self.apply_mappings {
+            errors = self
+                .type_errors
+                .map_row(self.schema, &mut raw)
+                .with_context(|| format!("Applying datatype mappings to row {line}"))?;
+        }
+        self.schema.apply_replacements_to_row(&mut raw);
+        if let Some(lookups) = self.lookups {
+            lookups
+                .enrich(&mut raw)
+                .with_context(|| format!("Looking up values for row {line}"))?;
+        }
+        let typed = self.type_errors.type_row(self.schema, &raw, &mut errors)?;
+        if let Some(rejection) = self
+            .type_errors
+            .settle(&mut raw, errors, original, (file, line))
+        {
+            return Ok(Prepared::Rejected(rejection));
+        }
+
+        if !self.filters.is_empty()
+            && !evaluate_conditions(self.filters, self.schema, self.headers, &raw, &typed)?
+        {
+            return Ok(Prepared::Filtered);
+        }
+
+        if !self.filter_exprs.is_empty()
+            && !evaluate_filter_expressions(
+                self.filter_exprs,
+                self.headers,
+                &raw,
+                &typed,
+                Some(ordinal + 1),
+            )?
+        {
+            return Ok(Prepared::Filtered);
+        }
+
+        Ok(Prepared::Row(RowData {
+            raw,
+            typed,
+            ordinal,
+        }))
+    }
 }
 
 fn build_prefix_key(
@@ -620,6 +1515,12 @@ fn build_prefix_key(
         .collect()
 }
 
+/// A row ready for the sink: rendered text, or typed values for Parquet.
+enum OutputRecord {
+    Text(Vec<String>),
+    Typed(Vec<Option<Value>>),
+}
+
 fn build_output_record(
     raw: &[String],
     typed: &[Option<Value>],
@@ -627,7 +1528,18 @@ fn build_output_record(
     headers: &[String],
     derived_columns: &[DerivedColumn],
     output_plan: &OutputPlan,
-) -> Result<Vec<String>> {
+) -> Result<OutputRecord> {
+    if output_plan.typed {
+        return build_output_values(
+            raw,
+            typed,
+            row_number,
+            headers,
+            derived_columns,
+            output_plan,
+        )
+        .map(OutputRecord::Typed);
+    }
     let mut record = Vec::with_capacity(output_plan.fields.len());
     for field in &output_plan.fields {
         match field {
@@ -639,16 +1551,53 @@ fn build_output_record(
                 record.push(formatted);
             }
             OutputField::Derived(idx) => {
-                let derived =
-                    derived_columns[*idx].evaluate(headers, raw, typed, Some(row_number))?;
-                record.push(derived);
+                let derived = &derived_columns[*idx];
+                record.push(if output_plan.json.is_some() {
+                    json_output::eval_token(&derived.evaluate_value(
+                        headers,
+                        raw,
+                        typed,
+                        Some(row_number),
+                    )?)
+                } else {
+                    derived.evaluate(headers, raw, typed, Some(row_number))?
+                });
             }
         }
     }
-    Ok(record)
+    Ok(OutputRecord::Text(record))
+}
+
+/// Collects the typed values of the output fields, so Parquet output writes the
+/// parsed cells rather than their rendered text. Derived columns are strings.
+fn build_output_values(
+    raw: &[String],
+    typed: &[Option<Value>],
+    row_number: usize,
+    headers: &[String],
+    derived_columns: &[DerivedColumn],
+    output_plan: &OutputPlan,
+) -> Result<Vec<Option<Value>>> {
+    output_plan
+        .fields
+        .iter()
+        .map(|field| match field {
+            OutputField::RowNumber => Ok(Some(Value::Integer(row_number as i64))),
+            OutputField::ExistingColumn(idx) => Ok(typed.get(*idx).cloned().flatten()),
+            OutputField::Derived(idx) => {
+                let value =
+                    derived_columns[*idx].evaluate(headers, raw, typed, Some(row_number))?;
+                Ok((!value.is_empty()).then_some(Value::String(value)))
+            }
+        })
+        .collect()
 }
 
-fn compare_rows(a: &RowData, b: &RowData, plan: &[SortInstruction]) -> std::cmp::Ordering {
+pub(crate) fn compare_rows(
+    a: &RowData,
+    b: &RowData,
+    plan: &[SortInstruction],
+) -> std::cmp::Ordering {
     for directive in plan {
         let left = ComparableValue(a.typed[directive.index].clone());
         let right = ComparableValue(b.typed[directive.index].clone());
@@ -664,17 +1613,71 @@ fn compare_rows(a: &RowData, b: &RowData, plan: &[SortInstruction]) -> std::cmp:
     a.ordinal.cmp(&b.ordinal)
 }
 
-#[derive(Debug)]
-struct RowData {
-    raw: Vec<String>,
-    typed: Vec<Option<Value>>,
-    ordinal: usize,
+/// Collects filtered rows for the in-memory path according to the sort strategy.
+enum RowBuffer<'p> {
+    Unsorted(Vec<RowData>),
+    Sorted(Vec<RowData>),
+    TopN(TopRows<'p>),
+    External(ExternalSorter<'p>),
 }
 
-#[derive(Debug)]
-struct SortInstruction {
-    index: usize,
-    ascending: bool,
+impl<'p> RowBuffer<'p> {
+    fn push(&mut self, row: RowData) -> Result<()> {
+        match self {
+            RowBuffer::Unsorted(rows) | RowBuffer::Sorted(rows) => rows.push(row),
+            RowBuffer::TopN(top) => top.push(row),
+            RowBuffer::External(sorter) => sorter.push(row)?,
+        }
+        Ok(())
+    }
+
+    fn finish(self, sort_plan: &[SortInstruction]) -> Result<SortedRows<'p>> {
+        match self {
+            RowBuffer::Unsorted(rows) => Ok(SortedRows::InMemory(rows.into_iter())),
+            RowBuffer::Sorted(mut rows) => {
+                rows.sort_by(|a, b| compare_rows(a, b, sort_plan));
+                Ok(SortedRows::InMemory(rows.into_iter()))
+            }
+            RowBuffer::TopN(top) => Ok(SortedRows::InMemory(top.into_sorted_vec().into_iter())),
+            RowBuffer::External(sorter) => sorter.finish(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct RowData {
+    pub(crate) raw: Vec<String>,
+    pub(crate) typed: Vec<Option<Value>>,
+    pub(crate) ordinal: usize,
+}
+
+impl RowData {
+    /// Approximates the heap footprint of the row for `--memory-limit` accounting.
+    pub(crate) fn estimated_size(&self) -> usize {
+        let raw_bytes: usize = self
+            .raw
+            .iter()
+            .map(|value| std::mem::size_of::<String>() + value.capacity())
+            .sum();
+        let typed_bytes: usize = self
+            .typed
+            .iter()
+            .map(|value| {
+                std::mem::size_of::<Option<Value>>()
+                    + match value {
+                        Some(Value::String(inner)) => inner.capacity(),
+                        _ => 0,
+                    }
+            })
+            .sum();
+        std::mem::size_of::<RowData>() + raw_bytes + typed_bytes
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct SortInstruction {
+    pub(crate) index: usize,
+    pub(crate) ascending: bool,
 }
 
 #[derive(Debug)]
@@ -710,6 +1713,10 @@ struct OutputPlan {
     headers: Vec<String>,
     fields: Vec<OutputField>,
     boolean_format: BooleanFormat,
+    /// Set when cells are rendered as JSON tokens rather than CSV text.
+    json: Option<JsonDecimals>,
+    /// Set when rows are passed to the sink as typed values (Parquet output).
+    typed: bool,
 }
 
 impl OutputPlan {
@@ -755,10 +1762,33 @@ impl OutputPlan {
             headers: output_headers,
             fields,
             boolean_format,
+            json: None,
+            typed: false,
         })
     }
 
+    /// Emits window columns ahead of derived columns. Window values are appended to each
+    /// row after the input columns, starting at `first_index`.
+    fn insert_window_columns<'n>(
+        &mut self,
+        first_index: usize,
+        names: impl Iterator<Item = &'n str>,
+    ) {
+        let position = self
+            .fields
+            .iter()
+            .position(|field| matches!(field, OutputField::Derived(_)))
+            .unwrap_or(self.fields.len());
+        let names = names.map(str::to_string).collect::<Vec<_>>();
+        let fields = (first_index..first_index + names.len()).map(OutputField::ExistingColumn);
+        self.fields.splice(position..position, fields);
+        self.headers.splice(position..position, names);
+    }
+
     fn format_existing_value(&self, raw: &str, typed: Option<&Value>) -> String {
+        if let Some(decimals) = self.json {
+            return json_output::value_token(raw, typed, decimals);
+        }
         match (self.boolean_format, typed) {
             (BooleanFormat::Original, _) => raw.to_string(),
             (BooleanFormat::TrueFalse, Some(Value::Boolean(true))) => "true".to_string(),
@@ -772,6 +1802,22 @@ impl OutputPlan {
     fn headers(&self) -> &[String] {
         &self.headers
     }
+
+    /// Datatypes of the output fields. Indices past the schema columns are window
+    /// columns; derived columns are written as strings.
+    fn column_types(&self, schema: &Schema, window_types: &[ColumnType]) -> Vec<ColumnType> {
+        self.fields
+            .iter()
+            .map(|field| match field {
+                OutputField::RowNumber => ColumnType::Integer,
+                OutputField::ExistingColumn(idx) => match schema.columns.get(*idx) {
+                    Some(column) => column.datatype.clone(),
+                    None => window_types[*idx - schema.columns.len()].clone(),
+                },
+                OutputField::Derived(_) => ColumnType::String,
+            })
+            .collect()
+    }
 }
 
 #[derive(Debug)]
@@ -0,0 +1,191 @@
+//! Reproducible row sampling for `process --sample` and `--sample-fraction`, and
+//! for `schema probe`/`schema infer --seed`.
+//!
+//! Sampling runs after filters and de-duplication, so a sample draws only from
+//! rows that would otherwise be emitted, and before sorting, windows, and
+//! derived columns.
+//!
+//! - `--sample N` keeps a uniform random sample of exactly `N` rows (or every
+//!   row when fewer match) using reservoir sampling. The reservoir holds at most
+//!   `N` rows, so memory stays bounded regardless of input size.
+//! - `--sample-fraction P` keeps each row independently with probability `P`
+//!   (Bernoulli sampling) and streams without buffering.
+//!
+//! Random draws come from a ChaCha8 generator seeded with `--seed`, and rows are
+//! offered in a fixed order on every code path, so the same seed, input, and
+//! filters select the same rows regardless of `--threads`. Without `--seed` a
+//! random seed is chosen and logged so a run can be repeated.
+//!
+//! Schema inference reuses the reservoir: with `--seed`, `--sample-rows N`
+//! votes over `N` records drawn from the whole input instead of the first `N`.
+
+use anyhow::{Result, anyhow};
+use log::info;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::process::RowData;
+
+/// Sampling method and seed for a process run.
+#[derive(Debug, Clone)]
+pub(crate) struct SamplePlan {
+    method: SampleMethod,
+    seed: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum SampleMethod {
+    Rows(usize),
+    Fraction(f64),
+}
+
+impl SamplePlan {
+    /// Builds a plan from the CLI options, or `None` when sampling is not requested.
+    pub(crate) fn from_args(
+        rows: Option<usize>,
+        fraction: Option<f64>,
+        seed: Option<u64>,
+    ) -> Result<Option<Self>> {
+        let method = match (rows, fraction) {
+            (Some(rows), None) => SampleMethod::Rows(rows),
+            (None, Some(fraction)) => {
+                if !(fraction > 0.0 && fraction <= 1.0) {
+                    return Err(anyhow!(
+                        "--sample-fraction must be greater than 0 and at most 1 (got {fraction})"
+                    ));
+                }
+                SampleMethod::Fraction(fraction)
+            }
+            (Some(_), Some(_)) => {
+                return Err(anyhow!(
+                    "--sample and --sample-fraction cannot be used together"
+                ));
+            }
+            (None, None) => {
+                if seed.is_some() {
+                    return Err(anyhow!("--seed requires --sample or --sample-fraction"));
+                }
+                return Ok(None);
+            }
+        };
+        let seed = seed.unwrap_or_else(|| {
+            let seed = rand::random();
+            info!("Sampling with seed {seed} (pass --seed {seed} to repeat this sample)");
+            seed
+        });
+        Ok(Some(Self { method, seed }))
+    }
+
+    /// A reservoir sample of `rows` rows drawn with `seed`.
+    pub(crate) fn reservoir(rows: usize, seed: u64) -> Self {
+        Self {
+            method: SampleMethod::Rows(rows),
+            seed,
+        }
+    }
+
+    /// Returns `true` when sampled rows are released as they arrive.
+    pub(crate) fn is_streaming(&self) -> bool {
+        matches!(self.method, SampleMethod::Fraction(_))
+    }
+}
+
+/// Applies a [`SamplePlan`] to rows offered in a deterministic order.
+pub(crate) struct Sampler<T = RowData> {
+    method: SampleMethod,
+    rng: ChaCha8Rng,
+    seen: usize,
+    reservoir: Vec<(usize, T)>,
+}
+
+impl<T> Sampler<T> {
+    pub(crate) fn new(plan: &SamplePlan) -> Self {
+        Self {
+            method: plan.method,
+            rng: ChaCha8Rng::seed_from_u64(plan.seed),
+            seen: 0,
+            reservoir: Vec::new(),
+        }
+    }
+
+    /// Offers the next row. Fraction sampling returns the row if it is selected;
+    /// row-count sampling retains it until [`finish`](Self::finish).
+    pub(crate) fn offer(&mut self, row: T) -> Option<T> {
+        let arrival = self.seen;
+        self.seen += 1;
+        match self.method {
+            SampleMethod::Fraction(fraction) => self.rng.random_bool(fraction).then_some(row),
+            SampleMethod::Rows(capacity) => {
+                if self.reservoir.len() < capacity {
+                    self.reservoir.push((arrival, row));
+                } else {
+                    let slot = self.rng.random_range(0..self.seen);
+                    if slot < capacity {
+                        self.reservoir[slot] = (arrival, row);
+                    }
+                }
+                None
+            }
+        }
+    }
+
+    /// Returns the retained rows in the order they were offered.
+    pub(crate) fn finish(self) -> Vec<T> {
+        let mut rows = self.reservoir;
+        rows.sort_by_key(|(arrival, _)| *arrival);
+        rows.into_iter().map(|(_, row)| row).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(ordinal: usize) -> RowData {
+        RowData {
+            raw: vec![ordinal.to_string()],
+            typed: vec![None],
+            ordinal,
+        }
+    }
+
+    fn sample(plan: &SamplePlan, rows: usize) -> Vec<usize> {
+        let mut sampler = Sampler::new(plan);
+        let mut kept = (0..rows)
+            .filter_map(|ordinal| sampler.offer(row(ordinal)))
+            .map(|row| row.ordinal)
+            .collect::<Vec<_>>();
+        kept.extend(sampler.finish().into_iter().map(|row| row.ordinal));
+        kept
+    }
+
+    #[test]
+    fn reservoir_is_repeatable_and_ordered() {
+        let plan = SamplePlan::from_args(Some(25), None, Some(7))
+            .expect("plan")
+            .expect("sampling enabled");
+        let first = sample(&plan, 1_000);
+        assert_eq!(first.len(), 25);
+        assert!(first.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(first, sample(&plan, 1_000));
+        assert_eq!(sample(&plan, 10), (0..10).collect::<Vec<_>>());
+
+        let other = SamplePlan::from_args(Some(25), None, Some(8))
+            .expect("plan")
+            .expect("sampling enabled");
+        assert_ne!(first, sample(&other, 1_000));
+    }
+
+    #[test]
+    fn fraction_keeps_roughly_the_requested_share() {
+        let plan = SamplePlan::from_args(None, Some(0.1), Some(42))
+            .expect("plan")
+            .expect("sampling enabled");
+        assert!(plan.is_streaming());
+        let kept = sample(&plan, 10_000);
+        assert!((800..1_200).contains(&kept.len()), "kept {}", kept.len());
+        assert_eq!(kept, sample(&plan, 10_000));
+        assert!(SamplePlan::from_args(None, Some(1.5), None).is_err());
+        assert!(SamplePlan::from_args(None, None, Some(1)).is_err());
+    }
+}
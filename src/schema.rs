@@ -20,7 +20,7 @@ use std::{
     fmt,
     fs::File,
     io::BufReader,
-    path::Path,
+    path::{Path, PathBuf},
     str::FromStr,
 };
 
@@ -34,15 +34,22 @@ use serde_yaml::Value;
 use uuid::Uuid;
 
 use crate::{
+    cli::{DialectArgs, InputFormatArgs},
     data::{
         CurrencyValue, FixedDecimalValue, Value as DataValue, parse_currency_decimal,
         parse_decimal_literal, parse_naive_date, parse_naive_datetime, parse_naive_time,
         parse_typed_value,
     },
-    io_utils,
+    dialect::{self, Dialect},
+    excel_input, fixed_width,
+    io_utils::{self, ByteRecords},
+    json_input,
+    malformed::MalformedRows,
+    parquet_input,
+    sample::{SamplePlan, Sampler},
 };
 
-const DECIMAL_MAX_PRECISION: u32 = 28;
+pub(crate) const DECIMAL_MAX_PRECISION: u32 = 28;
 const HEADER_ALIAS_THRESHOLD_PERCENT: usize = 80;
 const HEADER_ALIAS_MIN_MATCHES: usize = 4;
 const HEADER_DETECTION_SAMPLE_ROWS: usize = 6;
@@ -486,6 +493,33 @@ pub struct ColumnMeta {
         skip_serializing_if = "Vec::is_empty"
     )]
     pub datatype_mappings: Vec<DatatypeMapping>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lookup: Option<LookupSpec>,
+    /// 1-based byte position of the column in a fixed-width file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start: Option<usize>,
+    /// Byte width of the column in a fixed-width file.
+    #[serde(default, alias = "length", skip_serializing_if = "Option::is_none")]
+    pub width: Option<usize>,
+}
+
+/// Reference table used by `process` to enrich rows from a column's value.
+///
+/// `table` (and `schema`, when given) are resolved relative to the directory of
+/// the schema file that declares the lookup.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LookupSpec {
+    pub table: PathBuf,
+    pub key: String,
+    pub columns: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub defaults: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -495,6 +529,8 @@ pub struct Schema {
     pub schema_version: Option<String>,
     #[serde(default = "Schema::default_has_headers")]
     pub has_headers: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dialect: Option<Dialect>,
 }
 
 #[derive(Debug, Clone)]
@@ -552,12 +588,16 @@ impl Schema {
                 rename: None,
                 value_replacements: Vec::new(),
                 datatype_mappings: Vec::new(),
+                lookup: None,
+                start: None,
+                width: None,
             })
             .collect();
         Schema {
             columns,
             schema_version: None,
             has_headers: true,
+            dialect: None,
         }
     }
 
@@ -565,6 +605,14 @@ impl Schema {
         true
     }
 
+    /// Layers command-line dialect flags over the schema's `dialect:` block.
+    pub fn apply_dialect_args(&mut self, args: &DialectArgs) -> Result<()> {
+        let dialect = args.apply(self.dialect.take().unwrap_or_default());
+        dialect.validate()?;
+        self.dialect = Some(dialect);
+        Ok(())
+    }
+
     pub fn expects_headers(&self) -> bool {
         self.has_headers
     }
@@ -650,6 +698,9 @@ impl Schema {
         let reader = BufReader::new(file);
         let schema: Schema = serde_yaml::from_reader(reader).context("Parsing schema YAML")?;
         schema.validate_datatype_mappings()?;
+        if let Some(dialect) = &schema.dialect {
+            dialect.validate()?;
+        }
         Ok(schema)
     }
 
@@ -1812,7 +1863,7 @@ pub(crate) fn row_looks_like_header(row: &[String], header_aliases: &[HashSet<St
     )
 }
 
-fn generate_field_names(count: usize) -> Vec<String> {
+pub(crate) fn generate_field_names(count: usize) -> Vec<String> {
     (0..count).map(|idx| format!("field_{idx}")).collect()
 }
 
@@ -1895,7 +1946,7 @@ fn header_tokens_match_dictionary(row: &[String]) -> bool {
         >= 2
 }
 
-fn infer_has_header(first_row: &[String], other_rows: &[Vec<String>]) -> bool {
+pub(crate) fn infer_has_header(first_row: &[String], other_rows: &[Vec<String>]) -> bool {
     let header_like_first = first_row
         .iter()
         .filter(|value| value_is_header_like(value))
@@ -1956,11 +2007,16 @@ fn infer_has_header(first_row: &[String], other_rows: &[Vec<String>]) -> bool {
     header_like_first > data_like_first
 }
 
+/// Detects whether `path` starts with a header row and names its columns.
+/// Ragged rows are an error unless `flexible` is set for a command reading
+/// under an `--on-malformed` policy.
 pub fn detect_csv_layout(
     path: &Path,
     delimiter: u8,
     encoding: &'static Encoding,
     header_override: Option<bool>,
+    dialect: &Dialect,
+    flexible: bool,
 ) -> Result<CsvLayout> {
     if io_utils::is_dash(path) {
         return Ok(CsvLayout {
@@ -1970,7 +2026,10 @@ pub fn detect_csv_layout(
     }
 
     if let Some(force_header) = header_override {
-        let mut reader = io_utils::open_csv_reader_from_path(path, delimiter, force_header)?;
+        let mut reader = dialect
+            .reader_builder(delimiter, force_header)
+            .flexible(flexible)
+            .from_reader(io_utils::open_input_reader(path)?);
         if force_header {
             let header_record = reader.byte_headers()?.clone();
             let headers = io_utils::decode_headers(&header_record, encoding)?;
@@ -1993,7 +2052,11 @@ pub fn detect_csv_layout(
         }
     }
 
-    let mut reader = io_utils::open_csv_reader_from_path(path, delimiter, false)?;
+    // Under an `--on-malformed` policy ragged rows are left to the command's read.
+    let mut reader = dialect
+        .reader_builder(delimiter, false)
+        .flexible(flexible)
+        .from_reader(io_utils::open_input_reader(path)?);
     let mut record = csv::ByteRecord::new();
     let mut decoded_rows = Vec::new();
 
@@ -2052,13 +2115,211 @@ pub fn infer_schema_with_stats(
     _placeholder_policy: &PlaceholderPolicy,
     header_override: Option<bool>,
 ) -> Result<(Schema, InferenceStats)> {
-    let layout = detect_csv_layout(path, delimiter, encoding, header_override)?;
-    let mut reader = io_utils::open_csv_reader_from_path(path, delimiter, layout.has_headers)?;
-    let headers = if layout.has_headers {
-        let header_record = reader.byte_headers()?.clone();
-        io_utils::decode_headers(&header_record, encoding)?
+    infer_schema_with_input_format(
+        path,
+        sample_rows,
+        delimiter,
+        encoding,
+        header_override,
+        &InputFormatArgs::default(),
+        &DialectArgs::default(),
+    )
+}
+
+/// Like [`infer_schema_with_stats`], reading JSON and Excel inputs per
+/// `input_format`. CSV inputs have their dialect detected, overridden by
+/// `dialect_args`, and recorded in the schema when it is not the default.
+pub fn infer_schema_with_input_format(
+    path: &Path,
+    sample_rows: usize,
+    delimiter: u8,
+    encoding: &'static Encoding,
+    header_override: Option<bool>,
+    input_format: &InputFormatArgs,
+    dialect_args: &DialectArgs,
+) -> Result<(Schema, InferenceStats)> {
+    infer_schema_from(
+        path,
+        delimiter,
+        encoding,
+        &InferenceInput {
+            sample_rows,
+            header_override,
+            input_format,
+            dialect_args,
+            malformed: None,
+            seed: None,
+        },
+    )
+}
+
+/// How [`infer_schema_from`] reads its input.
+pub(crate) struct InferenceInput<'a> {
+    /// Rows to sample; `0` reads every row.
+    pub(crate) sample_rows: usize,
+    pub(crate) header_override: Option<bool>,
+    pub(crate) input_format: &'a InputFormatArgs,
+    pub(crate) dialect_args: &'a DialectArgs,
+    /// The reading command's `--on-malformed` policy; `None` keeps ragged rows
+    /// an error.
+    pub(crate) malformed: Option<&'a MalformedRows>,
+    /// Draws the `sample_rows` rows at random from the whole input with this seed
+    /// instead of reading the first rows.
+    pub(crate) seed: Option<u64>,
+}
+
+/// [`infer_schema_with_input_format`] with every reading option, including the
+/// malformed-row policy of the command that will read the rows.
+pub(crate) fn infer_schema_from(
+    path: &Path,
+    delimiter: u8,
+    encoding: &'static Encoding,
+    input: &InferenceInput,
+) -> Result<(Schema, InferenceStats)> {
+    let InferenceInput {
+        sample_rows,
+        header_override,
+        input_format,
+        dialect_args,
+        malformed,
+        seed,
+    } = *input;
+    // Sampling must not count or quarantine the rows the command reads again.
+    let malformed = malformed.map(MalformedRows::for_inference);
+    // Parquet columns are already typed; their rows are still read for samples.
+    let declared = if parquet_input::is_parquet(path) {
+        Some(parquet_input::read_schema(path)?)
     } else {
-        layout.headers.clone()
+        None
+    };
+    let is_csv = declared.is_none() && !json_input::is_json(path) && !excel_input::is_excel(path);
+    let dialect = if is_csv {
+        dialect_args.apply(dialect::detect(path, delimiter)?)
+    } else {
+        Dialect::default()
+    };
+    dialect.validate()?;
+    let layout = match &declared {
+        Some(schema) => CsvLayout {
+            headers: schema.headers(),
+            has_headers: true,
+        },
+        // JSON column names come from the objects' keys.
+        None if json_input::is_json(path) => CsvLayout {
+            headers: Vec::new(),
+            has_headers: true,
+        },
+        // Worksheets are assumed to start with a header row unless told otherwise.
+        None if excel_input::is_excel(path) => match header_override {
+            Some(false) => CsvLayout {
+                headers: generate_field_names(excel_input::width(path, input_format)?),
+                has_headers: false,
+            },
+            _ => CsvLayout {
+                headers: Vec::new(),
+                has_headers: true,
+            },
+        },
+        None => detect_csv_layout(
+            path,
+            delimiter,
+            encoding,
+            header_override,
+            &dialect,
+            malformed.is_some(),
+        )?,
+    };
+    let (read_headers, records) = io_utils::open_records(
+        path,
+        delimiter,
+        encoding,
+        layout.has_headers,
+        &dialect,
+        input_format,
+        malformed.as_ref(),
+    )?;
+    let headers = read_headers.unwrap_or_else(|| layout.headers.clone());
+    let (datatypes, stats) = infer_record_types(&headers, records, sample_rows, seed, encoding)?;
+
+    let columns = headers
+        .iter()
+        .zip(datatypes)
+        .enumerate()
+        .map(|(idx, (header, datatype))| ColumnMeta {
+            name: header.clone(),
+            datatype: match &declared {
+                Some(schema) => schema.columns[idx].datatype.clone(),
+                None => datatype,
+            },
+            rename: None,
+            value_replacements: Vec::new(),
+            datatype_mappings: Vec::new(),
+            lookup: None,
+            start: None,
+            width: None,
+        })
+        .collect();
+
+    let schema = Schema {
+        columns,
+        schema_version: None,
+        has_headers: layout.has_headers,
+        dialect: (!dialect.is_default()).then_some(dialect),
+    };
+    Ok((schema, stats))
+}
+
+/// Infers a schema for a fixed-width file, suggesting column boundaries from
+/// the byte positions that are blank on every sampled line. Types are voted over
+/// the same lines, so stdin is read once; with `seed` they are voted over lines
+/// drawn at random from a second pass over the whole file.
+pub fn infer_fixed_width_schema_with_stats(
+    path: &Path,
+    sample_rows: usize,
+    seed: Option<u64>,
+    encoding: &'static Encoding,
+    header_override: Option<bool>,
+) -> Result<(Schema, InferenceStats)> {
+    let (mut schema, sampled) =
+        fixed_width::suggest_schema(path, sample_rows, encoding, header_override)?;
+    let records: ByteRecords = match seed {
+        Some(_) if sample_rows > 0 => {
+            ensure!(
+                !io_utils::is_dash(path),
+                "--seed cannot be combined with --fixed-width on stdin, which can only be read once"
+            );
+            io_utils::open_schema_records(
+                path,
+                &schema,
+                io_utils::DEFAULT_CSV_DELIMITER,
+                encoding,
+                &InputFormatArgs::default(),
+                None,
+            )?
+            .1
+        }
+        _ => Box::new(sampled.into_iter().map(Ok)),
+    };
+    let (datatypes, stats) =
+        infer_record_types(&schema.headers(), records, sample_rows, seed, encoding)?;
+    for (column, datatype) in schema.columns.iter_mut().zip(datatypes) {
+        column.datatype = datatype;
+    }
+    Ok((schema, stats))
+}
+
+/// Votes a datatype for each column over up to `sample_rows` records (all when 0),
+/// drawn at random from the whole input when `seed` is set.
+fn infer_record_types(
+    headers: &[String],
+    records: ByteRecords,
+    sample_rows: usize,
+    seed: Option<u64>,
+    encoding: &'static Encoding,
+) -> Result<(Vec<ColumnType>, InferenceStats)> {
+    let records = match seed {
+        Some(seed) if sample_rows > 0 => sample_records(records, sample_rows, seed)?,
+        _ => records,
     };
     let mut candidates = vec![TypeCandidate::new(); headers.len()];
     let mut samples = vec![None; headers.len()];
@@ -2069,13 +2330,13 @@ pub fn infer_schema_with_stats(
         .map(|header| build_header_aliases(header))
         .collect();
 
-    let mut record = csv::ByteRecord::new();
     let mut processed = 0usize;
     let mut decode_errors = 0usize;
-    while reader.read_byte_record(&mut record)? {
+    for record in records {
         if sample_rows > 0 && processed >= sample_rows {
             break;
         }
+        let record = record?;
         let mut decoded_row: Vec<Option<String>> = Vec::with_capacity(headers.len());
 
         for field in record.iter().take(headers.len()) {
@@ -2127,23 +2388,6 @@ pub fn infer_schema_with_stats(
         processed += 1;
     }
 
-    let columns = headers
-        .iter()
-        .enumerate()
-        .map(|(idx, header)| ColumnMeta {
-            name: header.clone(),
-            datatype: candidates[idx].decide(),
-            rename: None,
-            value_replacements: Vec::new(),
-            datatype_mappings: Vec::new(),
-        })
-        .collect();
-
-    let schema = Schema {
-        columns,
-        schema_version: None,
-        has_headers: layout.has_headers,
-    };
     let stats = InferenceStats {
         sample_values: samples,
         rows_read: processed,
@@ -2155,8 +2399,17 @@ pub fn infer_schema_with_stats(
             .collect(),
         placeholder_summaries: placeholders,
     };
+    let datatypes = candidates.iter().map(TypeCandidate::decide).collect();
+    Ok((datatypes, stats))
+}
 
-    Ok((schema, stats))
+/// Reads every record and keeps a reservoir sample of `rows` of them, in input order.
+fn sample_records(records: ByteRecords, rows: usize, seed: u64) -> Result<ByteRecords> {
+    let mut sampler = Sampler::new(&SamplePlan::reservoir(rows, seed));
+    for record in records {
+        sampler.offer(record?);
+    }
+    Ok(Box::new(sampler.finish().into_iter().map(Ok)))
 }
 
 pub(crate) fn format_hint_for(datatype: &ColumnType, sample: Option<&str>) -> Option<String> {
@@ -2500,11 +2753,15 @@ mod tests {
             rename: None,
             value_replacements: Vec::new(),
             datatype_mappings: mappings,
+            lookup: None,
+            start: None,
+            width: None,
         };
         let schema = Schema {
             columns: vec![column],
             schema_version: None,
             has_headers: true,
+            dialect: None,
         };
 
         let mut row = vec!["2024-05-10T13:45:00".to_string()];
@@ -2530,11 +2787,15 @@ mod tests {
             rename: None,
             value_replacements: Vec::new(),
             datatype_mappings: vec![mapping],
+            lookup: None,
+            start: None,
+            width: None,
         };
         let schema = Schema {
             columns: vec![column],
             schema_version: None,
             has_headers: true,
+            dialect: None,
         };
         let mut row = vec!["3.1415926535".to_string()];
         schema
@@ -2559,11 +2820,15 @@ mod tests {
             rename: None,
             value_replacements: Vec::new(),
             datatype_mappings: vec![mapping],
+            lookup: None,
+            start: None,
+            width: None,
         };
         let schema = Schema {
             columns: vec![column],
             schema_version: None,
             has_headers: true,
+            dialect: None,
         };
         let mut row = vec!["12.345".to_string()];
         schema
@@ -2586,11 +2851,15 @@ mod tests {
             rename: None,
             value_replacements: Vec::new(),
             datatype_mappings: vec![mapping],
+            lookup: None,
+            start: None,
+            width: None,
         };
         let schema = Schema {
             columns: vec![column],
             schema_version: None,
             has_headers: true,
+            dialect: None,
         };
         let mut row = vec!["123.4567".to_string()];
         schema
@@ -2620,11 +2889,15 @@ mod tests {
             rename: None,
             value_replacements: Vec::new(),
             datatype_mappings: vec![currency_mapping, decimal_mapping],
+            lookup: None,
+            start: None,
+            width: None,
         };
         let schema = Schema {
             columns: vec![column],
             schema_version: None,
             has_headers: true,
+            dialect: None,
         };
         let mut row = vec!["$123.4567".to_string()];
         schema
@@ -2968,11 +3241,15 @@ columns:
             rename: None,
             value_replacements: Vec::new(),
             datatype_mappings: vec![mapping],
+            lookup: None,
+            start: None,
+            width: None,
         };
         let schema = Schema {
             columns: vec![column],
             schema_version: None,
             has_headers: true,
+            dialect: None,
         };
         let mut row = vec!["123.455".to_string()];
         schema
@@ -2996,11 +3273,15 @@ columns:
             rename: None,
             value_replacements: Vec::new(),
             datatype_mappings: vec![mapping],
+            lookup: None,
+            start: None,
+            width: None,
         };
         let schema = Schema {
             columns: vec![column],
             schema_version: None,
             has_headers: true,
+            dialect: None,
         };
         let mut row = vec!["-87.6549".to_string()];
         schema
@@ -3130,11 +3411,15 @@ columns:
             rename: None,
             value_replacements: Vec::new(),
             datatype_mappings: vec![mapping],
+            lookup: None,
+            start: None,
+            width: None,
         };
         let schema = Schema {
             columns: vec![column],
             schema_version: None,
             has_headers: true,
+            dialect: None,
         };
         let mut row = vec!["12.34".to_string()];
         let err = schema
@@ -3163,11 +3448,15 @@ columns:
             rename: None,
             value_replacements: Vec::new(),
             datatype_mappings: vec![mapping],
+            lookup: None,
+            start: None,
+            width: None,
         };
         let schema = Schema {
             columns: vec![column],
             schema_version: None,
             has_headers: true,
+            dialect: None,
         };
         let mut row = vec!["1234567.89".to_string()];
         let err = schema
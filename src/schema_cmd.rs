@@ -17,6 +17,7 @@ use std::path::Path;
 use std::str::FromStr;
 
 use anyhow::{Context, Result, anyhow};
+use encoding_rs::Encoding;
 use log::info;
 use sha2::{Digest, Sha256};
 use similar::TextDiff;
@@ -61,6 +62,7 @@ fn execute_manual(args: &SchemaArgs) -> Result<()> {
         columns,
         schema_version: None,
         has_headers: true,
+        dialect: None,
     };
     schema
         .save(output)
@@ -90,6 +92,38 @@ fn resolve_placeholder_policy(args: &SchemaProbeArgs) -> schema::PlaceholderPoli
     }
 }
 
+fn infer_probe_schema(
+    args: &SchemaProbeArgs,
+    delimiter: u8,
+    encoding: &'static Encoding,
+) -> Result<(Schema, InferenceStats)> {
+    if args.seed.is_some() && args.sample_rows == 0 {
+        return Err(anyhow!("--seed requires --sample-rows greater than 0"));
+    }
+    if args.fixed_width {
+        return schema::infer_fixed_width_schema_with_stats(
+            &args.input,
+            args.sample_rows,
+            args.seed,
+            encoding,
+            args.assume_header,
+        );
+    }
+    schema::infer_schema_from(
+        &args.input,
+        delimiter,
+        encoding,
+        &schema::InferenceInput {
+            sample_rows: args.sample_rows,
+            header_override: args.assume_header,
+            input_format: &args.input_format,
+            dialect_args: &args.dialect,
+            malformed: None,
+            seed: args.seed,
+        },
+    )
+}
+
 fn execute_probe(args: &SchemaProbeArgs) -> Result<()> {
     let input = &args.input;
     let delimiter = io_utils::resolve_input_delimiter(input, args.delimiter);
@@ -101,15 +135,8 @@ fn execute_probe(args: &SchemaProbeArgs) -> Result<()> {
         printable_delimiter(delimiter)
     );
 
-    let (mut schema, stats) = schema::infer_schema_with_stats(
-        input,
-        args.sample_rows,
-        delimiter,
-        encoding,
-        &placeholder_policy,
-        args.assume_header,
-    )
-    .with_context(|| format!("Inferring schema from {input:?}"))?;
+    let (mut schema, stats) = infer_probe_schema(args, delimiter, encoding)
+        .with_context(|| format!("Inferring schema from {input:?}"))?;
 
     let overrides = apply_overrides(&mut schema, &args.overrides)?;
 
@@ -145,15 +172,8 @@ fn execute_infer(args: &SchemaInferArgs) -> Result<()> {
         printable_delimiter(delimiter)
     );
 
-    let (mut schema, stats) = schema::infer_schema_with_stats(
-        input_path,
-        probe.sample_rows,
-        delimiter,
-        encoding,
-        &placeholder_policy,
-        probe.assume_header,
-    )
-    .with_context(|| format!("Inferring schema from {input_path:?}"))?;
+    let (mut schema, stats) = infer_probe_schema(probe, delimiter, encoding)
+        .with_context(|| format!("Inferring schema from {input_path:?}"))?;
 
     let overrides = apply_overrides(&mut schema, &probe.overrides)?;
 
@@ -381,6 +401,9 @@ fn parse_columns(specs: &[String]) -> Result<Vec<ColumnMeta>> {
                 rename,
                 value_replacements: Vec::new(),
                 datatype_mappings: Vec::new(),
+                lookup: None,
+                start: None,
+                width: None,
             });
         }
     }
@@ -943,9 +966,13 @@ mod tests {
                 rename: None,
                 value_replacements: Vec::new(),
                 datatype_mappings: Vec::new(),
+                lookup: None,
+                start: None,
+                width: None,
             }],
             schema_version: None,
             has_headers: true,
+            dialect: None,
         };
         let overrides = vec!["amount:integer".to_string(), "".to_string()];
         let applied = apply_overrides(&mut schema, &overrides).unwrap();
@@ -963,6 +990,9 @@ mod tests {
                     rename: None,
                     value_replacements: Vec::new(),
                     datatype_mappings: Vec::new(),
+                    lookup: None,
+                    start: None,
+                    width: None,
                 },
                 ColumnMeta {
                     name: "CustomerName".to_string(),
@@ -970,10 +1000,14 @@ mod tests {
                     rename: Some("customer_name".to_string()),
                     value_replacements: Vec::new(),
                     datatype_mappings: Vec::new(),
+                    lookup: None,
+                    start: None,
+                    width: None,
                 },
             ],
             schema_version: None,
             has_headers: true,
+            dialect: None,
         };
 
         let suggested = apply_default_name_mappings(&mut schema);
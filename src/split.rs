@@ -0,0 +1,499 @@
+//! Partitioned and size-limited CSV output for `process` and `append`.
+//!
+//! When `--split-by`, `--max-rows-per-file`, or `--max-bytes-per-file` is set,
+//! `--output` is treated as a file name pattern rather than a single path:
+//!
+//! - `{column}` is replaced with the row's value in a `--split-by` column
+//!   (characters that are unsafe in file names are replaced with `_`).
+//! - `{n}` is replaced with the 1-based part number, and `{n:04}` pads it to
+//!   the given width. Part numbers count separately for each partition.
+//!
+//! For example `out/{region}/part-{n:04}.csv` writes
+//! `out/west/part-0001.csv`, `out/west/part-0002.csv`, `out/east/part-0001.csv`, ...
+//! Every file is opened through [`io_utils::open_csv_writer`] and starts with the
+//! header row, so each part is a complete CSV in the configured delimiter,
+//! encoding, and dialect. Parent directories are created as needed.
+//!
+//! Split columns are looked up among the output headers, so they must be part of
+//! the emitted columns. Two partitions whose values render to the same file name
+//! (`a/b` and `a_b`) stop the run rather than overwrite each other.
+//!
+//! At most 128 split files are open at a time. When a new partition needs a file
+//! beyond that, the least recently written one is closed and later reopened in
+//! append mode if its partition receives more rows, so high-cardinality
+//! `--split-by` columns stay within the process's file-descriptor limit.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use encoding_rs::{Encoding, UTF_8};
+use log::info;
+
+use crate::{
+    cli::SplitArgs,
+    dialect::Dialect,
+    io_utils::{self, FinishWrite},
+};
+
+/// Split files kept open at once.
+const MAX_OPEN_FILES: usize = 128;
+
+/// Destination for CSV records: a single writer or a set of split files.
+pub(crate) enum CsvOutput {
+    Single(csv::Writer<Box<dyn FinishWrite>>),
+    Split(SplitWriter),
+}
+
+impl CsvOutput {
+    /// Opens `path` (stdout when `None` or `-`) unless `split` requests partitioned output.
+    pub(crate) fn open(
+        path: Option<&Path>,
+        delimiter: u8,
+        encoding: &'static Encoding,
+        dialect: &Dialect,
+        split: &SplitArgs,
+    ) -> Result<Self> {
+        if !split.is_enabled() {
+            return Ok(CsvOutput::Single(io_utils::open_csv_writer(
+                path, delimiter, encoding, dialect,
+            )?));
+        }
+        let pattern = path
+            .filter(|path| !io_utils::is_dash(path))
+            .ok_or_else(|| {
+                anyhow!("Splitting output requires --output with a file name pattern")
+            })?;
+        Ok(CsvOutput::Split(SplitWriter::new(
+            split, pattern, delimiter, encoding, dialect,
+        )?))
+    }
+
+    /// Declares the output columns. With `write` set the header row is written
+    /// (once for a single file, at the top of every split file).
+    pub(crate) fn start(&mut self, headers: &[String], write: bool) -> Result<()> {
+        match self {
+            CsvOutput::Single(writer) => {
+                if write {
+                    writer
+                        .write_record(headers.iter())
+                        .context("Writing output headers")?;
+                }
+                Ok(())
+            }
+            CsvOutput::Split(split) => split.start(headers, write),
+        }
+    }
+
+    pub(crate) fn write_record(&mut self, record: &[String]) -> Result<()> {
+        match self {
+            CsvOutput::Single(writer) => writer
+                .write_record(record.iter())
+                .context("Writing output row"),
+            CsvOutput::Split(split) => split.write_record(record),
+        }
+    }
+
+    pub(crate) fn finish(self) -> Result<()> {
+        match self {
+            CsvOutput::Single(writer) => io_utils::finish_csv_writer(writer),
+            CsvOutput::Split(split) => split.finish(),
+        }
+    }
+}
+
+/// Routes records to pattern-named files, rolling over on row or byte limits.
+pub(crate) struct SplitWriter {
+    pattern: Pattern,
+    split_by: Vec<String>,
+    split_columns: Vec<usize>,
+    max_rows: Option<usize>,
+    max_bytes: Option<usize>,
+    delimiter: u8,
+    encoding: &'static Encoding,
+    dialect: Dialect,
+    headers: Option<Vec<String>>,
+    parts: HashMap<Vec<String>, Part>,
+    /// Partition key that owns each rendered file path.
+    owners: HashMap<PathBuf, Vec<String>>,
+    /// Keys of the parts with an open writer, by when they were last written.
+    open: BTreeMap<u64, Vec<String>>,
+    writes: u64,
+    files_written: usize,
+}
+
+struct Part {
+    /// `None` once closed to make room for other partitions.
+    writer: Option<csv::Writer<Box<dyn FinishWrite>>>,
+    path: PathBuf,
+    number: usize,
+    rows: usize,
+    bytes: usize,
+    last_write: u64,
+}
+
+impl SplitWriter {
+    fn new(
+        args: &SplitArgs,
+        pattern: &Path,
+        delimiter: u8,
+        encoding: &'static Encoding,
+        dialect: &Dialect,
+    ) -> Result<Self> {
+        let split_by = args
+            .split_by
+            .iter()
+            .flat_map(|s| s.split(','))
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        let pattern = Pattern::parse(&pattern.to_string_lossy())?;
+        for column in &split_by {
+            if !pattern.references(column) {
+                bail!("Output pattern must include {{{column}}} when splitting by '{column}'");
+            }
+        }
+        if (args.max_rows_per_file.is_some() || args.max_bytes_per_file.is_some())
+            && !pattern.has_part_number()
+        {
+            bail!(
+                "Output pattern must include {{n}} when --max-rows-per-file or --max-bytes-per-file is set"
+            );
+        }
+        if args.max_rows_per_file == Some(0) {
+            bail!("--max-rows-per-file must be greater than zero");
+        }
+        Ok(Self {
+            pattern,
+            split_by,
+            split_columns: Vec::new(),
+            max_rows: args.max_rows_per_file,
+            max_bytes: args.max_bytes_per_file,
+            delimiter,
+            encoding,
+            dialect: dialect.clone(),
+            headers: None,
+            parts: HashMap::new(),
+            owners: HashMap::new(),
+            open: BTreeMap::new(),
+            writes: 0,
+            files_written: 0,
+        })
+    }
+
+    fn start(&mut self, headers: &[String], write: bool) -> Result<()> {
+        self.split_columns = self
+            .split_by
+            .iter()
+            .map(|column| {
+                headers
+                    .iter()
+                    .position(|header| header == column)
+                    .ok_or_else(|| anyhow!("Split column '{column}' is not part of the output"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if let Some(column) = self
+            .pattern
+            .columns()
+            .find(|column| !self.split_by.iter().any(|split| split == column))
+        {
+            bail!(
+                "Output pattern references {{{column}}} but '{column}' is not a --split-by column"
+            );
+        }
+        self.headers = write.then(|| headers.to_vec());
+        Ok(())
+    }
+
+    fn write_record(&mut self, record: &[String]) -> Result<()> {
+        let key = self
+            .split_columns
+            .iter()
+            .map(|idx| record.get(*idx).cloned().unwrap_or_default())
+            .collect::<Vec<_>>();
+        let size = match self.max_bytes {
+            Some(_) => Some(record_bytes(
+                &self.dialect,
+                self.delimiter,
+                record,
+                self.encoding,
+            )?),
+            None => None,
+        };
+        let rollover = self.parts.get(&key).is_some_and(|part| {
+            part.rows > 0
+                && (self.max_rows.is_some_and(|max| part.rows >= max)
+                    || self
+                        .max_bytes
+                        .zip(size)
+                        .is_some_and(|(max, size)| part.bytes + size > max))
+        });
+        let number = match self.parts.get(&key) {
+            Some(part) if rollover => Some(part.number + 1),
+            Some(_) => None,
+            None => Some(1),
+        };
+        if let Some(number) = number {
+            if let Some(previous) = self.parts.remove(&key) {
+                self.close(previous)?;
+            }
+            self.make_room()?;
+            let part = self.open_part(&key, number)?;
+            self.parts.insert(key.clone(), part);
+        } else if self
+            .parts
+            .get(&key)
+            .is_some_and(|part| part.writer.is_none())
+        {
+            self.make_room()?;
+            let part = self.parts.get_mut(&key).expect("split part exists");
+            part.writer = Some(io_utils::reopen_csv_writer(
+                &part.path,
+                self.delimiter,
+                self.encoding,
+                &self.dialect,
+            )?);
+        }
+        self.writes += 1;
+        let part = self.parts.get_mut(&key).expect("split part opened above");
+        self.open.remove(&part.last_write);
+        part.last_write = self.writes;
+        self.open.insert(self.writes, key);
+        part.writer
+            .as_mut()
+            .expect("split part opened above")
+            .write_record(record.iter())
+            .context("Writing output row")?;
+        part.rows += 1;
+        part.bytes += size.unwrap_or(0);
+        Ok(())
+    }
+
+    /// Closes the least recently written files until another can be opened.
+    fn make_room(&mut self) -> Result<()> {
+        while self.open.len() >= MAX_OPEN_FILES {
+            let (_, key) = self.open.pop_first().expect("open split files");
+            let part = self.parts.get_mut(&key).expect("open split part");
+            if let Some(writer) = part.writer.take() {
+                io_utils::finish_csv_writer(writer)
+                    .with_context(|| format!("Closing split output file {:?}", part.path))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn close(&mut self, part: Part) -> Result<()> {
+        if let Some(writer) = part.writer {
+            self.open.remove(&part.last_write);
+            io_utils::finish_csv_writer(writer).context("Finishing split output file")?;
+        }
+        Ok(())
+    }
+
+    fn open_part(&mut self, key: &[String], number: usize) -> Result<Part> {
+        let values = self
+            .split_by
+            .iter()
+            .zip(key)
+            .map(|(column, value)| (column.as_str(), sanitize(value)))
+            .collect::<HashMap<_, _>>();
+        let path = PathBuf::from(self.pattern.render(&values, number));
+        match self.owners.get(&path) {
+            Some(owner) if owner.as_slice() != key => bail!(
+                "Split values {owner:?} and {key:?} both map to output file {path:?}; \
+                 rename one of them or change the output pattern"
+            ),
+            Some(_) => {}
+            None => {
+                self.owners.insert(path.clone(), key.to_vec());
+            }
+        }
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Creating output directory {parent:?}"))?;
+        }
+        let mut writer =
+            io_utils::open_csv_writer(Some(&path), self.delimiter, self.encoding, &self.dialect)?;
+        let mut bytes = 0;
+        if let Some(headers) = &self.headers {
+            writer
+                .write_record(headers.iter())
+                .context("Writing output headers")?;
+            bytes = record_bytes(&self.dialect, self.delimiter, headers, self.encoding)?;
+            if self.dialect.bom && self.encoding == UTF_8 {
+                bytes += '\u{FEFF}'.len_utf8();
+            }
+        }
+        self.files_written += 1;
+        Ok(Part {
+            writer: Some(writer),
+            path,
+            number,
+            rows: 0,
+            bytes,
+            last_write: 0,
+        })
+    }
+
+    fn finish(self) -> Result<()> {
+        for writer in self.parts.into_values().filter_map(|part| part.writer) {
+            io_utils::finish_csv_writer(writer).context("Finishing split output file")?;
+        }
+        info!("Wrote {} split output file(s)", self.files_written);
+        Ok(())
+    }
+}
+
+/// Bytes the record occupies once written in `dialect` and transcoded to `encoding`.
+fn record_bytes(
+    dialect: &Dialect,
+    delimiter: u8,
+    record: &[String],
+    encoding: &'static Encoding,
+) -> Result<usize> {
+    let mut writer = dialect.writer_builder(delimiter).from_writer(Vec::new());
+    writer
+        .write_record(record.iter())
+        .context("Measuring output row")?;
+    let written = writer
+        .into_inner()
+        .map_err(|err| anyhow!("Measuring output row: {}", err.error()))?;
+    Ok(if encoding == UTF_8 {
+        written.len()
+    } else {
+        encoding.encode(&String::from_utf8_lossy(&written)).0.len()
+    })
+}
+
+/// Replaces characters that would change the directory layout or are invalid in file names.
+fn sanitize(value: &str) -> String {
+    let cleaned = value
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>();
+    if cleaned.trim_matches('.').is_empty() {
+        "_".repeat(cleaned.len().max(1))
+    } else {
+        cleaned
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Segment {
+    Literal(String),
+    Column(String),
+    PartNumber(usize),
+}
+
+#[derive(Debug)]
+struct Pattern {
+    segments: Vec<Segment>,
+}
+
+impl Pattern {
+    fn parse(pattern: &str) -> Result<Self> {
+        let mut segments = Vec::new();
+        let mut rest = pattern;
+        while let Some(open) = rest.find('{') {
+            if open > 0 {
+                segments.push(Segment::Literal(rest[..open].to_string()));
+            }
+            let close = rest[open..]
+                .find('}')
+                .map(|close| open + close)
+                .ok_or_else(|| anyhow!("Unclosed '{{' in output pattern '{pattern}'"))?;
+            let placeholder = rest[open + 1..close].trim();
+            segments.push(match placeholder.split_once(':') {
+                Some(("n", width)) => Segment::PartNumber(width.parse().map_err(|_| {
+                    anyhow!("Invalid part number width '{width}' in output pattern '{pattern}'")
+                })?),
+                _ if placeholder == "n" => Segment::PartNumber(0),
+                _ if placeholder.is_empty() => {
+                    bail!("Empty placeholder in output pattern '{pattern}'")
+                }
+                _ => Segment::Column(placeholder.to_string()),
+            });
+            rest = &rest[close + 1..];
+        }
+        if !rest.is_empty() {
+            segments.push(Segment::Literal(rest.to_string()));
+        }
+        Ok(Self { segments })
+    }
+
+    fn references(&self, column: &str) -> bool {
+        self.columns().any(|name| name == column)
+    }
+
+    fn columns(&self) -> impl Iterator<Item = &str> {
+        self.segments.iter().filter_map(|segment| match segment {
+            Segment::Column(name) => Some(name.as_str()),
+            _ => None,
+        })
+    }
+
+    fn has_part_number(&self) -> bool {
+        self.segments
+            .iter()
+            .any(|segment| matches!(segment, Segment::PartNumber(_)))
+    }
+
+    fn render(&self, values: &HashMap<&str, String>, number: usize) -> String {
+        self.segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Literal(text) => text.clone(),
+                Segment::Column(name) => values.get(name.as_str()).cloned().unwrap_or_default(),
+                Segment::PartNumber(width) => format!("{number:0width$}"),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pattern_renders_columns_and_padded_part_numbers() {
+        let pattern = Pattern::parse("out/{region}/part-{n:04}.csv").expect("parse pattern");
+        assert!(pattern.references("region"));
+        assert!(pattern.has_part_number());
+        let values = HashMap::from([("region", sanitize("north/east"))]);
+        assert_eq!(pattern.render(&values, 12), "out/north_east/part-0012.csv");
+        assert_eq!(
+            Pattern::parse("rows-{n}.csv")
+                .expect("parse pattern")
+                .render(&HashMap::new(), 3),
+            "rows-3.csv"
+        );
+        assert!(Pattern::parse("out/{region.csv").is_err());
+        assert!(Pattern::parse("out/{n:wide}.csv").is_err());
+        assert_eq!(sanitize(".."), "__");
+        assert_eq!(sanitize(""), "_");
+    }
+
+    #[test]
+    fn record_bytes_match_quoted_output() {
+        let record = vec!["a\"b".to_string(), String::new()];
+        let mut writer = csv::WriterBuilder::new()
+            .quote_style(csv::QuoteStyle::Always)
+            .from_writer(Vec::new());
+        writer.write_record(record.iter()).expect("write");
+        let written = writer.into_inner().expect("flush").len();
+        assert_eq!(
+            record_bytes(&Dialect::default(), b',', &record, UTF_8).expect("measure"),
+            written
+        );
+    }
+}
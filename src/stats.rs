@@ -21,6 +21,7 @@ use crate::{
     cli::StatsArgs,
     data::Value,
     filter, frequency, io_utils,
+    malformed::{MalformedRows, MalformedSummary},
     rows::{evaluate_filter_expressions, parse_typed_row},
     schema::{self, ColumnType, DecimalSpec, Schema},
     table,
@@ -28,7 +29,7 @@ use crate::{
 
 /// Computes and prints summary statistics (count, min, max, mean, median, std-dev)
 /// or frequency counts for numeric and temporal columns in a CSV file.
-pub fn execute(args: &StatsArgs) -> Result<()> {
+pub fn execute(args: &StatsArgs) -> Result<MalformedSummary> {
     if args.schema.is_none() && io_utils::is_dash(&args.input) {
         return Err(anyhow!(
             "Reading from stdin requires --schema (or --meta) for stats operations"
@@ -38,7 +39,8 @@ pub fn execute(args: &StatsArgs) -> Result<()> {
     let delimiter = io_utils::resolve_input_delimiter(&args.input, args.delimiter);
     let encoding = io_utils::resolve_encoding(args.input_encoding.as_deref())?;
 
-    let schema = load_or_infer_schema(args, delimiter, encoding)?;
+    let malformed = MalformedRows::from_args(&args.malformed)?;
+    let schema = load_or_infer_schema(args, delimiter, encoding, malformed.as_ref())?;
 
     let columns = resolve_columns(&schema, &args.columns, args.frequency)?;
     if columns.is_empty() {
@@ -60,6 +62,8 @@ pub fn execute(args: &StatsArgs) -> Result<()> {
             row_limit: (args.limit > 0).then_some(args.limit),
             filters: &filters,
             filter_exprs: &args.filter_exprs,
+            input_format: &args.input_format,
+            malformed: malformed.as_ref(),
         };
         let rows = frequency::compute_frequency_rows(
             &args.input,
@@ -69,6 +73,11 @@ pub fn execute(args: &StatsArgs) -> Result<()> {
             &columns,
             &freq_options,
         )?;
+        let malformed_rows = malformed
+            .as_ref()
+            .map(MalformedRows::finish)
+            .transpose()?
+            .unwrap_or_default();
         let headers = vec![
             "column".to_string(),
             "value".to_string(),
@@ -77,25 +86,31 @@ pub fn execute(args: &StatsArgs) -> Result<()> {
         ];
         table::print_table(&headers, &rows);
         info!("Computed frequency counts for {} column(s)", columns.len());
-        return Ok(());
-    }
-
-    let expects_headers = schema.expects_headers();
-    let mut reader = io_utils::open_csv_reader_from_path(&args.input, delimiter, expects_headers)?;
-    let headers = if expects_headers {
-        let headers = io_utils::reader_headers(&mut reader, encoding)?;
-        schema
-            .validate_headers(&headers)
-            .with_context(|| format!("Validating headers for {:?}", args.input))?;
-        headers
-    } else {
-        schema.headers()
+        return Ok(malformed_rows);
+    }
+
+    let (read_headers, records) = io_utils::open_schema_records(
+        &args.input,
+        &schema,
+        delimiter,
+        encoding,
+        &args.input_format,
+        malformed.as_ref(),
+    )?;
+    let headers = match read_headers {
+        Some(headers) => {
+            schema
+                .validate_headers(&headers)
+                .with_context(|| format!("Validating headers for {:?}", args.input))?;
+            headers
+        }
+        None => schema.headers(),
     };
     let header_aliases = schema.header_alias_sets();
 
     let mut stats = StatsAccumulator::new(&columns, &schema);
 
-    for (row_idx, record) in reader.byte_records().enumerate() {
+    for (row_idx, record) in records.enumerate() {
         if args.limit > 0 && row_idx >= args.limit {
             break;
         }
@@ -138,6 +153,11 @@ pub fn execute(args: &StatsArgs) -> Result<()> {
             .ingest(&typed)
             .with_context(|| format!("Processing row {}", row_idx + 2))?;
     }
+    let malformed_rows = malformed
+        .as_ref()
+        .map(MalformedRows::finish)
+        .transpose()?
+        .unwrap_or_default();
 
     let rows = stats.render_rows();
     let headers = vec![
@@ -151,20 +171,36 @@ pub fn execute(args: &StatsArgs) -> Result<()> {
     ];
     table::print_table(&headers, &rows);
     info!("Computed summary statistics for {} column(s)", rows.len());
-    Ok(())
+    Ok(malformed_rows)
 }
 
 fn load_or_infer_schema(
     args: &StatsArgs,
     delimiter: u8,
     encoding: &'static Encoding,
+    malformed: Option<&MalformedRows>,
 ) -> Result<Schema> {
-    if let Some(path) = &args.schema {
-        Schema::load(path).with_context(|| format!("Loading schema from {path:?}"))
+    let mut schema = if let Some(path) = &args.schema {
+        Schema::load(path).with_context(|| format!("Loading schema from {path:?}"))?
     } else {
-        schema::infer_schema(&args.input, 0, delimiter, encoding, None)
-            .with_context(|| format!("Inferring schema from {input:?}", input = args.input))
-    }
+        schema::infer_schema_from(
+            &args.input,
+            delimiter,
+            encoding,
+            &schema::InferenceInput {
+                sample_rows: 0,
+                header_override: None,
+                input_format: &args.input_format,
+                dialect_args: &args.dialect,
+                malformed,
+                seed: None,
+            },
+        )
+        .map(|(schema, _stats)| schema)
+        .with_context(|| format!("Inferring schema from {input:?}", input = args.input))?
+    };
+    schema.apply_dialect_args(&args.dialect)?;
+    Ok(schema)
 }
 
 fn resolve_columns(
@@ -570,8 +606,13 @@ mod tests {
         schema.columns[assists_index].datatype = crate::schema::ColumnType::Integer;
         let columns = vec![goals_index, assists_index];
         let mut accumulator = StatsAccumulator::new(&columns, &schema);
-        let mut reader =
-            crate::io_utils::open_csv_reader_from_path(&path, delimiter, true).expect("open csv");
+        let mut reader = crate::io_utils::open_csv_reader_from_path(
+            &path,
+            delimiter,
+            true,
+            &crate::dialect::Dialect::default(),
+        )
+        .expect("open csv");
         crate::io_utils::reader_headers(&mut reader, UTF_8).expect("headers");
 
         for (idx, record) in reader.byte_records().enumerate() {
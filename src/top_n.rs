@@ -0,0 +1,146 @@
+//! Bounded top-N selection for `process --sort ... --limit N`.
+//!
+//! Instead of buffering and sorting every row, a max-heap of at most `N` rows is
+//! maintained where the heap root is the *worst* retained row under
+//! [`compare_rows`]. Each incoming row either displaces the root or is
+//! discarded, so memory stays proportional to `N` and the pass is
+//! O(n log N). Because [`compare_rows`] falls back to the input `ordinal`,
+//! ties resolve exactly as they would after a full stable sort.
+
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+use crate::process::{RowData, SortInstruction, compare_rows};
+
+/// Retains the first `capacity` rows of the sort order defined by a plan.
+pub(crate) struct TopRows<'p> {
+    capacity: usize,
+    plan: &'p [SortInstruction],
+    heap: BinaryHeap<RankedRow<'p>>,
+}
+
+impl<'p> TopRows<'p> {
+    pub(crate) fn new(capacity: usize, plan: &'p [SortInstruction]) -> Self {
+        Self {
+            capacity,
+            plan,
+            heap: BinaryHeap::with_capacity(capacity.saturating_add(1).min(1 << 16)),
+        }
+    }
+
+    /// Offers a row, keeping it only if it ranks within the first `capacity` rows.
+    pub(crate) fn push(&mut self, row: RowData) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.heap.len() < self.capacity {
+            self.heap.push(RankedRow {
+                row,
+                plan: self.plan,
+            });
+            return;
+        }
+        let displaces_worst = self
+            .heap
+            .peek()
+            .is_some_and(|worst| compare_rows(&row, &worst.row, self.plan) == Ordering::Less);
+        if displaces_worst {
+            self.heap.pop();
+            self.heap.push(RankedRow {
+                row,
+                plan: self.plan,
+            });
+        }
+    }
+
+    /// Returns the retained rows in final sort order.
+    pub(crate) fn into_sorted_vec(self) -> Vec<RowData> {
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|ranked| ranked.row)
+            .collect()
+    }
+}
+
+struct RankedRow<'p> {
+    row: RowData,
+    plan: &'p [SortInstruction],
+}
+
+impl PartialEq for RankedRow<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for RankedRow<'_> {}
+
+impl PartialOrd for RankedRow<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for RankedRow<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        compare_rows(&self.row, &other.row, self.plan)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::Value;
+
+    fn rows() -> Vec<RowData> {
+        (0..200)
+            .map(|idx| {
+                let key = ((idx * 13) % 7) as i64;
+                RowData {
+                    raw: vec![key.to_string()],
+                    typed: vec![Some(Value::Integer(key))],
+                    ordinal: idx,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn top_rows_match_full_sort_prefix_including_ties() {
+        let plan = vec![SortInstruction {
+            index: 0,
+            ascending: false,
+        }];
+        let mut expected = rows();
+        expected.sort_by(|a, b| compare_rows(a, b, &plan));
+        let expected = expected
+            .into_iter()
+            .take(45)
+            .map(|row| row.ordinal)
+            .collect::<Vec<_>>();
+
+        let mut top = TopRows::new(45, &plan);
+        for row in rows() {
+            top.push(row);
+        }
+        let actual = top
+            .into_sorted_vec()
+            .into_iter()
+            .map(|row| row.ordinal)
+            .collect::<Vec<_>>();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn zero_capacity_retains_nothing() {
+        let plan = vec![SortInstruction {
+            index: 0,
+            ascending: true,
+        }];
+        let mut top = TopRows::new(0, &plan);
+        for row in rows() {
+            top.push(row);
+        }
+        assert!(top.into_sorted_vec().is_empty());
+    }
+}
@@ -0,0 +1,297 @@
+//! Recovery from cells that fail their datatype mapping or column type.
+//!
+//! `process` normally stops at the first cell that cannot be mapped or parsed.
+//! `--on-type-error` chooses what happens instead:
+//!
+//! - `fail` (default): stop with the error.
+//! - `null`: empty the cell and keep the row.
+//! - `quarantine`: drop the row and append it to `--type-reject-file`, followed
+//!   by its `source` file and the `row_number`, `column_name`, `raw_value`, and
+//!   `reason` of its first bad cell — the fields `schema verify` reports for
+//!   invalid values. `row_number` is the row's line in its own file, as in the
+//!   `--reject-file` of `--on-malformed quarantine`.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use anyhow::{Context, Result, bail};
+use encoding_rs::UTF_8;
+use log::{debug, info};
+
+use crate::{
+    cli::OnTypeError,
+    data::{Value, parse_typed_value},
+    dialect::Dialect,
+    io_utils::{self, FinishWrite},
+    rows::parse_typed_row,
+    schema::Schema,
+};
+
+/// A cell that failed its datatype mapping or could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CellError {
+    pub(crate) column: usize,
+    pub(crate) value: String,
+    pub(crate) reason: String,
+}
+
+/// Runs each column's datatype mappings, emptying and reporting the cells that
+/// fail instead of stopping at the first.
+pub(crate) fn map_row(schema: &Schema, row: &mut [String]) -> Vec<CellError> {
+    let mut errors = Vec::new();
+    for (idx, column) in schema.columns.iter().enumerate() {
+        if !column.has_mappings() {
+            continue;
+        }
+        let Some(cell) = row.get_mut(idx) else {
+            continue;
+        };
+        match column.apply_mappings_to_value(cell) {
+            Ok(Some(mapped)) => *cell = mapped,
+            Ok(None) => cell.clear(),
+            Err(err) => errors.push(CellError {
+                column: idx,
+                value: std::mem::take(cell),
+                reason: err.to_string(),
+            }),
+        }
+    }
+    errors
+}
+
+/// Parses each cell as its column type, leaving the cells that fail `None`.
+pub(crate) fn type_row(schema: &Schema, row: &[String]) -> (Vec<Option<Value>>, Vec<CellError>) {
+    let mut errors = Vec::new();
+    let typed = schema
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(idx, column)| {
+            let value = row.get(idx).map(|s| s.as_str()).unwrap_or("");
+            let normalized = column.normalize_value(value);
+            parse_typed_value(normalized.as_ref(), &column.datatype).unwrap_or_else(|err| {
+                errors.push(CellError {
+                    column: idx,
+                    value: value.to_string(),
+                    reason: err.to_string(),
+                });
+                None
+            })
+        })
+        .collect();
+    (typed, errors)
+}
+
+/// A row dropped by `--on-type-error quarantine`.
+#[derive(Debug)]
+pub(crate) struct TypeRejection {
+    /// Position of the row's input among the `-i` files.
+    pub(crate) file: usize,
+    /// Line of the row in its own file.
+    pub(crate) row_number: usize,
+    /// The row as read, before mappings and replacements.
+    pub(crate) record: Vec<String>,
+    pub(crate) error: CellError,
+}
+
+/// The `--on-type-error` policy for one `process` run, shared by the worker
+/// threads that prepare rows.
+pub(crate) struct TypeErrors {
+    policy: OnTypeError,
+    nulled: AtomicUsize,
+}
+
+impl TypeErrors {
+    pub(crate) fn new(policy: OnTypeError) -> Self {
+        Self {
+            policy,
+            nulled: AtomicUsize::new(0),
+        }
+    }
+
+    pub(crate) fn policy(&self) -> OnTypeError {
+        self.policy
+    }
+
+    /// Applies `schema`'s datatype mappings to `row`. Under `fail` the first
+    /// failure is returned as an error; otherwise failed cells are emptied and
+    /// collected.
+    pub(crate) fn map_row(&self, schema: &Schema, row: &mut [String]) -> Result<Vec<CellError>> {
+        if self.policy == OnTypeError::Fail {
+            schema.apply_transformations_to_row(row)?;
+            return Ok(Vec::new());
+        }
+        Ok(map_row(schema, row))
+    }
+
+    /// Parses `row` as `schema`'s column types, adding failed cells to `errors`
+    /// unless the policy is `fail`.
+    pub(crate) fn type_row(
+        &self,
+        schema: &Schema,
+        row: &[String],
+        errors: &mut Vec<CellError>,
+    ) -> Result<Vec<Option<Value>>> {
+        if self.policy == OnTypeError::Fail {
+            return parse_typed_row(schema, row);
+        }
+        let (typed, failed) = type_row(schema, row);
+        errors.extend(failed);
+        Ok(typed)
+    }
+
+    /// Empties the failed cells of `row` under `null`, or returns the row's
+    /// rejection under `quarantine`. `original` is the row as read, from line
+    /// `row_number` of input `file`.
+    pub(crate) fn settle(
+        &self,
+        row: &mut [String],
+        mut errors: Vec<CellError>,
+        original: Option<Vec<String>>,
+        (file, row_number): (usize, usize),
+    ) -> Option<TypeRejection> {
+        if errors.is_empty() {
+            return None;
+        }
+        if let Some(record) = original.filter(|_| self.policy == OnTypeError::Quarantine) {
+            return Some(TypeRejection {
+                file,
+                row_number,
+                record,
+                error: errors.swap_remove(0),
+            });
+        }
+        for error in &errors {
+            debug!(
+                "Row {row_number}: emptying {:?} ({})",
+                error.value, error.reason
+            );
+            if let Some(cell) = row.get_mut(error.column) {
+                cell.clear();
+            }
+        }
+        self.nulled.fetch_add(errors.len(), Ordering::Relaxed);
+        None
+    }
+
+    /// Logs how many cells were emptied.
+    pub(crate) fn finish(self) {
+        let nulled = self.nulled.into_inner();
+        if nulled > 0 {
+            info!("Emptied {nulled} cell(s) that failed their datatype mapping or column type");
+        }
+    }
+}
+
+/// The reject file of `--on-type-error quarantine`.
+pub(crate) struct TypeRejects {
+    writer: csv::Writer<Box<dyn FinishWrite>>,
+    path: PathBuf,
+    sources: Vec<String>,
+    column_names: Vec<String>,
+    rows: usize,
+}
+
+impl TypeRejects {
+    /// Validates the flag combination and opens the reject file when quarantining.
+    /// `headers` names the columns of the rows as read from `inputs`.
+    pub(crate) fn open(
+        policy: OnTypeError,
+        path: Option<&Path>,
+        inputs: &[PathBuf],
+        headers: &[String],
+        schema: &Schema,
+    ) -> Result<Option<Self>> {
+        let path = match (policy, path) {
+            (OnTypeError::Quarantine, Some(path)) => path,
+            (OnTypeError::Quarantine, None) => {
+                bail!("--on-type-error quarantine requires --type-reject-file")
+            }
+            (_, Some(_)) => bail!("--type-reject-file requires --on-type-error quarantine"),
+            (_, None) => return Ok(None),
+        };
+        let mut writer = io_utils::open_csv_writer(Some(path), b',', UTF_8, &Dialect::default())?;
+        writer
+            .write_record(headers.iter().map(String::as_str).chain([
+                "source",
+                "row_number",
+                "column_name",
+                "raw_value",
+                "reason",
+            ]))
+            .with_context(|| format!("Writing type reject file header to {path:?}"))?;
+        Ok(Some(Self {
+            writer,
+            path: path.to_path_buf(),
+            sources: inputs
+                .iter()
+                .map(|input| input.display().to_string())
+                .collect(),
+            column_names: schema
+                .columns
+                .iter()
+                .map(|column| column.output_name().to_string())
+                .collect(),
+            rows: 0,
+        }))
+    }
+
+    pub(crate) fn write(&mut self, rejection: &TypeRejection) -> Result<()> {
+        let error = &rejection.error;
+        // Report the value as read; lookup columns only exist after enrichment.
+        let raw_value = rejection.record.get(error.column).unwrap_or(&error.value);
+        let column_name = self
+            .column_names
+            .get(error.column)
+            .map_or("", String::as_str);
+        let source = self.sources.get(rejection.file).map_or("", String::as_str);
+        self.writer
+            .write_record(rejection.record.iter().map(String::as_str).chain([
+                source,
+                rejection.row_number.to_string().as_str(),
+                column_name,
+                raw_value,
+                &error.reason,
+            ]))
+            .with_context(|| format!("Writing rejected row {}", rejection.row_number))?;
+        self.rows += 1;
+        Ok(())
+    }
+
+    /// Finishes the reject file and logs how many rows it received.
+    pub(crate) fn finish(self) -> Result<()> {
+        io_utils::finish_csv_writer(self.writer)
+            .with_context(|| format!("Finishing type reject file {:?}", self.path))?;
+        if self.rows > 0 {
+            info!(
+                "Quarantined {} row(s) with type errors to {:?}",
+                self.rows, self.path
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::ColumnType;
+
+    #[test]
+    fn type_row_reports_each_bad_cell_and_keeps_the_rest() {
+        let mut schema = Schema::from_headers(&["id".to_string(), "amount".to_string()]);
+        schema.columns[0].datatype = ColumnType::Integer;
+        schema.columns[1].datatype = ColumnType::Float;
+        let row = vec!["7".to_string(), "n/a".to_string()];
+
+        let (typed, errors) = type_row(&schema, &row);
+
+        assert_eq!(typed, vec![Some(Value::Integer(7)), None]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].column, 1);
+        assert_eq!(errors[0].value, "n/a");
+        assert!(errors[0].reason.contains("n/a"), "{}", errors[0].reason);
+    }
+}
@@ -14,7 +14,7 @@ use anyhow::{Context, Result, anyhow};
 use log::info;
 
 use crate::{
-    cli::SchemaVerifyArgs,
+    cli::{InputFormatArgs, SchemaVerifyArgs},
     data::parse_typed_value,
     io_utils,
     schema::{ColumnType, Schema},
@@ -25,8 +25,9 @@ use crate::{
 /// and optionally printing an invalid-row detail or summary table.
 pub fn execute(args: &SchemaVerifyArgs) -> Result<()> {
     let input_encoding = io_utils::resolve_encoding(args.input_encoding.as_deref())?;
-    let schema = Schema::load(&args.schema)
+    let mut schema = Schema::load(&args.schema)
         .with_context(|| format!("Loading schema from {schema:?}", schema = args.schema))?;
+    schema.apply_dialect_args(&args.dialect)?;
     let report_config = args
         .report_invalid
         .as_ref()
@@ -34,7 +35,14 @@ pub fn execute(args: &SchemaVerifyArgs) -> Result<()> {
         .transpose()?;
     for input in &args.inputs {
         let delimiter = io_utils::resolve_input_delimiter(input, args.delimiter);
-        validate_file_against_schema(&schema, input, delimiter, input_encoding, report_config)?;
+        validate_file_against_schema(
+            &schema,
+            input,
+            delimiter,
+            input_encoding,
+            &args.input_format,
+            report_config,
+        )?;
         info!("✓ {input:?} matches schema");
     }
     Ok(())
@@ -111,12 +119,12 @@ fn validate_file_against_schema(
     path: &Path,
     delimiter: u8,
     encoding: &'static encoding_rs::Encoding,
+    input_format: &InputFormatArgs,
     report: Option<InvalidReportOptions>,
 ) -> Result<()> {
-    let mut reader =
-        io_utils::open_csv_reader_from_path(path, delimiter, schema.expects_headers())?;
-    if schema.expects_headers() {
-        let headers = io_utils::reader_headers(&mut reader, encoding)?;
+    let (headers, records) =
+        io_utils::open_schema_records(path, schema, delimiter, encoding, input_format, None)?;
+    if let Some(headers) = headers {
         schema
             .validate_headers(&headers)
             .map_err(|err| anyhow!("Validating headers for {path:?}: {err}"))?;
@@ -136,7 +144,7 @@ fn validate_file_against_schema(
     let mut column_summary: HashMap<String, ColumnSummary> = HashMap::new();
     let mut total_errors = 0usize;
 
-    for (row_idx, record) in reader.byte_records().enumerate() {
+    for (row_idx, record) in records.enumerate() {
         let record = record.with_context(|| format!("Reading row {} in {path:?}", row_idx + 2))?;
         let decoded = io_utils::decode_record(&record, encoding)?;
         let mut transformed = decoded.clone();
@@ -0,0 +1,692 @@
+//! Window-style derived columns for `process --window`.
+//!
+//! A window specification has the form
+//! `name=function(args) [partition by col, ...] [order by col [asc|desc], ...]`
+//! and supports:
+//!
+//! | Function | Value |
+//! |----------|-------|
+//! | `running_sum(col)` | Cumulative sum of `col` within the partition (exact for Integer/Decimal/Currency) |
+//! | `lag(col[, offset[, default]])` | `col` from `offset` rows earlier in the partition (default 1) |
+//! | `lead(col[, offset[, default]])` | `col` from `offset` rows later in the partition (default 1) |
+//! | `row_number()` | 1-based position within the partition |
+//! | `rank()` | Rank by the `order by` values, with ties sharing a rank and leaving gaps |
+//!
+//! Windows never sort on their own. The process sort plan (or the index variant
+//! selected for it) must deliver rows grouped by the partition columns and then
+//! ordered by the `order by` columns; when no `--sort` is given the plan is
+//! derived from the first window. Rows then flow through [`WindowEvaluator`] in
+//! a single pass, holding back only as many rows as the largest `lead` offset.
+//!
+//! Window values are appended to each row after the input columns so they are
+//! emitted like derived columns and can be referenced by name from `--derive`.
+
+use std::collections::VecDeque;
+
+use anyhow::{Context, Result, anyhow, bail};
+
+use crate::{
+    aggregate::NumericSum,
+    data::{ComparableValue, Value, parse_typed_value},
+    process::{RowData, SortInstruction},
+    schema::{ColumnType, DECIMAL_MAX_PRECISION, DecimalSpec, Schema},
+};
+
+/// A `--window` specification with column references still unresolved.
+#[derive(Debug, Clone)]
+pub struct WindowSpec {
+    pub name: String,
+    function: FunctionSpec,
+    pub partition_by: Vec<String>,
+    /// `(column, ascending)` pairs.
+    pub order_by: Vec<(String, bool)>,
+}
+
+#[derive(Debug, Clone)]
+enum FunctionSpec {
+    RunningSum(String),
+    Lag(String, usize, Option<String>),
+    Lead(String, usize, Option<String>),
+    RowNumber,
+    Rank,
+}
+
+impl WindowSpec {
+    pub fn parse(spec: &str) -> Result<Self> {
+        let (name, body) = spec
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Window '{spec}' must use the form name=function(...)"))?;
+        let name = name.trim();
+        if name.is_empty() {
+            bail!("Window '{spec}' is missing a name");
+        }
+        let body = body.trim();
+        let open = body
+            .find('(')
+            .ok_or_else(|| anyhow!("Window '{spec}' is missing a function call"))?;
+        let close = body
+            .find(')')
+            .filter(|close| *close > open)
+            .ok_or_else(|| anyhow!("Window '{spec}' is missing a closing ')'"))?;
+        let function_name = body[..open].trim().to_ascii_lowercase();
+        let args = body[open + 1..close]
+            .split(',')
+            .map(str::trim)
+            .filter(|arg| !arg.is_empty())
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        let function = parse_function(&function_name, args)
+            .with_context(|| format!("Parsing window '{spec}'"))?;
+        let (partition_by, order_by) = parse_clauses(&body[close + 1..])
+            .with_context(|| format!("Parsing window '{spec}'"))?;
+        if matches!(function, FunctionSpec::Rank) && order_by.is_empty() {
+            bail!("Window '{spec}' uses rank() and requires an order by clause");
+        }
+        Ok(Self {
+            name: name.to_string(),
+            function,
+            partition_by,
+            order_by,
+        })
+    }
+}
+
+pub fn parse_windows(specs: &[String]) -> Result<Vec<WindowSpec>> {
+    specs.iter().map(|spec| WindowSpec::parse(spec)).collect()
+}
+
+fn parse_function(name: &str, args: Vec<String>) -> Result<FunctionSpec> {
+    let mut args = args.into_iter();
+    let mut column = |function: &str| {
+        args.next()
+            .ok_or_else(|| anyhow!("{function}() requires a column argument"))
+    };
+    let function = match name {
+        "running_sum" => FunctionSpec::RunningSum(column("running_sum")?),
+        "lag" | "lead" => {
+            let source = column(name)?;
+            let offset = match args.next() {
+                Some(offset) => offset
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|offset| *offset > 0)
+                    .ok_or_else(|| anyhow!("{name}() offset must be a positive integer"))?,
+                None => 1,
+            };
+            let default = args.next();
+            if name == "lag" {
+                FunctionSpec::Lag(source, offset, default)
+            } else {
+                FunctionSpec::Lead(source, offset, default)
+            }
+        }
+        "row_number" => FunctionSpec::RowNumber,
+        "rank" => FunctionSpec::Rank,
+        other => bail!(
+            "Unknown window function '{other}' (expected running_sum, lag, lead, row_number, or rank)"
+        ),
+    };
+    if args.next().is_some() {
+        bail!("Too many arguments for {name}()");
+    }
+    Ok(function)
+}
+
+type Clauses = (Vec<String>, Vec<(String, bool)>);
+
+fn parse_clauses(text: &str) -> Result<Clauses> {
+    #[derive(PartialEq)]
+    enum Section {
+        None,
+        Partition,
+        Order,
+    }
+    let words = text.split_whitespace().collect::<Vec<_>>();
+    let mut section = Section::None;
+    let mut partition = String::new();
+    let mut order = String::new();
+    let mut idx = 0;
+    while idx < words.len() {
+        let word = words[idx];
+        let next_is_by = words
+            .get(idx + 1)
+            .is_some_and(|next| next.eq_ignore_ascii_case("by"));
+        if next_is_by && word.eq_ignore_ascii_case("partition") {
+            section = Section::Partition;
+            idx += 2;
+            continue;
+        }
+        if next_is_by && word.eq_ignore_ascii_case("order") {
+            section = Section::Order;
+            idx += 2;
+            continue;
+        }
+        let target = match section {
+            Section::Partition => &mut partition,
+            Section::Order => &mut order,
+            Section::None => bail!("Unexpected '{word}' after window function"),
+        };
+        if !target.is_empty() {
+            target.push(' ');
+        }
+        target.push_str(word);
+        idx += 1;
+    }
+
+    let partition_by = split_list(&partition);
+    let order_by = split_list(&order)
+        .into_iter()
+        .map(|item| parse_order_item(&item))
+        .collect::<Result<Vec<_>>>()?;
+    Ok((partition_by, order_by))
+}
+
+fn split_list(text: &str) -> Vec<String> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn parse_order_item(item: &str) -> Result<(String, bool)> {
+    let lowered = item.to_ascii_lowercase();
+    for (suffix, ascending) in [
+        (":asc", true),
+        (":desc", false),
+        (" asc", true),
+        (" desc", false),
+    ] {
+        if lowered.ends_with(suffix) {
+            let column = item[..item.len() - suffix.len()].trim();
+            if column.is_empty() {
+                bail!("Order by item '{item}' is missing a column");
+            }
+            return Ok((column.to_string(), ascending));
+        }
+    }
+    Ok((item.to_string(), true))
+}
+
+/// Window specifications with columns resolved against the input headers.
+#[derive(Debug)]
+pub(crate) struct WindowPlan {
+    windows: Vec<ResolvedWindow>,
+    max_lead: usize,
+}
+
+#[derive(Debug)]
+struct ResolvedWindow {
+    name: String,
+    function: Function,
+    partition: Vec<usize>,
+    order: Vec<SortInstruction>,
+}
+
+#[derive(Debug)]
+enum Function {
+    RunningSum(usize, ColumnType),
+    Lag(usize, usize, WindowValue),
+    Lead(usize, usize, WindowValue),
+    RowNumber,
+    Rank,
+}
+
+type WindowValue = (String, Option<Value>);
+
+impl WindowPlan {
+    /// Resolves column names using `resolve`, which maps a name to its input index.
+    pub(crate) fn resolve(
+        specs: &[WindowSpec],
+        schema: &Schema,
+        resolve: impl Fn(&str) -> Result<usize>,
+    ) -> Result<Self> {
+        let mut windows = Vec::with_capacity(specs.len());
+        for spec in specs {
+            let context = || format!("Resolving window '{}'", spec.name);
+            let default_value = |column: usize, default: &Option<String>| {
+                let raw = default.clone().unwrap_or_default();
+                let typed = parse_typed_value(&raw, &schema.columns[column].datatype)
+                    .ok()
+                    .flatten();
+                (raw, typed)
+            };
+            let function = match &spec.function {
+                FunctionSpec::RunningSum(column) => {
+                    let idx = resolve(column).with_context(context)?;
+                    let datatype = schema.columns[idx].datatype.clone();
+                    if !matches!(
+                        datatype,
+                        ColumnType::Integer
+                            | ColumnType::Float
+                            | ColumnType::Decimal(_)
+                            | ColumnType::Currency
+                    ) {
+                        bail!(
+                            "Window '{}' requires a numeric column but '{column}' is {}",
+                            spec.name,
+                            datatype.describe()
+                        );
+                    }
+                    Function::RunningSum(idx, datatype)
+                }
+                FunctionSpec::Lag(column, offset, default) => {
+                    let idx = resolve(column).with_context(context)?;
+                    Function::Lag(idx, *offset, default_value(idx, default))
+                }
+                FunctionSpec::Lead(column, offset, default) => {
+                    let idx = resolve(column).with_context(context)?;
+                    Function::Lead(idx, *offset, default_value(idx, default))
+                }
+                FunctionSpec::RowNumber => Function::RowNumber,
+                FunctionSpec::Rank => Function::Rank,
+            };
+            let partition = spec
+                .partition_by
+                .iter()
+                .map(|column| resolve(column))
+                .collect::<Result<Vec<_>>>()
+                .with_context(context)?;
+            let order = spec
+                .order_by
+                .iter()
+                .map(|(column, ascending)| {
+                    Ok(SortInstruction {
+                        index: resolve(column)?,
+                        ascending: *ascending,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()
+                .with_context(context)?;
+            windows.push(ResolvedWindow {
+                name: spec.name.clone(),
+                function,
+                partition,
+                order,
+            });
+        }
+        let max_lead = windows
+            .iter()
+            .filter_map(|window| match window.function {
+                Function::Lead(_, offset, _) => Some(offset),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0);
+        Ok(Self { windows, max_lead })
+    }
+
+    /// Output names of the window columns, in evaluation order.
+    pub(crate) fn names(&self) -> impl Iterator<Item = &str> {
+        self.windows.iter().map(|window| window.name.as_str())
+    }
+
+    /// Datatypes of the window columns, in evaluation order. Running sums of
+    /// Decimal columns widen to the maximum precision at the same scale.
+    pub(crate) fn column_types<'s>(
+        &'s self,
+        schema: &'s Schema,
+    ) -> impl Iterator<Item = ColumnType> + 's {
+        self.windows.iter().map(|window| match &window.function {
+            Function::RunningSum(_, ColumnType::Decimal(spec)) => {
+                ColumnType::Decimal(DecimalSpec {
+                    precision: DECIMAL_MAX_PRECISION,
+                    scale: spec.scale,
+                })
+            }
+            Function::RunningSum(_, datatype) => datatype.clone(),
+            Function::Lag(column, ..) | Function::Lead(column, ..) => {
+                schema.columns[*column].datatype.clone()
+            }
+            Function::RowNumber | Function::Rank => ColumnType::Integer,
+        })
+    }
+
+    /// Number of rows beyond the emitted ones that must be retained for `lead`.
+    pub(crate) fn lookahead(&self) -> usize {
+        self.max_lead
+    }
+
+    /// Sort instructions implied by the first window: partition columns ascending,
+    /// then its `order by` columns.
+    pub(crate) fn implied_sort(&self) -> Vec<SortInstruction> {
+        self.windows
+            .first()
+            .map(|window| {
+                window
+                    .partition
+                    .iter()
+                    .map(|index| SortInstruction {
+                        index: *index,
+                        ascending: true,
+                    })
+                    .chain(window.order.iter().cloned())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Checks that `sort_plan` delivers every window's partitions contiguously and in
+    /// `order by` order.
+    pub(crate) fn validate_order(&self, sort_plan: &[SortInstruction]) -> Result<()> {
+        for window in &self.windows {
+            let partition_len = window.partition.len();
+            let required = partition_len + window.order.len();
+            let grouped = sort_plan.len() >= required && {
+                let mut leading = sort_plan[..partition_len]
+                    .iter()
+                    .map(|instruction| instruction.index)
+                    .collect::<Vec<_>>();
+                let mut partition = window.partition.clone();
+                leading.sort_unstable();
+                partition.sort_unstable();
+                leading == partition
+            };
+            let ordered = grouped
+                && sort_plan[partition_len..required]
+                    .iter()
+                    .zip(&window.order)
+                    .all(|(actual, wanted)| {
+                        actual.index == wanted.index && actual.ascending == wanted.ascending
+                    });
+            if !ordered {
+                bail!(
+                    "Window '{}' needs rows sorted by its partition columns and then its order by columns; adjust --sort to match",
+                    window.name
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Computes window values over rows that arrive in window order.
+pub(crate) struct WindowEvaluator<'p> {
+    plan: &'p WindowPlan,
+    states: Vec<WindowState>,
+    pending: VecDeque<(RowData, Vec<Option<WindowValue>>)>,
+}
+
+#[derive(Default)]
+struct WindowState {
+    partition: Option<Vec<ComparableValue>>,
+    position: usize,
+    sum: Option<NumericSum>,
+    history: VecDeque<WindowValue>,
+    rank: usize,
+    last_order: Option<Vec<ComparableValue>>,
+}
+
+impl<'p> WindowEvaluator<'p> {
+    pub(crate) fn new(plan: &'p WindowPlan) -> Self {
+        Self {
+            plan,
+            states: plan
+                .windows
+                .iter()
+                .map(|_| WindowState::default())
+                .collect(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Accepts the next row in window order and returns the oldest row whose window
+    /// values are complete, if any.
+    pub(crate) fn push(&mut self, row: RowData) -> Result<Option<RowData>> {
+        let mut values = Vec::with_capacity(self.plan.windows.len());
+        for (window, state) in self.plan.windows.iter().zip(self.states.iter_mut()) {
+            let partition = key(&row, window.partition.iter().copied());
+            if state.partition.as_ref() != Some(&partition) {
+                *state = WindowState {
+                    partition: Some(partition),
+                    ..WindowState::default()
+                };
+            }
+            state.position += 1;
+            let value = match &window.function {
+                Function::RunningSum(column, datatype) => {
+                    let sum = state
+                        .sum
+                        .get_or_insert_with(|| NumericSum::for_type(datatype));
+                    if let Some(Some(value)) = row.typed.get(*column) {
+                        sum.add(value)
+                            .with_context(|| format!("Computing window '{}'", window.name))?;
+                    }
+                    Some((sum.render(), sum.as_value()))
+                }
+                Function::Lag(column, offset, default) => {
+                    let value = state
+                        .history
+                        .len()
+                        .checked_sub(*offset)
+                        .map(|idx| state.history[idx].clone())
+                        .unwrap_or_else(|| default.clone());
+                    state.history.push_back(source_value(&row, *column));
+                    if state.history.len() > *offset {
+                        state.history.pop_front();
+                    }
+                    Some(value)
+                }
+                Function::Lead(..) => None,
+                Function::RowNumber => Some(integer_value(state.position)),
+                Function::Rank => {
+                    let order = key(&row, window.order.iter().map(|order| order.index));
+                    if state.last_order.as_ref() != Some(&order) {
+                        state.rank = state.position;
+                        state.last_order = Some(order);
+                    }
+                    Some(integer_value(state.rank))
+                }
+            };
+            values.push(value);
+        }
+        self.pending.push_back((row, values));
+        if self.pending.len() > self.plan.max_lead {
+            return Ok(self.complete_front());
+        }
+        Ok(None)
+    }
+
+    /// Flushes the rows held back for `lead` once the input is exhausted.
+    pub(crate) fn finish(mut self) -> Vec<RowData> {
+        let mut rows = Vec::with_capacity(self.pending.len());
+        while let Some(row) = self.complete_front() {
+            rows.push(row);
+        }
+        rows
+    }
+
+    fn complete_front(&mut self) -> Option<RowData> {
+        let mut values = self.pending.front()?.1.clone();
+        for (window, value) in self.plan.windows.iter().zip(values.iter_mut()) {
+            if let Function::Lead(column, offset, default) = &window.function {
+                let (current, _) = &self.pending[0];
+                let partition = key(current, window.partition.iter().copied());
+                *value = Some(
+                    self.pending
+                        .get(*offset)
+                        .filter(|(ahead, _)| {
+                            key(ahead, window.partition.iter().copied()) == partition
+                        })
+                        .map(|(ahead, _)| source_value(ahead, *column))
+                        .unwrap_or_else(|| default.clone()),
+                );
+            }
+        }
+        let (mut row, _) = self.pending.pop_front()?;
+        for (raw, typed) in values.into_iter().flatten() {
+            row.raw.push(raw);
+            row.typed.push(typed);
+        }
+        Some(row)
+    }
+}
+
+/// Adapts a stream of rows in window order into rows carrying their window values.
+///
+/// Rows pass through unchanged when no windows were requested.
+pub(crate) struct WindowedRows<'p, I> {
+    rows: I,
+    evaluator: Option<WindowEvaluator<'p>>,
+    drained: std::vec::IntoIter<RowData>,
+}
+
+impl<'p, I> WindowedRows<'p, I> {
+    pub(crate) fn new(plan: Option<&'p WindowPlan>, rows: I) -> Self {
+        Self {
+            rows,
+            evaluator: plan.map(WindowEvaluator::new),
+            drained: Vec::new().into_iter(),
+        }
+    }
+}
+
+impl<I> Iterator for WindowedRows<'_, I>
+where
+    I: Iterator<Item = Result<RowData>>,
+{
+    type Item = Result<RowData>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(row) = self.drained.next() {
+                return Some(Ok(row));
+            }
+            let Some(evaluator) = self.evaluator.as_mut() else {
+                return self.rows.next();
+            };
+            match self.rows.next() {
+                Some(Ok(row)) => match evaluator.push(row) {
+                    Ok(Some(row)) => return Some(Ok(row)),
+                    Ok(None) => continue,
+                    Err(err) => return Some(Err(err)),
+                },
+                Some(Err(err)) => return Some(Err(err)),
+                None => {
+                    self.drained = self.evaluator.take()?.finish().into_iter();
+                }
+            }
+        }
+    }
+}
+
+fn key(row: &RowData, columns: impl Iterator<Item = usize>) -> Vec<ComparableValue> {
+    columns
+        .map(|idx| ComparableValue(row.typed.get(idx).cloned().flatten()))
+        .collect()
+}
+
+fn source_value(row: &RowData, column: usize) -> WindowValue {
+    (
+        row.raw.get(column).cloned().unwrap_or_default(),
+        row.typed.get(column).cloned().flatten(),
+    )
+}
+
+fn integer_value(value: usize) -> WindowValue {
+    (value.to_string(), Some(Value::Integer(value as i64)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema() -> Schema {
+        let mut schema = Schema::from_headers(&[
+            "account".to_string(),
+            "day".to_string(),
+            "amount".to_string(),
+        ]);
+        schema.columns[1].datatype = ColumnType::Integer;
+        schema.columns[2].datatype = ColumnType::Integer;
+        schema
+    }
+
+    fn resolve_in(schema: &Schema) -> impl Fn(&str) -> Result<usize> + '_ {
+        |name| {
+            schema
+                .column_index(name)
+                .ok_or_else(|| anyhow!("Column '{name}' not found"))
+        }
+    }
+
+    fn row(ordinal: usize, account: &str, day: i64, amount: i64) -> RowData {
+        RowData {
+            raw: vec![account.to_string(), day.to_string(), amount.to_string()],
+            typed: vec![
+                Some(Value::String(account.to_string())),
+                Some(Value::Integer(day)),
+                Some(Value::Integer(amount)),
+            ],
+            ordinal,
+        }
+    }
+
+    #[test]
+    fn parse_spec_with_partition_and_order() {
+        let spec = WindowSpec::parse(
+            "balance = running_sum(amount) PARTITION BY account, region order by posted_at desc, id:asc",
+        )
+        .expect("parse window");
+        assert_eq!(spec.name, "balance");
+        assert_eq!(spec.partition_by, vec!["account", "region"]);
+        assert_eq!(
+            spec.order_by,
+            vec![("posted_at".to_string(), false), ("id".to_string(), true)]
+        );
+        assert!(WindowSpec::parse("r=rank() partition by account").is_err());
+        assert!(WindowSpec::parse("x=median(amount)").is_err());
+        assert!(WindowSpec::parse("x=lag(amount, 0)").is_err());
+    }
+
+    #[test]
+    fn evaluator_computes_partitioned_values() {
+        let schema = schema();
+        let specs = parse_windows(&[
+            "balance=running_sum(amount) partition by account order by day".to_string(),
+            "prev=lag(amount, 1, 0) partition by account order by day".to_string(),
+            "next=lead(amount) partition by account order by day".to_string(),
+            "n=row_number() partition by account order by day".to_string(),
+            "r=rank() order by account".to_string(),
+        ])
+        .expect("parse windows");
+        let plan = WindowPlan::resolve(&specs, &schema, resolve_in(&schema)).expect("resolve");
+        assert_eq!(plan.lookahead(), 1);
+        plan.validate_order(&plan.implied_sort())
+            .expect("implied order");
+
+        let mut evaluator = WindowEvaluator::new(&plan);
+        let mut rows = Vec::new();
+        for input in [
+            row(0, "a", 1, 10),
+            row(1, "a", 2, 5),
+            row(2, "a", 3, -3),
+            row(3, "b", 1, 7),
+        ] {
+            rows.extend(evaluator.push(input).expect("push"));
+        }
+        rows.extend(evaluator.finish());
+        let windows = rows
+            .iter()
+            .map(|row| row.raw[3..].join("|"))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            windows,
+            vec!["10|0|5|1|1", "15|10|-3|2|1", "12|5||3|1", "7|0||1|4"]
+        );
+    }
+
+    #[test]
+    fn validate_order_rejects_mismatched_sort() {
+        let schema = schema();
+        let specs =
+            parse_windows(&["n=row_number() partition by account order by day".to_string()])
+                .expect("parse");
+        let plan = WindowPlan::resolve(&specs, &schema, resolve_in(&schema)).expect("resolve");
+        let by_day = vec![SortInstruction {
+            index: 1,
+            ascending: true,
+        }];
+        assert!(plan.validate_order(&by_day).is_err());
+    }
+}
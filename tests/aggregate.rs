@@ -0,0 +1,158 @@
+use std::path::{Path, PathBuf};
+
+use assert_cmd::Command;
+use csv::ReaderBuilder;
+use predicates::str::contains;
+use tempfile::tempdir;
+
+const SALES_SCHEMA: &str = "columns:
+- name: region
+  datatype: String
+- name: customer
+  datatype: String
+- name: amount
+  datatype: Currency
+- name: units
+  datatype: Integer
+";
+
+fn write_sales(dir: &Path) -> (PathBuf, PathBuf) {
+    let input = dir.join("sales.csv");
+    std::fs::write(
+        &input,
+        "region,customer,amount,units\n\
+         west,acme,10.10,3\n\
+         east,zeta,0.20,1\n\
+         west,acme,0.05,2\n\
+         east,beta,5.00,\n\
+         west,omni,1.00,7\n\
+         north,acme,0.10,4\n",
+    )
+    .expect("write sales");
+    let schema = dir.join("sales-schema.yml");
+    std::fs::write(&schema, SALES_SCHEMA).expect("write schema");
+    (input, schema)
+}
+
+fn read_rows(path: &Path) -> (Vec<String>, Vec<Vec<String>>) {
+    let mut reader = ReaderBuilder::new()
+        .has_headers(true)
+        .from_path(path)
+        .expect("open output");
+    let headers = reader
+        .headers()
+        .expect("headers")
+        .iter()
+        .map(str::to_string)
+        .collect();
+    let rows = reader
+        .records()
+        .map(|record| record.expect("record").iter().map(str::to_string).collect())
+        .collect();
+    (headers, rows)
+}
+
+#[test]
+fn aggregate_groups_with_exact_currency_sums() {
+    let temp = tempdir().expect("tempdir");
+    let (input, schema) = write_sales(temp.path());
+    let output = temp.path().join("by_region.csv");
+
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args([
+            "aggregate",
+            "-i",
+            input.to_str().unwrap(),
+            "-m",
+            schema.to_str().unwrap(),
+            "-o",
+            output.to_str().unwrap(),
+            "--group-by",
+            "region",
+            "--agg",
+            "count,total=sum(amount),mean(units),min(amount),max(customer)",
+            "--agg",
+            "count_distinct(customer)",
+            "--agg",
+            "first(customer),last(units)",
+        ])
+        .assert()
+        .success();
+
+    let (headers, rows) = read_rows(&output);
+    assert_eq!(
+        headers,
+        [
+            "region",
+            "count",
+            "total",
+            "mean_units",
+            "min_amount",
+            "max_customer",
+            "count_distinct_customer",
+            "first_customer",
+            "last_units",
+        ]
+    );
+    assert_eq!(
+        rows,
+        vec![
+            vec!["east", "2", "5.20", "1", "0.20", "zeta", "2", "zeta", ""],
+            vec!["north", "1", "0.10", "4", "0.10", "acme", "1", "acme", "4"],
+            vec!["west", "3", "11.15", "4", "0.05", "omni", "2", "acme", "7"],
+        ]
+    );
+}
+
+#[test]
+fn aggregate_without_group_by_applies_filters() {
+    let temp = tempdir().expect("tempdir");
+    let (input, schema) = write_sales(temp.path());
+    let output = temp.path().join("totals.csv");
+
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args([
+            "aggregate",
+            "-i",
+            input.to_str().unwrap(),
+            "-m",
+            schema.to_str().unwrap(),
+            "-o",
+            output.to_str().unwrap(),
+            "--agg",
+            "rows=count,sum(amount)",
+            "--filter",
+            "customer = acme",
+            "--filter-expr",
+            "units >= 3",
+        ])
+        .assert()
+        .success();
+
+    let (headers, rows) = read_rows(&output);
+    assert_eq!(headers, ["rows", "sum_amount"]);
+    assert_eq!(rows, vec![vec!["2", "10.20"]]);
+}
+
+#[test]
+fn aggregate_rejects_sum_of_text_column() {
+    let temp = tempdir().expect("tempdir");
+    let (input, schema) = write_sales(temp.path());
+
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args([
+            "aggregate",
+            "-i",
+            input.to_str().unwrap(),
+            "-m",
+            schema.to_str().unwrap(),
+            "--agg",
+            "sum(customer)",
+        ])
+        .assert()
+        .failure()
+        .stderr(contains("requires a numeric column"));
+}
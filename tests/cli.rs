@@ -1364,3 +1364,133 @@ fn append_header_column_count_mismatch_reports_error() {
         .failure()
         .stderr(contains("mismatch").or(contains("Mismatch")));
 }
+
+#[test]
+fn process_refuses_a_stale_index_unless_asked_to_rebuild_it() {
+    let dir = tempdir().expect("temp dir");
+    let csv_path = dir.path().join("landed.csv");
+    fs::write(&csv_path, "id,name\n3,c\n1,a\n2,b\n").expect("write csv");
+    let index_path = dir.path().join("landed.idx");
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["index", "-i"])
+        .arg(&csv_path)
+        .arg("-o")
+        .arg(&index_path)
+        .args(["-C", "id"])
+        .assert()
+        .success();
+
+    let sorted = |extra: &[&str]| {
+        let mut command = Command::cargo_bin("csv-managed").expect("binary exists");
+        command
+            .args(["process", "-i"])
+            .arg(&csv_path)
+            .arg("-x")
+            .arg(&index_path)
+            .args(["--sort", "id", "--quote-style", "necessary"])
+            .args(extra);
+        command.assert()
+    };
+    sorted(&[]).success().stdout("id,name\n1,a\n2,b\n3,c\n");
+
+    // The file is re-landed with different rows; the recorded offsets no longer fit.
+    fs::write(&csv_path, "id,name\n20,bb\n10,aa\n40,dd\n30,cc\n").expect("rewrite csv");
+    sorted(&[])
+        .failure()
+        .stderr(contains("is stale").and(contains("--rebuild-stale-index")));
+
+    sorted(&["--rebuild-stale-index"])
+        .success()
+        .stdout("id,name\n10,aa\n20,bb\n30,cc\n40,dd\n")
+        .stderr(contains("Rebuilding index"));
+    // The rebuilt index was saved, so the next run uses it without the flag.
+    sorted(&[])
+        .success()
+        .stdout("id,name\n10,aa\n20,bb\n30,cc\n40,dd\n");
+}
+
+#[test]
+fn index_update_adds_appended_rows_and_refuses_rewritten_files() {
+    let dir = tempdir().expect("temp dir");
+    let csv_path = dir.path().join("events.csv");
+    fs::write(&csv_path, "id,name\n3,c\n1,a\n").expect("write csv");
+    let index_path = dir.path().join("events.idx");
+    let index = |extra: &[&str]| {
+        let mut command = Command::cargo_bin("csv-managed").expect("binary exists");
+        command
+            .args(["index", "-i"])
+            .arg(&csv_path)
+            .arg("-o")
+            .arg(&index_path)
+            .args(extra);
+        command.assert()
+    };
+    index(&["--spec", "id:asc", "--spec", "desc=id:desc"]).success();
+
+    let mut file = fs::OpenOptions::new()
+        .append(true)
+        .open(&csv_path)
+        .expect("open csv for append");
+    file.write_all(b"4,d\n2,b\n").expect("append rows");
+    drop(file);
+    index(&["--update"])
+        .success()
+        .stderr(contains("Indexed 2 appended row(s)"));
+
+    let loaded = CsvIndex::load(&index_path).expect("load index");
+    assert_eq!(loaded.row_count(), 4);
+    assert_eq!(loaded.variants().len(), 2);
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["process", "-i"])
+        .arg(&csv_path)
+        .arg("-x")
+        .arg(&index_path)
+        .args(["--sort", "id:desc", "--quote-style", "necessary"])
+        .assert()
+        .success()
+        .stdout("id,name\n4,d\n3,c\n2,b\n1,a\n");
+
+    // Rewriting already-indexed rows is not an append.
+    fs::write(&csv_path, "id,name\n9,z\n1,a\n4,d\n2,b\n5,e\n").expect("rewrite csv");
+    index(&["--update"])
+        .failure()
+        .stderr(contains("changed since they were indexed"));
+}
+
+#[test]
+fn process_warns_that_threads_are_ignored_with_an_index() {
+    let dir = tempdir().expect("temp dir");
+    let csv_path = dir.path().join("rows.csv");
+    fs::write(&csv_path, "id,name\n2,b\n1,a\n").expect("write csv");
+    let index_path = dir.path().join("rows.idx");
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["index", "-i"])
+        .arg(&csv_path)
+        .arg("-o")
+        .arg(&index_path)
+        .args(["-C", "id"])
+        .assert()
+        .success();
+
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["process", "-i"])
+        .arg(&csv_path)
+        .arg("-x")
+        .arg(&index_path)
+        .args([
+            "--sort",
+            "id",
+            "--threads",
+            "2",
+            "--quote-style",
+            "necessary",
+        ])
+        .assert()
+        .success()
+        .stdout("id,name\n1,a\n2,b\n")
+        .stderr(contains("--threads is ignored"));
+}
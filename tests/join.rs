@@ -0,0 +1,527 @@
+//! Integration tests for `join`, including piped (`--left -`) left inputs.
+
+use std::{fs, path::Path};
+
+use assert_cmd::Command;
+use predicates::str::contains;
+use tempfile::tempdir;
+
+const CUSTOMERS: &str = "id,name,tier\n1,Ada,gold\n2,Grace,silver\n3,Linus,bronze\n";
+const ORDERS: &str =
+    "order_id,customer_id,total\n100,2,250.00\n101,1,720.50\n102,4,80.00\n103,02,610.00\n";
+
+fn write_customers(dir: &Path) -> std::path::PathBuf {
+    let path = dir.join("customers.csv");
+    fs::write(&path, CUSTOMERS).expect("write customers");
+    path
+}
+
+fn join_stdout(args: &[&str], stdin: &str) -> Vec<Vec<String>> {
+    let assert = Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .arg("join")
+        .args(args)
+        .write_stdin(stdin)
+        .assert()
+        .success();
+    let mut reader = csv::Reader::from_reader(assert.get_output().stdout.as_slice());
+    let mut rows = vec![
+        reader
+            .headers()
+            .expect("headers")
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>(),
+    ];
+    rows.extend(reader.records().map(|record| {
+        record
+            .expect("record")
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }));
+    rows
+}
+
+fn column(rows: &[Vec<String>], idx: usize) -> Vec<&str> {
+    rows.iter().map(|row| row[idx].as_str()).collect()
+}
+
+#[test]
+fn join_stream_left_infers_header_schema_and_uses_right_key_type() {
+    let temp = tempdir().expect("tempdir");
+    let customers = write_customers(temp.path());
+    let rows = join_stdout(
+        &[
+            "--left",
+            "-",
+            "--right",
+            customers.to_str().unwrap(),
+            "--left-key",
+            "customer_id",
+            "--right-key",
+            "id",
+        ],
+        ORDERS,
+    );
+    assert_eq!(
+        rows[0],
+        vec!["order_id", "customer_id", "total", "name", "tier"]
+    );
+    // Keys parse with the right side's integer type, so "02" matches id 2.
+    let matched = rows[1..]
+        .iter()
+        .map(|row| (row[0].as_str(), row[3].as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        matched,
+        vec![("100", "Grace"), ("101", "Ada"), ("103", "Grace")]
+    );
+}
+
+#[test]
+fn join_stream_left_assume_types_overrides_inferred_strings() {
+    let temp = tempdir().expect("tempdir");
+    let customers = write_customers(temp.path());
+    let schema = temp.path().join("customers-schema.yml");
+    fs::write(
+        &schema,
+        "columns:\n  - name: id\n    datatype: String\n  - name: name\n    datatype: String\n  - name: tier\n    datatype: String\n",
+    )
+    .expect("write schema");
+
+    let args = [
+        "--left",
+        "-",
+        "--right",
+        customers.to_str().unwrap(),
+        "--right-schema",
+        schema.to_str().unwrap(),
+        "--left-key",
+        "customer_id",
+        "--right-key",
+        "id",
+    ];
+    // String keys on both sides compare verbatim: "02" no longer matches "2".
+    let rows = join_stdout(&args, ORDERS);
+    assert_eq!(rows.len(), 3);
+
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .arg("join")
+        .args(args)
+        .args([
+            "--left-assume-types",
+            "customer_id:integer,total:decimal(10,2)",
+        ])
+        .write_stdin(ORDERS)
+        .assert()
+        .failure()
+        .stderr(contains("Type mismatch for join key 'customer_id'"))
+        .stderr(contains("--left-assume-types customer_id:string"));
+}
+
+#[test]
+fn join_stream_left_reports_invalid_key_token_with_row_and_type() {
+    let temp = tempdir().expect("tempdir");
+    let customers = write_customers(temp.path());
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args([
+            "join",
+            "--left",
+            "-",
+            "--right",
+            customers.to_str().unwrap(),
+            "--left-key",
+            "customer_id",
+            "--right-key",
+            "id",
+        ])
+        .write_stdin("order_id,customer_id\n100,2\n101,abc\n")
+        .assert()
+        .failure()
+        .stderr(contains("left row 3"))
+        .stderr(contains("value 'abc' is not a valid integer"));
+}
+
+#[test]
+fn join_stream_left_full_outer_includes_unmatched_rows() {
+    let temp = tempdir().expect("tempdir");
+    let customers = write_customers(temp.path());
+    let rows = join_stdout(
+        &[
+            "--left",
+            "-",
+            "--right",
+            customers.to_str().unwrap(),
+            "--left-key",
+            "customer_id",
+            "--right-key",
+            "id",
+            "--type",
+            "full",
+            "--left-assume-types",
+            "customer_id:integer",
+        ],
+        ORDERS,
+    );
+    assert_eq!(rows.len(), 6);
+    assert!(rows.contains(&vec![
+        "102".to_string(),
+        "4".to_string(),
+        "80.00".to_string(),
+        String::new(),
+        String::new(),
+    ]));
+    assert!(rows.contains(&vec![
+        String::new(),
+        "3".to_string(),
+        String::new(),
+        "Linus".to_string(),
+        "bronze".to_string(),
+    ]));
+}
+
+#[test]
+fn pipeline_process_join_process_without_temp_files() {
+    let temp = tempdir().expect("tempdir");
+    let customers = write_customers(temp.path());
+    let orders = temp.path().join("orders.csv");
+    fs::write(&orders, ORDERS).expect("write orders");
+
+    let stage1 = Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args([
+            "process",
+            "-i",
+            orders.to_str().unwrap(),
+            "--filter",
+            "total >= 200",
+            "--columns",
+            "order_id,customer_id,total",
+        ])
+        .assert()
+        .success();
+
+    let stage2 = Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args([
+            "join",
+            "--left",
+            "-",
+            "--right",
+            customers.to_str().unwrap(),
+            "--left-key",
+            "customer_id",
+            "--right-key",
+            "id",
+        ])
+        .write_stdin(stage1.get_output().stdout.clone())
+        .assert()
+        .success();
+
+    let stage3 = Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args([
+            "process",
+            "-i",
+            "-",
+            "--derive",
+            "label=concat(name,\"-\",tier)",
+            "--filter",
+            "total > 500",
+            "--columns",
+            "order_id,name",
+            "--sort",
+            "order_id:asc",
+        ])
+        .write_stdin(stage2.get_output().stdout.clone())
+        .assert()
+        .success();
+
+    let mut reader = csv::Reader::from_reader(stage3.get_output().stdout.as_slice());
+    assert_eq!(
+        reader.headers().expect("headers"),
+        vec!["order_id", "name", "label"]
+    );
+    let rows = reader
+        .records()
+        .map(|record| record.expect("record").iter().collect::<Vec<_>>().join(","))
+        .collect::<Vec<_>>();
+    assert_eq!(rows, ["101,Ada,Ada-gold", "103,Grace,Grace-silver"]);
+}
+
+fn sorted_rows(mut rows: Vec<Vec<String>>) -> Vec<Vec<String>> {
+    let header = rows.remove(0);
+    rows.sort();
+    rows.insert(0, header);
+    rows
+}
+
+#[test]
+fn merge_join_via_indexes_matches_hash_join_for_every_kind() {
+    let temp = tempdir().expect("tempdir");
+    let dir = temp.path();
+    fs::write(
+        dir.join("customers.csv"),
+        "id,name\n3,Linus\n1,Ada\n2,Grace\n2,Grace II\n5,Nobody\n",
+    )
+    .expect("write customers");
+    fs::write(
+        dir.join("orders.csv"),
+        "order_id,customer_id,total\n100,2,250\n101,1,720\n102,4,80\n103,02,610\n104,,5\n",
+    )
+    .expect("write orders");
+    fs::write(
+        dir.join("customers-schema.yml"),
+        "columns:\n  - name: id\n    datatype: Integer\n  - name: name\n    datatype: String\n",
+    )
+    .expect("write customers schema");
+    fs::write(
+        dir.join("orders-schema.yml"),
+        "columns:\n  - name: order_id\n    datatype: Integer\n  - name: customer_id\n    datatype: Integer\n  - name: total\n    datatype: Integer\n",
+    )
+    .expect("write orders schema");
+    for (input, key, index) in [
+        ("customers", "id", "customers.idx"),
+        ("orders", "customer_id", "orders.idx"),
+    ] {
+        Command::cargo_bin("csv-managed")
+            .expect("binary exists")
+            .current_dir(dir)
+            .args([
+                "index",
+                "-i",
+                &format!("{input}.csv"),
+                "-o",
+                index,
+                "--columns",
+                key,
+                "--schema",
+                &format!("{input}-schema.yml"),
+            ])
+            .assert()
+            .success();
+    }
+
+    let base = [
+        "--left",
+        "orders.csv",
+        "--right",
+        "customers.csv",
+        "--left-key",
+        "customer_id",
+        "--right-key",
+        "id",
+        "--left-schema",
+        "orders-schema.yml",
+        "--right-schema",
+        "customers-schema.yml",
+    ];
+    let run = |extra: &[&str]| {
+        let assert = Command::cargo_bin("csv-managed")
+            .expect("binary exists")
+            .current_dir(dir)
+            .arg("join")
+            .args(base)
+            .args(extra)
+            .assert()
+            .success();
+        let mut reader = csv::Reader::from_reader(assert.get_output().stdout.as_slice());
+        let mut rows = vec![
+            reader
+                .headers()
+                .expect("headers")
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+        ];
+        rows.extend(reader.records().map(|record| {
+            record
+                .expect("record")
+                .iter()
+                .map(ToString::to_string)
+                .collect()
+        }));
+        rows
+    };
+
+    for kind in ["inner", "left", "right", "full", "semi", "anti"] {
+        let merged = run(&[
+            "--type",
+            kind,
+            "--left-index",
+            "orders.idx",
+            "--right-index",
+            "customers.idx",
+        ]);
+        // Merge output follows key order, with unmatched rows interleaved by key.
+        let keys = merged[1..]
+            .iter()
+            .map(|row| row[1].parse::<i64>().ok())
+            .collect::<Vec<_>>();
+        assert!(keys.is_sorted(), "{kind} output is in key order: {keys:?}");
+        assert_eq!(
+            sorted_rows(merged),
+            sorted_rows(run(&["--type", kind])),
+            "{kind} join"
+        );
+    }
+
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .current_dir(dir)
+        .arg("join")
+        .args(base)
+        .args(["--left-index", "orders.idx"])
+        .assert()
+        .failure()
+        .stderr(contains("require both --left-index and --right-index"));
+
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .current_dir(dir)
+        .arg("join")
+        .args(base)
+        .args([
+            "--left-index",
+            "customers.idx",
+            "--right-index",
+            "customers.idx",
+        ])
+        .assert()
+        .failure()
+        .stderr(contains("left index has no variant"));
+}
+
+fn write_reconciliation_inputs(dir: &Path) -> (String, String) {
+    let ledger = dir.join("ledger.csv");
+    let bank = dir.join("bank.csv");
+    fs::write(
+        &ledger,
+        "txn,amount,status\nT1,10.00,open\nT2,25.50,open\nT3,7.25,closed\n",
+    )
+    .expect("write ledger");
+    fs::write(
+        &bank,
+        "ref,amount,status\nT1,10.00,cleared\nT2,25.50,pending\nT2,25.55,cleared\nT9,1.00,cleared\n",
+    )
+    .expect("write bank");
+    (
+        ledger.to_str().unwrap().to_string(),
+        bank.to_str().unwrap().to_string(),
+    )
+}
+
+#[test]
+fn join_semi_and_anti_return_left_rows_by_match() {
+    let temp = tempdir().expect("tempdir");
+    let (ledger, bank) = write_reconciliation_inputs(temp.path());
+    let base = [
+        "--left",
+        ledger.as_str(),
+        "--right",
+        bank.as_str(),
+        "--left-key",
+        "txn",
+        "--right-key",
+        "ref",
+    ];
+
+    let semi = join_stdout(&[&base[..], &["--type", "semi"]].concat(), "");
+    assert_eq!(semi[0], vec!["txn", "amount", "status"]);
+    // T2 has two bank rows but is emitted once.
+    assert_eq!(column(&semi[1..], 0), vec!["T1", "T2"]);
+
+    let anti = join_stdout(&[&base[..], &["--type", "anti"]].concat(), "");
+    assert_eq!(anti[0], vec!["txn", "amount", "status"]);
+    assert_eq!(column(&anti[1..], 0), vec!["T3"]);
+}
+
+#[test]
+fn join_cross_pairs_every_row_without_keys() {
+    let temp = tempdir().expect("tempdir");
+    let (ledger, bank) = write_reconciliation_inputs(temp.path());
+    let rows = join_stdout(
+        &["--left", &ledger, "--right", &bank, "--type", "cross"],
+        "",
+    );
+    assert_eq!(
+        rows[0],
+        vec![
+            "txn",
+            "amount",
+            "status",
+            "ref",
+            "right_amount_1",
+            "right_status_1"
+        ]
+    );
+    assert_eq!(rows.len(), 1 + 3 * 4);
+
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args([
+            "join",
+            "--left",
+            &ledger,
+            "--right",
+            &bank,
+            "--type",
+            "cross",
+            "--left-key",
+            "txn",
+        ])
+        .assert()
+        .failure()
+        .stderr(contains("does not take --left-key"));
+}
+
+#[test]
+fn join_right_match_policies_and_column_prefixes() {
+    let temp = tempdir().expect("tempdir");
+    let (ledger, bank) = write_reconciliation_inputs(temp.path());
+    let base = [
+        "--left",
+        ledger.as_str(),
+        "--right",
+        bank.as_str(),
+        "--left-key",
+        "txn",
+        "--right-key",
+        "ref",
+        "--left-prefix",
+        "ledger_",
+        "--right-prefix",
+        "bank_",
+    ];
+
+    let all = join_stdout(&base, "");
+    assert_eq!(
+        all[0],
+        vec![
+            "txn",
+            "ledger_amount",
+            "ledger_status",
+            "bank_amount",
+            "bank_status"
+        ]
+    );
+    assert_eq!(column(&all[1..], 3), vec!["10.00", "25.50", "25.55"]);
+
+    let first = join_stdout(&[&base[..], &["--right-match", "first"]].concat(), "");
+    assert_eq!(column(&first[1..], 3), vec!["10.00", "25.50"]);
+
+    let last = join_stdout(&[&base[..], &["--right-match", "last"]].concat(), "");
+    assert_eq!(column(&last[1..], 3), vec!["10.00", "25.55"]);
+
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .arg("join")
+        .args(base)
+        .args(["--right-match", "error"])
+        .assert()
+        .failure()
+        .stderr(contains("Right row 4 repeats join key (T2)"));
+}
@@ -3,6 +3,7 @@ use std::{fs, path::PathBuf};
 use assert_cmd::Command;
 use csv_managed::schema::{ColumnType, Schema};
 use encoding_rs::WINDOWS_1252;
+use predicates::str::contains;
 use tempfile::tempdir;
 
 fn fixture_path(name: &str) -> PathBuf {
@@ -97,3 +98,46 @@ fn probe_honors_input_encoding() {
     assert_eq!(schema.columns.len(), 2);
     assert_eq!(schema.columns[1].name, "name");
 }
+
+#[test]
+fn probe_seed_samples_rows_from_the_whole_file() {
+    let dir = tempdir().expect("temp dir");
+    let input_path = dir.path().join("ordered.csv");
+    // Time-ordered extract: the first rows are all numeric, later codes are text.
+    let mut content = String::from("id,code\n");
+    for id in 0..1_000 {
+        if id < 50 {
+            content.push_str(&format!("{id},{id}\n"));
+        } else {
+            content.push_str(&format!("{id},C-{id}\n"));
+        }
+    }
+    fs::write(&input_path, content).expect("write input");
+
+    let infer = |extra: &[&str]| {
+        let schema_path = dir.path().join("ordered-schema.yml");
+        Command::cargo_bin("csv-managed")
+            .expect("binary exists")
+            .args(["schema", "infer", "-i"])
+            .arg(&input_path)
+            .arg("-o")
+            .arg(&schema_path)
+            .args(["--sample-rows", "20"])
+            .args(extra)
+            .assert()
+            .success();
+        let schema = Schema::load(&schema_path).expect("load inferred schema");
+        schema.columns[1].datatype.clone()
+    };
+    assert_eq!(infer(&[]), ColumnType::Integer);
+    assert_eq!(infer(&["--seed", "7"]), ColumnType::String);
+
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["schema", "probe", "-i"])
+        .arg(&input_path)
+        .args(["--sample-rows", "0", "--seed", "7"])
+        .assert()
+        .failure()
+        .stderr(contains("--seed requires --sample-rows"));
+}
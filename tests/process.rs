@@ -303,6 +303,9 @@ fn create_boolean_subset(
                 rename: None,
                 value_replacements: Vec::new(),
                 datatype_mappings: Vec::new(),
+                lookup: None,
+                start: None,
+                width: None,
             },
             ColumnMeta {
                 name: GOALS_COL.to_string(),
@@ -310,6 +313,9 @@ fn create_boolean_subset(
                 rename: None,
                 value_replacements: Vec::new(),
                 datatype_mappings: Vec::new(),
+                lookup: None,
+                start: None,
+                width: None,
             },
             ColumnMeta {
                 name: BOOLEAN_COL.to_string(),
@@ -317,10 +323,14 @@ fn create_boolean_subset(
                 rename: None,
                 value_replacements: Vec::new(),
                 datatype_mappings: Vec::new(),
+                lookup: None,
+                start: None,
+                width: None,
             },
         ],
         schema_version: None,
         has_headers: true,
+        dialect: None,
     };
     schema.save(&schema_path).expect("write boolean schema");
 
@@ -1521,3 +1531,2048 @@ fn process_derives_using_positional_aliases() {
         );
     }
 }
+
+#[test]
+fn process_memory_limit_spills_and_matches_in_memory_sort() {
+    let temp = tempdir().expect("tempdir");
+    let input = primary_dataset();
+    let schema_path = create_schema(&temp, &input);
+    let in_memory = temp.path().join("in_memory.csv");
+    let spilled = temp.path().join("spilled.csv");
+
+    for (output, extra) in [
+        (&in_memory, Vec::<&str>::new()),
+        (&spilled, vec!["--memory-limit", "16K"]),
+    ] {
+        Command::cargo_bin("csv-managed")
+            .expect("binary exists")
+            .args([
+                "process",
+                "-i",
+                input.to_str().unwrap(),
+                "-o",
+                output.to_str().unwrap(),
+                "--schema",
+                schema_path.to_str().unwrap(),
+                "--sort",
+                "Squad:asc",
+                "--sort",
+                &format!("{GOALS_COL}:desc"),
+            ])
+            .args(&extra)
+            .assert()
+            .success();
+    }
+
+    let (expected_headers, expected_rows) = read_csv(&in_memory);
+    let (actual_headers, actual_rows) = read_csv(&spilled);
+    assert_eq!(actual_headers, expected_headers);
+    assert_eq!(actual_rows.len(), count_rows(&input));
+    assert_eq!(
+        actual_rows, expected_rows,
+        "external sort must reproduce the in-memory ordering, including ties"
+    );
+}
+
+#[test]
+fn process_sort_with_limit_matches_full_sort_prefix() {
+    let temp = tempdir().expect("tempdir");
+    let input = primary_dataset();
+    let schema_path = create_schema(&temp, &input);
+    let full = temp.path().join("full.csv");
+    let top = temp.path().join("top.csv");
+
+    for (output, extra) in [(&full, Vec::<&str>::new()), (&top, vec!["--limit", "40"])] {
+        Command::cargo_bin("csv-managed")
+            .expect("binary exists")
+            .args([
+                "process",
+                "-i",
+                input.to_str().unwrap(),
+                "-o",
+                output.to_str().unwrap(),
+                "--schema",
+                schema_path.to_str().unwrap(),
+                "--sort",
+                &format!("{GOALS_COL}:desc"),
+            ])
+            .args(&extra)
+            .assert()
+            .success();
+    }
+
+    let (expected_headers, expected_rows) = read_csv(&full);
+    let (actual_headers, actual_rows) = read_csv(&top);
+    assert_eq!(actual_headers, expected_headers);
+    assert_eq!(actual_rows.len(), 40);
+    assert_eq!(
+        actual_rows,
+        expected_rows[..40].to_vec(),
+        "top-N selection must match the head of a full sort, including ties"
+    );
+}
+
+#[test]
+fn process_threads_preserve_single_threaded_output() {
+    let temp = tempdir().expect("tempdir");
+    let input = temp.path().join("ledger.csv");
+    let mut writer = WriterBuilder::new()
+        .from_path(&input)
+        .expect("create input");
+    writer
+        .write_record(["id", "region", "amount"])
+        .expect("write headers");
+    for id in 0..20_000 {
+        let region = ["north", "south", "east"][id % 3];
+        let amount = (id * 7919) % 1_000;
+        writer
+            .write_record([id.to_string(), region.to_string(), amount.to_string()])
+            .expect("write row");
+    }
+    writer.flush().expect("flush input");
+    let schema_path = create_schema(&temp, &input);
+
+    let scenarios: [&[&str]; 3] = [
+        &["--limit", "12000"],
+        &["--sort", "amount:desc", "--sort", "region:asc"],
+        &["--sort", "region:asc", "--limit", "9000"],
+    ];
+    for (scenario, extra) in scenarios.iter().enumerate() {
+        let mut outputs = Vec::new();
+        for threads in ["1", "4"] {
+            let output = temp.path().join(format!("out-{scenario}-{threads}.csv"));
+            Command::cargo_bin("csv-managed")
+                .expect("binary exists")
+                .args([
+                    "process",
+                    "-i",
+                    input.to_str().unwrap(),
+                    "-o",
+                    output.to_str().unwrap(),
+                    "--schema",
+                    schema_path.to_str().unwrap(),
+                    "--filter",
+                    "amount>=100",
+                    "--derive",
+                    "double_amount=amount*2",
+                    "--row-numbers",
+                    "--threads",
+                    threads,
+                ])
+                .args(extra.iter())
+                .assert()
+                .success();
+            outputs.push(read_csv(&output));
+        }
+        assert!(!outputs[0].1.is_empty());
+        assert_eq!(
+            outputs[1], outputs[0],
+            "threaded output must match single-threaded output for {extra:?}"
+        );
+    }
+}
+
+fn run_process_rows(args: &[&str], output: &Path) -> Vec<StringRecord> {
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .arg("process")
+        .args(args)
+        .args(["-o", output.to_str().unwrap()])
+        .assert()
+        .success();
+    read_csv(output).1
+}
+
+fn column_values(rows: &[StringRecord], idx: usize) -> Vec<String> {
+    rows.iter().map(|row| row[idx].to_string()).collect()
+}
+
+#[test]
+fn process_distinct_on_compares_typed_values() {
+    let temp = tempdir().expect("tempdir");
+    let input = temp.path().join("balances.csv");
+    std::fs::write(
+        &input,
+        "id,account,balance\n1,A,1.0\n2,B,2.50\n3,A,1.00\n4,a,1.0\n5,B,2.5\n6,C,3\n6,C,3.00\n",
+    )
+    .expect("write input");
+    let schema_path = create_schema_with_overrides(
+        &temp,
+        &input,
+        &[(
+            "balance",
+            ColumnType::Decimal(DecimalSpec {
+                precision: 10,
+                scale: 2,
+            }),
+        )],
+    );
+    let output = temp.path().join("distinct.csv");
+    let base = [
+        "-i",
+        input.to_str().unwrap(),
+        "--schema",
+        schema_path.to_str().unwrap(),
+    ];
+
+    let whole_row = run_process_rows(&[&base[..], &["--distinct"]].concat(), &output);
+    assert_eq!(column_values(&whole_row, 0), ["1", "2", "3", "4", "5", "6"]);
+
+    let keyed = [&base[..], &["--distinct-on", "account,balance"]].concat();
+    let first = run_process_rows(&keyed, &output);
+    assert_eq!(column_values(&first, 0), ["1", "2", "4", "6"]);
+    assert_eq!(first.len(), 4);
+
+    let last = run_process_rows(&[&keyed[..], &["--keep", "last"]].concat(), &output);
+    assert_eq!(column_values(&last, 0), ["3", "4", "5", "6"]);
+    assert_eq!(last[3][2].to_string(), "3.00");
+}
+
+#[test]
+fn process_distinct_on_streams_through_matching_index() {
+    let temp = tempdir().expect("tempdir");
+    let input = primary_dataset();
+    let schema_path = create_schema(&temp, &input);
+    let index_path = temp.path().join("squad.idx");
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args([
+            "index",
+            "-i",
+            input.to_str().unwrap(),
+            "-o",
+            index_path.to_str().unwrap(),
+            "--schema",
+            schema_path.to_str().unwrap(),
+            "--spec",
+            "Squad:asc",
+        ])
+        .assert()
+        .success();
+    let output = temp.path().join("squads.csv");
+    let base = [
+        "-i",
+        input.to_str().unwrap(),
+        "--schema",
+        schema_path.to_str().unwrap(),
+        "--distinct-on",
+        "Squad",
+        "-C",
+        "Squad,Player",
+    ];
+
+    for keep in ["first", "last"] {
+        let with_keep = [&base[..], &["--keep", keep]].concat();
+        let sorted = run_process_rows(
+            &[&with_keep[..], &["--sort", "Squad:asc"]].concat(),
+            &output,
+        );
+        let indexed = run_process_rows(
+            &[&with_keep[..], &["--index", index_path.to_str().unwrap()]].concat(),
+            &output,
+        );
+        let squads = column_values(&sorted, 0);
+        let mut unique = squads.clone();
+        unique.dedup();
+        assert_eq!(squads, unique, "each squad should appear once");
+        assert!(sorted.len() > 50);
+        assert_eq!(
+            indexed, sorted,
+            "index streaming must keep the same occurrence as hashing (--keep {keep})"
+        );
+    }
+}
+
+#[test]
+fn process_memory_limit_rejects_unknown_unit() {
+    let input = primary_dataset();
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args([
+            "process",
+            "-i",
+            input.to_str().unwrap(),
+            "--sort",
+            PLAYER_COL,
+            "--memory-limit",
+            "12parsecs",
+        ])
+        .assert()
+        .failure()
+        .stderr(contains("Unknown memory limit unit"));
+}
+
+#[test]
+fn process_window_running_balance_matches_with_and_without_index() {
+    let temp = tempdir().expect("tempdir");
+    let input = temp.path().join("ledger.csv");
+    std::fs::write(
+        &input,
+        "account,posted_at,amount\n\
+         B,2024-01-03,7.25\n\
+         A,2024-01-02,-2.50\n\
+         A,2024-01-01,10.00\n\
+         B,2024-01-01,1.10\n\
+         A,2024-01-05,0.05\n",
+    )
+    .expect("write input");
+    let schema_path =
+        create_schema_with_overrides(&temp, &input, &[("amount", ColumnType::Currency)]);
+    let index_path = temp.path().join("ledger.idx");
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args([
+            "index",
+            "-i",
+            input.to_str().unwrap(),
+            "-o",
+            index_path.to_str().unwrap(),
+            "--schema",
+            schema_path.to_str().unwrap(),
+            "--spec",
+            "account:asc,posted_at:asc",
+        ])
+        .assert()
+        .success();
+    let output = temp.path().join("balances.csv");
+    let base = [
+        "-i",
+        input.to_str().unwrap(),
+        "--schema",
+        schema_path.to_str().unwrap(),
+        "--window",
+        "balance=running_sum(amount) partition by account order by posted_at",
+        "--window",
+        "previous=lag(amount, 1, 0) partition by account order by posted_at",
+        "--window",
+        "next_posted=lead(posted_at) partition by account order by posted_at",
+        "--derive",
+        "overdrawn=balance < 0",
+    ];
+
+    let in_memory = run_process_rows(&base, &output);
+    let (headers, _) = read_csv(&output);
+    assert_eq!(
+        headers.iter().collect::<Vec<_>>(),
+        [
+            "account",
+            "posted_at",
+            "amount",
+            "balance",
+            "previous",
+            "next_posted",
+            "overdrawn"
+        ]
+    );
+    let rendered = in_memory
+        .iter()
+        .map(|row| row.iter().collect::<Vec<_>>().join("|"))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rendered,
+        [
+            "A|2024-01-01|10.00|10.00|0|2024-01-02|false",
+            "A|2024-01-02|-2.50|7.50|10.00|2024-01-05|false",
+            "A|2024-01-05|0.05|7.55|-2.50||false",
+            "B|2024-01-01|1.10|1.10|0|2024-01-03|false",
+            "B|2024-01-03|7.25|8.35|1.10||false",
+        ]
+    );
+
+    let indexed = run_process_rows(
+        &[&base[..], &["--index", index_path.to_str().unwrap()]].concat(),
+        &output,
+    );
+    assert_eq!(indexed, in_memory);
+    let limited = run_process_rows(&[&base[..], &["--limit", "2"]].concat(), &output);
+    assert_eq!(limited, in_memory[..2]);
+
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["process"])
+        .args(base)
+        .args(["--sort", "posted_at"])
+        .assert()
+        .failure()
+        .stderr(contains("adjust --sort to match"));
+}
+
+#[test]
+fn process_split_by_column_rolls_over_row_limit() {
+    let temp = tempdir().expect("tempdir");
+    let input = temp.path().join("orders.csv");
+    std::fs::write(
+        &input,
+        "id,region,amount\n1,west,5\n2,east,7\n3,west,1\n4,west,9\n5,east,2\n6,north/east,3\n",
+    )
+    .expect("write input");
+    let pattern = temp.path().join("out/{region}/part-{n:03}.csv");
+
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args([
+            "process",
+            "-i",
+            input.to_str().unwrap(),
+            "-o",
+            pattern.to_str().unwrap(),
+            "--split-by",
+            "region",
+            "--max-rows-per-file",
+            "2",
+            "--sort",
+            "id:desc",
+        ])
+        .assert()
+        .success();
+
+    let part = |name: &str| {
+        let (headers, rows) = read_csv(&temp.path().join("out").join(name));
+        assert_eq!(
+            headers.iter().collect::<Vec<_>>(),
+            ["id", "region", "amount"]
+        );
+        column_values(&rows, 0)
+    };
+    assert_eq!(part("west/part-001.csv"), ["4", "3"]);
+    assert_eq!(part("west/part-002.csv"), ["1"]);
+    assert_eq!(part("east/part-001.csv"), ["5", "2"]);
+    assert_eq!(part("north_east/part-001.csv"), ["6"]);
+    assert!(!temp.path().join("out/east/part-002.csv").exists());
+
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args([
+            "process",
+            "-i",
+            input.to_str().unwrap(),
+            "-o",
+            temp.path().join("out/all.csv").to_str().unwrap(),
+            "--split-by",
+            "region",
+        ])
+        .assert()
+        .failure()
+        .stderr(contains("must include {region}"));
+}
+
+#[test]
+fn split_by_rejects_values_that_map_to_the_same_file() {
+    let temp = tempdir().expect("tempdir");
+    let input = temp.path().join("keys.csv");
+    std::fs::write(&input, "k,v\na/b,1\na_b,2\na/b,3\n").expect("write input");
+    let pattern = temp.path().join("out/part-{k}.csv");
+
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["process", "-i"])
+        .arg(&input)
+        .arg("-o")
+        .arg(&pattern)
+        .args(["--split-by", "k"])
+        .assert()
+        .failure()
+        .stderr(contains(
+            r#"Split values ["a/b"] and ["a_b"] both map to output file"#,
+        ));
+    let (_, rows) = read_csv(&temp.path().join("out/part-a_b.csv"));
+    assert_eq!(column_values(&rows, 1), ["1"]);
+}
+
+#[test]
+fn split_by_reopens_files_beyond_the_open_file_cap() {
+    let temp = tempdir().expect("tempdir");
+    let input = temp.path().join("events.csv");
+    // 200 partitions, more than are kept open, each written twice far apart.
+    let mut csv = String::from("customer,seq\n");
+    for seq in 0..400 {
+        csv.push_str(&format!("c{},{seq}\n", seq % 200));
+    }
+    std::fs::write(&input, csv).expect("write csv");
+
+    for pattern in ["out/{customer}.csv", "gz/{customer}.csv.gz"] {
+        Command::cargo_bin("csv-managed")
+            .expect("binary exists")
+            .args(["process", "-i"])
+            .arg(&input)
+            .arg("-o")
+            .arg(temp.path().join(pattern))
+            .args(["--split-by", "customer"])
+            .assert()
+            .success();
+    }
+
+    for customer in [0, 150, 199] {
+        let (headers, rows) = read_csv(&temp.path().join(format!("out/c{customer}.csv")));
+        assert_eq!(headers.iter().collect::<Vec<_>>(), ["customer", "seq"]);
+        assert_eq!(
+            column_values(&rows, 1),
+            [customer.to_string(), (customer + 200).to_string()]
+        );
+    }
+    // The reopened gzip part holds two members that read back as one file.
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["process", "--quote-style", "necessary", "-i"])
+        .arg(temp.path().join("gz/c7.csv.gz"))
+        .assert()
+        .success()
+        .stdout("customer,seq\nc7,7\nc7,207\n");
+}
+
+#[test]
+fn append_splits_output_by_file_size() {
+    let temp = tempdir().expect("tempdir");
+    let first = temp.path().join("first.csv");
+    let second = temp.path().join("second.csv");
+    std::fs::write(&first, "id,name\n1,alpha\n2,beta\n3,gamma\n").expect("write first");
+    std::fs::write(&second, "id,name\n4,delta\n5,epsilon\n").expect("write second");
+    let pattern = temp.path().join("chunk-{n}.tsv");
+
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args([
+            "append",
+            "-i",
+            first.to_str().unwrap(),
+            "-i",
+            second.to_str().unwrap(),
+            "-o",
+            pattern.to_str().unwrap(),
+            "--max-bytes-per-file",
+            "40",
+        ])
+        .assert()
+        .success();
+
+    let mut ids = Vec::new();
+    for n in 1..=3 {
+        let path = temp.path().join(format!("chunk-{n}.tsv"));
+        let contents = std::fs::read_to_string(&path).expect("read chunk");
+        assert!(contents.len() <= 40, "{path:?} exceeds the size limit");
+        assert!(contents.starts_with("\"id\"\t\"name\"\n"));
+        let (_, rows) = read_csv(&path);
+        ids.extend(column_values(&rows, 0));
+    }
+    assert!(!temp.path().join("chunk-4.tsv").exists());
+    assert_eq!(ids, ["1", "2", "3", "4", "5"]);
+}
+
+#[test]
+fn process_sample_is_seeded_and_drawn_after_filters() {
+    let temp = tempdir().expect("tempdir");
+    let input = temp.path().join("events.csv");
+    let mut writer = WriterBuilder::new()
+        .from_path(&input)
+        .expect("create input");
+    writer.write_record(["id", "kind"]).expect("write headers");
+    for id in 0..20_000 {
+        let kind = if id % 4 == 0 { "error" } else { "info" };
+        writer
+            .write_record([id.to_string(), kind.to_string()])
+            .expect("write row");
+    }
+    writer.flush().expect("flush input");
+    let schema_path = create_schema_with_overrides(&temp, &input, &[("id", ColumnType::Integer)]);
+    let output = temp.path().join("sample.csv");
+    let base = [
+        "-i",
+        input.to_str().unwrap(),
+        "--schema",
+        schema_path.to_str().unwrap(),
+        "--filter-expr",
+        "kind == \"error\"",
+        "--seed",
+        "2024",
+    ];
+
+    let reservoir = run_process_rows(&[&base[..], &["--sample", "50"]].concat(), &output);
+    assert_eq!(reservoir.len(), 50);
+    assert!(reservoir.iter().all(|row| &row[1] == "error"));
+    let ids = column_values(&reservoir, 0)
+        .iter()
+        .map(|id| id.parse::<usize>().expect("numeric id"))
+        .collect::<Vec<_>>();
+    assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+    assert!(
+        *ids.last().unwrap() > 10_000,
+        "sample should reach past the head"
+    );
+    let threaded = run_process_rows(
+        &[&base[..], &["--sample", "50", "--threads", "4"]].concat(),
+        &output,
+    );
+    assert_eq!(threaded, reservoir);
+    let sorted = run_process_rows(
+        &[&base[..], &["--sample", "50", "--sort", "id:desc"]].concat(),
+        &output,
+    );
+    let mut reversed = reservoir.clone();
+    reversed.reverse();
+    assert_eq!(sorted, reversed);
+
+    let fraction = run_process_rows(
+        &[&base[..], &["--sample-fraction", "0.1"]].concat(),
+        &output,
+    );
+    assert!(
+        (350..650).contains(&fraction.len()),
+        "kept {}",
+        fraction.len()
+    );
+    let threaded = run_process_rows(
+        &[&base[..], &["--sample-fraction", "0.1", "--threads", "4"]].concat(),
+        &output,
+    );
+    assert_eq!(threaded, fraction);
+}
+
+#[test]
+fn process_reads_glob_inputs_as_one_stream_with_source_column() {
+    let temp = tempdir().expect("tempdir");
+    let drops = temp.path().join("drops");
+    std::fs::create_dir(&drops).expect("create drops dir");
+    std::fs::write(drops.join("2026-10-02.csv"), "id,amount\n3,30\n1,5\n").expect("write drop");
+    std::fs::write(drops.join("2026-10-01.csv"), "id,amount\n2,20\n4,1\n").expect("write drop");
+    std::fs::write(drops.join("notes.txt"), "ignored").expect("write notes");
+    let schema_path = temp.path().join("drops-schema.yml");
+    std::fs::write(
+        &schema_path,
+        "columns:\n- name: id\n  datatype: Integer\n- name: amount\n  datatype: Integer\n",
+    )
+    .expect("write schema");
+    let pattern = drops.join("2026-10-*.csv");
+    let output = temp.path().join("combined.csv");
+
+    let rows = run_process_rows(
+        &[
+            "-i",
+            pattern.to_str().unwrap(),
+            "--schema",
+            schema_path.to_str().unwrap(),
+            "--source-column",
+            "file",
+            "--filter",
+            "amount >= 5",
+            "--sort",
+            "id:asc",
+        ],
+        &output,
+    );
+    let (headers, _) = read_csv(&output);
+    assert_eq!(headers.iter().collect::<Vec<_>>(), ["id", "amount", "file"]);
+    assert_eq!(column_values(&rows, 0), ["1", "2", "3"]);
+    let sources = column_values(&rows, 2);
+    assert!(sources[0].ends_with("2026-10-02.csv"));
+    assert!(sources[1].ends_with("2026-10-01.csv"));
+
+    std::fs::write(drops.join("2026-10-03.csv"), "id,total\n5,50\n").expect("write drop");
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args([
+            "process",
+            "-i",
+            pattern.to_str().unwrap(),
+            "--schema",
+            schema_path.to_str().unwrap(),
+        ])
+        .assert()
+        .failure()
+        .stderr(contains("2026-10-03.csv").and(contains("Header mismatch")));
+}
+
+#[test]
+fn process_enriches_rows_from_schema_declared_lookup() {
+    let temp = tempdir().expect("tempdir");
+    let lookups = temp.path().join("ref");
+    std::fs::create_dir(&lookups).expect("create ref dir");
+    std::fs::write(
+        lookups.join("countries.csv"),
+        "iso2,country_name,region\nDE,Germany,EMEA\nJP,Japan,APAC\nUS,United States,AMER\n",
+    )
+    .expect("write countries");
+    std::fs::write(
+        temp.path().join("sales.csv"),
+        "order_id,country_code,amount\n1,US,10\n2,de,20\n3,JP,30\n4,XX,40\n",
+    )
+    .expect("write sales");
+    let schema_path = temp.path().join("sales-schema.yml");
+    std::fs::write(
+        &schema_path,
+        "columns:\n\
+         - name: order_id\n  datatype: Integer\n\
+         - name: country_code\n  datatype: String\n  replace:\n  - from: de\n    to: DE\n  lookup:\n    table: ref/countries.csv\n    key: iso2\n    columns: [country_name, region]\n    defaults:\n      region: UNKNOWN\n\
+         - name: amount\n  datatype: Integer\n",
+    )
+    .expect("write schema");
+    let input = temp.path().join("sales.csv");
+    let output = temp.path().join("enriched.csv");
+
+    let rows = run_process_rows(
+        &[
+            "-i",
+            input.to_str().unwrap(),
+            "--schema",
+            schema_path.to_str().unwrap(),
+            "--on-miss",
+            "default",
+            "--filter",
+            "region != APAC",
+        ],
+        &output,
+    );
+    let (headers, _) = read_csv(&output);
+    assert_eq!(
+        headers.iter().collect::<Vec<_>>(),
+        [
+            "order_id",
+            "country_code",
+            "amount",
+            "country_name",
+            "region"
+        ]
+    );
+    assert_eq!(column_values(&rows, 0), ["1", "2", "4"]);
+    assert_eq!(column_values(&rows, 3), ["United States", "Germany", ""]);
+    assert_eq!(column_values(&rows, 4), ["AMER", "EMEA", "UNKNOWN"]);
+
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args([
+            "process",
+            "-i",
+            input.to_str().unwrap(),
+            "--schema",
+            schema_path.to_str().unwrap(),
+            "--on-miss",
+            "error",
+        ])
+        .assert()
+        .failure()
+        .stderr(contains("No match in lookup table").and(contains("country_code = 'XX'")));
+}
+
+#[test]
+fn process_writes_typed_json_lines_and_arrays() {
+    let temp = tempdir().expect("tempdir");
+    let input = temp.path().join("orders.csv");
+    std::fs::write(
+        &input,
+        "id,shipped,price,note\n1,yes,12.50,first\n2,no,3.10,\n",
+    )
+    .expect("write orders");
+    let schema_path = temp.path().join("orders-schema.yml");
+    std::fs::write(
+        &schema_path,
+        "columns:\n\
+         - name: id\n  datatype: Integer\n\
+         - name: shipped\n  datatype: Boolean\n\
+         - name: price\n  datatype: decimal(6,2)\n  name_mapping: unit_price\n\
+         - name: note\n  datatype: String\n",
+    )
+    .expect("write schema");
+
+    let run = |extra: &[&str]| {
+        let output = Command::cargo_bin("csv-managed")
+            .expect("binary exists")
+            .args([
+                "process",
+                "-i",
+                input.to_str().unwrap(),
+                "--schema",
+                schema_path.to_str().unwrap(),
+                "--derive",
+                "double=id*2",
+            ])
+            .args(extra)
+            .output()
+            .expect("run process");
+        assert!(output.status.success(), "{output:?}");
+        String::from_utf8(output.stdout).expect("utf-8 output")
+    };
+
+    let lines = run(&["--output-format", "jsonl"]);
+    assert_eq!(
+        lines,
+        "{\"id\":1,\"shipped\":true,\"unit_price\":\"12.50\",\"note\":\"first\",\"double\":2}\n\
+         {\"id\":2,\"shipped\":false,\"unit_price\":\"3.10\",\"note\":null,\"double\":4}\n"
+    );
+
+    let array = run(&["--output-format", "json", "--json-decimals", "number"]);
+    let parsed: serde_json::Value = serde_json::from_str(&array).expect("valid JSON array");
+    assert_eq!(parsed[0]["unit_price"], serde_json::json!(12.5));
+    assert_eq!(parsed[1]["shipped"], serde_json::json!(false));
+    assert_eq!(parsed.as_array().map(Vec::len), Some(2));
+
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args([
+            "process",
+            "-i",
+            input.to_str().unwrap(),
+            "--output-format",
+            "json",
+            "--preview",
+        ])
+        .assert()
+        .failure()
+        .stderr(contains("cannot be combined with --preview"));
+}
+
+fn read_parquet(path: &Path) -> (Vec<String>, Vec<String>) {
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    let reader =
+        SerializedFileReader::new(std::fs::File::open(path).expect("open parquet")).expect("read");
+    let descriptor = reader.metadata().file_metadata().schema_descr_ptr();
+    let types = descriptor
+        .columns()
+        .iter()
+        .map(|column| {
+            format!(
+                "{}:{}:{:?}",
+                column.name(),
+                column.physical_type(),
+                column.logical_type()
+            )
+        })
+        .collect();
+    let rows = reader
+        .get_row_iter(None)
+        .expect("row iterator")
+        .map(|row| row.expect("row").to_string())
+        .collect();
+    (types, rows)
+}
+
+#[test]
+fn process_writes_parquet_typed_from_schema() {
+    let temp = tempdir().expect("tempdir");
+    let input = temp.path().join("events.csv");
+    std::fs::write(
+        &input,
+        "id,day,at,amount,price,active,ref\n\
+         1,2024-03-01,2024-03-01 08:30:00,12.5,$3.10,yes,6f1c2a9e-8d4b-4c1e-9a55-0c7e2b1d3f40\n\
+         2,2024-03-02,,7,$1.00,no,\n",
+    )
+    .expect("write events");
+    let schema_path = temp.path().join("events-schema.yml");
+    std::fs::write(
+        &schema_path,
+        "columns:\n\
+         - name: id\n  datatype: Integer\n\
+         - name: day\n  datatype: Date\n\
+         - name: at\n  datatype: DateTime\n\
+         - name: amount\n  datatype: decimal(10,2)\n\
+         - name: price\n  datatype: Currency\n\
+         - name: active\n  datatype: Boolean\n\
+         - name: ref\n  datatype: Guid\n",
+    )
+    .expect("write schema");
+    let output = temp.path().join("events.parquet");
+
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args([
+            "process",
+            "-i",
+            input.to_str().unwrap(),
+            "--schema",
+            schema_path.to_str().unwrap(),
+            "--derive",
+            "label=\"evt\"",
+            "--window",
+            "running_amount=running_sum(amount)",
+            "--window",
+            "running_price=running_sum(price)",
+            "--output-format",
+            "parquet",
+            "--row-group-size",
+            "1",
+            "--parquet-compression",
+            "zstd",
+            "-o",
+            output.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+
+    let (types, rows) = read_parquet(&output);
+    assert_eq!(
+        types,
+        [
+            "id:INT64:None",
+            "day:INT32:Some(Date)",
+            "at:INT64:Some(Timestamp { is_adjusted_to_u_t_c: false, unit: MICROS(MicroSeconds) })",
+            "amount:INT64:Some(Decimal { scale: 2, precision: 10 })",
+            "price:INT64:Some(Decimal { scale: 4, precision: 18 })",
+            "active:BOOLEAN:None",
+            "ref:FIXED_LEN_BYTE_ARRAY:Some(Uuid)",
+            "running_amount:FIXED_LEN_BYTE_ARRAY:Some(Decimal { scale: 2, precision: 28 })",
+            "running_price:INT64:Some(Decimal { scale: 4, precision: 18 })",
+            "label:BYTE_ARRAY:Some(String)",
+        ]
+    );
+    assert_eq!(rows.len(), 2);
+    assert!(rows[0].contains("amount: 12.50"), "{}", rows[0]);
+    assert!(rows[0].contains("price: 3.1000"), "{}", rows[0]);
+    assert!(rows[1].contains("at: null"), "{}", rows[1]);
+    assert!(rows[1].contains("ref: null"), "{}", rows[1]);
+    assert!(rows[1].contains("active: false"), "{}", rows[1]);
+    assert!(rows[1].contains("running_amount: 19.50"), "{}", rows[1]);
+    assert!(rows[1].contains("running_price: 4.1000"), "{}", rows[1]);
+
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args([
+            "process",
+            "-i",
+            input.to_str().unwrap(),
+            "--output-format",
+            "parquet",
+        ])
+        .assert()
+        .failure()
+        .stderr(contains("Parquet output requires --output"));
+}
+
+#[test]
+fn append_writes_parquet_across_inputs() {
+    let temp = tempdir().expect("tempdir");
+    let first = temp.path().join("a.csv");
+    let second = temp.path().join("b.csv");
+    std::fs::write(&first, "id,name\n1,alpha\n").expect("write a");
+    std::fs::write(&second, "id,name\n2,beta\n3,\n").expect("write b");
+    let schema_path = temp.path().join("ab-schema.yml");
+    std::fs::write(
+        &schema_path,
+        "columns:\n\
+         - name: id\n  datatype: Integer\n\
+         - name: name\n  datatype: String\n",
+    )
+    .expect("write schema");
+    let output = temp.path().join("ab.parquet");
+
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args([
+            "append",
+            "-i",
+            first.to_str().unwrap(),
+            "-i",
+            second.to_str().unwrap(),
+            "--schema",
+            schema_path.to_str().unwrap(),
+            "--output-format",
+            "parquet",
+            "-o",
+            output.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+
+    let (types, rows) = read_parquet(&output);
+    assert_eq!(types, ["id:INT64:None", "name:BYTE_ARRAY:Some(String)"]);
+    assert_eq!(
+        rows,
+        [
+            "{id: 1, name: \"alpha\"}",
+            "{id: 2, name: \"beta\"}",
+            "{id: 3, name: null}"
+        ]
+    );
+}
+
+#[test]
+fn parquet_output_keeps_string_padding_and_typed_values() {
+    let temp = tempdir().expect("tempdir");
+    let input = temp.path().join("padded.csv");
+    std::fs::write(&input, "id,name,active\n1,  Ann ,yes\n2,   ,no\n").expect("write input");
+    let schema_path = temp.path().join("padded-schema.yml");
+    std::fs::write(
+        &schema_path,
+        "columns:\n\
+         - name: id\n  datatype: Integer\n\
+         - name: name\n  datatype: String\n\
+         - name: active\n  datatype: Boolean\n",
+    )
+    .expect("write schema");
+
+    // `--boolean-format` only affects rendered text; Parquet keeps the parsed values.
+    let output = temp.path().join("process.parquet");
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["process", "-i"])
+        .arg(&input)
+        .arg("--schema")
+        .arg(&schema_path)
+        .args([
+            "--row-numbers",
+            "--boolean-format",
+            "one-zero",
+            "--output-format",
+            "parquet",
+            "-o",
+        ])
+        .arg(&output)
+        .assert()
+        .success();
+    assert_eq!(
+        read_parquet(&output).1,
+        [
+            "{row_number: 1, id: 1, name: \"  Ann \", active: true}",
+            "{row_number: 2, id: 2, name: \"   \", active: false}",
+        ]
+    );
+
+    let output = temp.path().join("append.parquet");
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["append", "-i"])
+        .arg(&input)
+        .arg("--schema")
+        .arg(&schema_path)
+        .args(["--output-format", "parquet", "-o"])
+        .arg(&output)
+        .assert()
+        .success();
+    assert_eq!(
+        read_parquet(&output).1,
+        [
+            "{id: 1, name: \"  Ann \", active: true}",
+            "{id: 2, name: \"   \", active: false}",
+        ]
+    );
+}
+
+#[test]
+fn parquet_inputs_read_through_process_stats_and_schema() {
+    let temp = tempdir().expect("tempdir");
+    let input = temp.path().join("events.csv");
+    std::fs::write(
+        &input,
+        "id,day,at,amount,active,ref\n\
+         1,2024-03-01,2024-03-01 08:30:00,12.5,yes,6f1c2a9e-8d4b-4c1e-9a55-0c7e2b1d3f40\n\
+         2,2024-03-02,,7,no,\n\
+         3,2024-03-03,2024-03-03 17:05:30,20.25,yes,\n",
+    )
+    .expect("write events");
+    let schema_path = temp.path().join("events-schema.yml");
+    std::fs::write(
+        &schema_path,
+        "columns:\n\
+         - name: id\n  datatype: Integer\n\
+         - name: day\n  datatype: Date\n\
+         - name: at\n  datatype: DateTime\n\
+         - name: amount\n  datatype: decimal(10,2)\n\
+         - name: active\n  datatype: Boolean\n\
+         - name: ref\n  datatype: Guid\n",
+    )
+    .expect("write schema");
+    let parquet = temp.path().join("events.parquet");
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args([
+            "process",
+            "-i",
+            input.to_str().unwrap(),
+            "--schema",
+            schema_path.to_str().unwrap(),
+            "--output-format",
+            "parquet",
+            "--row-group-size",
+            "2",
+            "-o",
+            parquet.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+
+    let output = temp.path().join("filtered.csv");
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args([
+            "process",
+            "-i",
+            parquet.to_str().unwrap(),
+            "--filter",
+            "amount >= 10",
+            "--sort",
+            "day:desc",
+            "-o",
+            output.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+    let (headers, rows) = read_csv(&output);
+    assert_eq!(
+        headers.iter().collect::<Vec<_>>(),
+        ["id", "day", "at", "amount", "active", "ref"]
+    );
+    assert_eq!(rows.len(), 2);
+    assert_eq!(
+        rows[0].iter().collect::<Vec<_>>(),
+        [
+            "3",
+            "2024-03-03",
+            "2024-03-03 17:05:30",
+            "20.25",
+            "true",
+            ""
+        ]
+    );
+    assert_eq!(
+        rows[1].iter().collect::<Vec<_>>(),
+        [
+            "1",
+            "2024-03-01",
+            "2024-03-01 08:30:00",
+            "12.50",
+            "true",
+            "6f1c2a9e-8d4b-4c1e-9a55-0c7e2b1d3f40"
+        ]
+    );
+
+    let inferred = temp.path().join("inferred-schema.yml");
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args([
+            "schema",
+            "infer",
+            "-i",
+            parquet.to_str().unwrap(),
+            "-o",
+            inferred.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+    let schema = Schema::load(&inferred).expect("load inferred schema");
+    let types = schema
+        .columns
+        .iter()
+        .map(|column| column.datatype.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        types,
+        [
+            ColumnType::Integer,
+            ColumnType::Date,
+            ColumnType::DateTime,
+            ColumnType::Decimal(DecimalSpec::new(10, 2).expect("decimal spec")),
+            ColumnType::Boolean,
+            ColumnType::Guid,
+        ]
+    );
+
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args([
+            "schema",
+            "verify",
+            "-m",
+            inferred.to_str().unwrap(),
+            "-i",
+            parquet.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["stats", "-i", parquet.to_str().unwrap(), "-C", "amount"])
+        .assert()
+        .success()
+        .stdout(contains("amount").and(contains("20.25")));
+
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args([
+            "process",
+            "-i",
+            parquet.to_str().unwrap(),
+            "--index",
+            parquet.to_str().unwrap(),
+        ])
+        .assert()
+        .failure()
+        .stderr(contains("--index cannot be used with Parquet input"));
+}
+
+#[test]
+fn json_inputs_flatten_nested_fields_for_infer_and_process() {
+    let temp = tempdir().expect("tempdir");
+    let input = temp.path().join("orders.jsonl");
+    std::fs::write(
+        &input,
+        r#"{"id": 1, "placed": "2024-03-01", "total": 12.5, "address": {"city": "Oslo", "zip": "0150"}, "tags": ["new", "gift"]}
+{"id": 2, "placed": "2024-03-02", "total": 7, "address": {"city": "Bergen"}, "tags": [], "note": "leave at door"}
+
+{"id": 3, "placed": "2024-03-03", "total": null, "address": {"city": null, "zip": "5003"}, "tags": ["gift"]}
+"#,
+    )
+    .expect("write orders");
+
+    let schema_path = temp.path().join("orders-schema.yml");
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args([
+            "schema",
+            "infer",
+            "-i",
+            input.to_str().unwrap(),
+            "-o",
+            schema_path.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+    let schema = Schema::load(&schema_path).expect("load inferred schema");
+    let columns = schema
+        .columns
+        .iter()
+        .map(|column| (column.name.as_str(), column.datatype.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(columns[0], ("id", ColumnType::Integer));
+    assert_eq!(columns[1], ("placed", ColumnType::Date));
+    assert_eq!(columns[3].0, "address.city");
+    assert_eq!(columns[4], ("address.zip", ColumnType::String));
+    assert_eq!(columns[5], ("tags", ColumnType::String));
+    assert_eq!(columns[6], ("note", ColumnType::String));
+
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args([
+            "schema",
+            "verify",
+            "-m",
+            schema_path.to_str().unwrap(),
+            "-i",
+            input.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+
+    let output = temp.path().join("orders.csv");
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args([
+            "process",
+            "-i",
+            input.to_str().unwrap(),
+            "--schema",
+            schema_path.to_str().unwrap(),
+            "--filter",
+            "id >= 2",
+            "-o",
+            output.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+    let (headers, rows) = read_csv(&output);
+    assert_eq!(
+        headers.iter().collect::<Vec<_>>(),
+        [
+            "id",
+            "placed",
+            "total",
+            "address.city",
+            "address.zip",
+            "tags",
+            "note"
+        ]
+    );
+    assert_eq!(
+        rows[0].iter().collect::<Vec<_>>(),
+        ["2", "2024-03-02", "7", "Bergen", "", "", "leave at door"]
+    );
+    assert_eq!(
+        rows[1].iter().collect::<Vec<_>>(),
+        ["3", "2024-03-03", "", "", "5003", "gift", ""]
+    );
+
+    let exploded = temp.path().join("exploded.csv");
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args([
+            "process",
+            "-i",
+            input.to_str().unwrap(),
+            "--json-arrays",
+            "explode",
+            "--json-separator",
+            "_",
+            "-C",
+            "id,address_city,tags",
+            "-o",
+            exploded.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+    let (headers, rows) = read_csv(&exploded);
+    assert_eq!(
+        headers.iter().collect::<Vec<_>>(),
+        ["id", "address_city", "tags"]
+    );
+    let rows = rows
+        .iter()
+        .map(|row| row.iter().collect::<Vec<_>>().join(","))
+        .collect::<Vec<_>>();
+    assert_eq!(rows, ["1,Oslo,new", "1,Oslo,gift", "2,Bergen,", "3,,gift"]);
+}
+
+#[test]
+fn json_arrays_are_read_element_by_element_and_key_collisions_fail() {
+    let temp = tempdir().expect("tempdir");
+    let input = temp.path().join("batches.json");
+    std::fs::write(
+        &input,
+        "[\n  {\"id\": 1, \"a\": {\"b\": \"x\"}},\n  {\"id\": 2}\n]\n{\"id\": 3}\n[ ]\n[{\"id\": 4, \"a\": {}}]\n",
+    )
+    .expect("write batches");
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["process", "--quote-style", "necessary", "-i"])
+        .arg(&input)
+        .assert()
+        .success()
+        .stdout("id,a.b,a\n1,x,\n2,,\n3,,\n4,,\n");
+
+    for (name, content, message) in [
+        (
+            "collision.json",
+            r#"[{"id": 1}, {"a.b": 1, "a": {"b": 2}}]"#,
+            "JSON object 2 has more than one value for column 'a.b'",
+        ),
+        (
+            "trailing.json",
+            r#"[{"id": 1},]"#,
+            "expected a value after ','",
+        ),
+        ("unterminated.json", r#"[{"id": 1}"#, "expected ',' or ']'"),
+    ] {
+        let path = temp.path().join(name);
+        std::fs::write(&path, content).expect("write json");
+        Command::cargo_bin("csv-managed")
+            .expect("binary exists")
+            .args(["process", "-i"])
+            .arg(&path)
+            .assert()
+            .failure()
+            .stderr(contains(message));
+    }
+}
+
+fn write_orders_workbook(path: &Path) {
+    use rust_xlsxwriter::{ExcelDateTime, Format, Workbook};
+
+    let mut workbook = Workbook::new();
+    let notes = workbook.add_worksheet();
+    notes.set_name("Notes").expect("name sheet");
+    notes.write_string(0, 0, "exported weekly").expect("write");
+
+    let date = Format::new().set_num_format("yyyy-mm-dd");
+    let datetime = Format::new().set_num_format("yyyy-mm-dd hh:mm:ss");
+    let orders = workbook.add_worksheet();
+    orders.set_name("Orders").expect("name sheet");
+    orders
+        .write_string(0, 0, "Weekly orders export")
+        .expect("write");
+    for (col, header) in ["sku", "placed", "at", "qty", "price", "paid"]
+        .iter()
+        .enumerate()
+    {
+        orders.write_string(2, col as u16, *header).expect("write");
+    }
+    let rows = [
+        ("00123", (2024, 3, 1), (8, 30, 0), 3.0, 12.5, true),
+        ("04567", (2024, 3, 2), (17, 5, 30), 1.0, 0.1 + 0.2, false),
+    ];
+    for (idx, (sku, (y, m, d), (hh, mm, ss), qty, price, paid)) in rows.into_iter().enumerate() {
+        let row = 3 + idx as u32;
+        let day = ExcelDateTime::from_ymd(y, m, d).expect("date");
+        let at = ExcelDateTime::from_ymd(y, m, d)
+            .and_then(|day| day.and_hms(hh, mm, ss))
+            .expect("datetime");
+        orders.write_string(row, 0, sku).expect("write");
+        orders
+            .write_datetime_with_format(row, 1, &day, &date)
+            .expect("write");
+        orders
+            .write_datetime_with_format(row, 2, &at, &datetime)
+            .expect("write");
+        orders.write_number(row, 3, qty).expect("write");
+        orders.write_number(row, 4, price).expect("write");
+        orders.write_boolean(row, 5, paid).expect("write");
+    }
+    workbook.save(path).expect("save workbook");
+}
+
+#[test]
+fn excel_inputs_read_a_sheet_range_with_typed_cells() {
+    let temp = tempdir().expect("tempdir");
+    let book = temp.path().join("orders.xlsx");
+    write_orders_workbook(&book);
+    let book_arg = book.to_str().unwrap();
+    let sheet_args = ["--sheet", "Orders", "--range", "A3:F"];
+
+    let schema_path = temp.path().join("orders-schema.yml");
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["schema", "infer", "-i", book_arg, "-o"])
+        .arg(&schema_path)
+        .args(sheet_args)
+        .assert()
+        .success();
+    let schema = Schema::load(&schema_path).expect("load inferred schema");
+    let columns = schema
+        .columns
+        .iter()
+        .map(|column| (column.name.as_str(), column.datatype.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(columns[0], ("sku", ColumnType::String));
+    assert_eq!(columns[1], ("placed", ColumnType::Date));
+    assert_eq!(columns[2], ("at", ColumnType::DateTime));
+    assert_eq!(columns[3], ("qty", ColumnType::Integer));
+    assert_eq!(columns[5], ("paid", ColumnType::Boolean));
+
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["schema", "verify", "-m"])
+        .arg(&schema_path)
+        .args(["-i", book_arg])
+        .args(sheet_args)
+        .assert()
+        .success();
+
+    let output = temp.path().join("orders.csv");
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["process", "-i", book_arg, "--schema"])
+        .arg(&schema_path)
+        .args(sheet_args)
+        .args(["-o"])
+        .arg(&output)
+        .assert()
+        .success();
+    let (headers, rows) = read_csv(&output);
+    assert_eq!(
+        headers.iter().collect::<Vec<_>>(),
+        ["sku", "placed", "at", "qty", "price", "paid"]
+    );
+    assert_eq!(
+        rows[0].iter().collect::<Vec<_>>(),
+        [
+            "00123",
+            "2024-03-01",
+            "2024-03-01 08:30:00",
+            "3",
+            "12.5",
+            "true"
+        ]
+    );
+    assert_eq!(
+        rows[1].iter().collect::<Vec<_>>(),
+        [
+            "04567",
+            "2024-03-02",
+            "2024-03-02 17:05:30",
+            "1",
+            "0.3",
+            "false"
+        ]
+    );
+
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["process", "-i", book_arg, "--sheet", "Invoices"])
+        .assert()
+        .failure()
+        .stderr(contains("Worksheet 'Invoices' not found").and(contains("Notes, Orders")));
+}
+
+#[test]
+fn fixed_width_inputs_are_sliced_by_schema_positions() {
+    let temp = tempdir().expect("tempdir");
+    let input = temp.path().join("orders.txt");
+    std::fs::write(
+        &input,
+        "sku   placed         qty  price\n\
+         00123 2024-03-01       3  12.50\n\
+         04567 2024-03-02      10   0.30\n\
+         \n\
+         00089 2024-03-05       1 104.00\n",
+    )
+    .expect("write fixed-width input");
+
+    let schema_path = temp.path().join("orders-schema.yml");
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["schema", "infer", "--fixed-width", "-i"])
+        .arg(&input)
+        .arg("-o")
+        .arg(&schema_path)
+        .assert()
+        .success();
+    let schema = Schema::load(&schema_path).expect("load inferred schema");
+    assert!(schema.has_headers);
+    let columns = schema
+        .columns
+        .iter()
+        .map(|column| {
+            (
+                column.name.as_str(),
+                column.datatype.clone(),
+                column.start,
+                column.width,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        columns,
+        [
+            ("sku", ColumnType::String, Some(1), Some(6)),
+            ("placed", ColumnType::Date, Some(7), Some(15)),
+            ("qty", ColumnType::Integer, Some(22), Some(4)),
+            (
+                "price",
+                ColumnType::Decimal(DecimalSpec {
+                    precision: 5,
+                    scale: 2
+                }),
+                Some(26),
+                Some(6)
+            ),
+        ]
+    );
+
+    // Stdin can only be read once, so its types come from the lines sampled for the layout.
+    let stdin_schema_path = temp.path().join("stdin-schema.yml");
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["schema", "infer", "--fixed-width", "-i", "-", "-o"])
+        .arg(&stdin_schema_path)
+        .write_stdin(std::fs::read(&input).expect("read input"))
+        .assert()
+        .success();
+    let stdin_schema = Schema::load(&stdin_schema_path).expect("load stdin schema");
+    assert_eq!(
+        stdin_schema
+            .columns
+            .iter()
+            .map(|column| column.datatype.clone())
+            .collect::<Vec<_>>(),
+        schema
+            .columns
+            .iter()
+            .map(|column| column.datatype.clone())
+            .collect::<Vec<_>>()
+    );
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["schema", "probe", "--fixed-width", "--seed", "1", "-i", "-"])
+        .write_stdin(std::fs::read(&input).expect("read input"))
+        .assert()
+        .failure()
+        .stderr(contains("stdin, which can only be read once"));
+
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["schema", "verify", "-m"])
+        .arg(&schema_path)
+        .arg("-i")
+        .arg(&input)
+        .assert()
+        .success();
+
+    let output = temp.path().join("orders.csv");
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["process", "-i"])
+        .arg(&input)
+        .arg("--schema")
+        .arg(&schema_path)
+        .args(["--filter", "qty>=3", "-o"])
+        .arg(&output)
+        .assert()
+        .success();
+    let (headers, rows) = read_csv(&output);
+    assert_eq!(
+        headers.iter().collect::<Vec<_>>(),
+        ["sku", "placed", "qty", "price"]
+    );
+    assert_eq!(rows.len(), 2);
+    assert_eq!(
+        rows[0].iter().collect::<Vec<_>>(),
+        ["00123", "2024-03-01", "3", "12.50"]
+    );
+    assert_eq!(
+        rows[1].iter().collect::<Vec<_>>(),
+        ["04567", "2024-03-02", "10", "0.30"]
+    );
+
+    // Widths alone lay the columns out back to back; `length` is accepted too.
+    let headerless = temp.path().join("codes.txt");
+    std::fs::write(&headerless, "A0012024-01-31\nB0022024-02-29\n").expect("write codes");
+    let widths_schema = temp.path().join("codes-schema.yml");
+    std::fs::write(
+        &widths_schema,
+        "has_headers: false\n\
+         columns:\n\
+         - name: code\n  datatype: String\n  length: 4\n\
+         - name: day\n  datatype: Date\n  width: 10\n",
+    )
+    .expect("write widths schema");
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["process", "-i"])
+        .arg(&headerless)
+        .arg("--schema")
+        .arg(&widths_schema)
+        .args(["--index", "codes.idx"])
+        .assert()
+        .failure()
+        .stderr(contains("--index cannot be used with fixed-width input"));
+    let sorted = temp.path().join("codes.csv");
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["process", "-i"])
+        .arg(&headerless)
+        .arg("--schema")
+        .arg(&widths_schema)
+        .args(["--sort", "day:desc", "-o"])
+        .arg(&sorted)
+        .assert()
+        .success();
+    let (headers, rows) = read_csv(&sorted);
+    assert_eq!(headers.iter().collect::<Vec<_>>(), ["code", "day"]);
+    assert_eq!(rows[0].iter().collect::<Vec<_>>(), ["B002", "2024-02-29"]);
+    assert_eq!(rows[1].iter().collect::<Vec<_>>(), ["A001", "2024-01-31"]);
+}
+
+#[test]
+fn compressed_inputs_and_outputs_stream_and_index() {
+    use std::io::{Read, Write};
+
+    let temp = tempdir().expect("tempdir");
+    let plain = temp.path().join("orders.tsv");
+    let mut text = String::from("id\tregion\tamount\n");
+    for id in 1..=12_000 {
+        text.push_str(&format!("{id}\tr{}\t{}\n", id % 7, id * 3 % 10_007));
+    }
+    std::fs::write(&plain, &text).expect("write tsv");
+
+    // Gzip output keeps the inner `.tsv` delimiter and is block-gzipped.
+    let gzipped = temp.path().join("orders.tsv.gz");
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["process", "-i"])
+        .arg(&plain)
+        .arg("-o")
+        .arg(&gzipped)
+        .assert()
+        .success();
+    let bytes = std::fs::read(&gzipped).expect("read gzip output");
+    assert_eq!(&bytes[..4], [0x1f, 0x8b, 0x08, 0x04]);
+    let mut inflated = String::new();
+    flate2::read::MultiGzDecoder::new(bytes.as_slice())
+        .read_to_string(&mut inflated)
+        .expect("inflate output");
+    assert!(inflated.starts_with("\"id\"\t\"region\"\t\"amount\"\n\"1\"\t\"r1\"\t\"3\"\n"));
+
+    // Compressed input streams into zstd output with the `.csv` delimiter.
+    let zstd_output = temp.path().join("filtered.csv.zst");
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["process", "-i"])
+        .arg(&gzipped)
+        .args(["--filter", "region = r0", "-o"])
+        .arg(&zstd_output)
+        .assert()
+        .success();
+    let decoded = zstd::decode_all(std::fs::File::open(&zstd_output).expect("open zstd"))
+        .expect("decode zstd output");
+    let decoded = String::from_utf8(decoded).expect("utf8 output");
+    assert!(decoded.starts_with("\"id\",\"region\",\"amount\"\n\"7\",\"r0\",\"21\"\n"));
+    assert_eq!(decoded.lines().count(), 1 + 12_000 / 7);
+
+    // Indexes address BGZF sources by uncompressed offset.
+    let index = temp.path().join("orders.idx");
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["index", "-i"])
+        .arg(&gzipped)
+        .arg("-o")
+        .arg(&index)
+        .args(["--columns", "amount"])
+        .assert()
+        .success();
+    let sorted = temp.path().join("sorted.csv");
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["process", "-i"])
+        .arg(&gzipped)
+        .arg("--index")
+        .arg(&index)
+        .args(["--sort", "amount", "--columns", "id", "--limit", "3", "-o"])
+        .arg(&sorted)
+        .assert()
+        .success();
+    let (_, rows) = read_csv(&sorted);
+    let ids = rows
+        .iter()
+        .map(|row| row[0].to_string())
+        .collect::<Vec<_>>();
+    let mut expected = text
+        .lines()
+        .skip(1)
+        .map(|line| line.split('\t').collect::<Vec<_>>())
+        .map(|fields| (fields[2].to_string(), fields[0].to_string()))
+        .collect::<Vec<_>>();
+    expected.sort();
+    assert_eq!(
+        ids,
+        expected[..3]
+            .iter()
+            .map(|(_, id)| id.clone())
+            .collect::<Vec<_>>()
+    );
+
+    // Standard input is recognised by its magic bytes; single-member gzip
+    // streams fine but cannot be indexed.
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder
+        .write_all(b"id,name\n2,beta\n1,alpha\n")
+        .expect("gzip stdin");
+    let stdin_gzip = encoder.finish().expect("finish gzip");
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["process", "-i", "-", "--sort", "id"])
+        .write_stdin(stdin_gzip.clone())
+        .assert()
+        .success()
+        .stdout(contains("\"1\",\"alpha\"\n\"2\",\"beta\""));
+    let single_member = temp.path().join("names.csv.gz");
+    std::fs::write(&single_member, &stdin_gzip).expect("write gzip");
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["index", "-i"])
+        .arg(&single_member)
+        .arg("-o")
+        .arg(temp.path().join("names.idx"))
+        .args(["--columns", "id"])
+        .assert()
+        .failure()
+        .stderr(contains("not a block-gzipped (BGZF) file"));
+}
+
+#[cfg(unix)]
+#[test]
+fn compressed_output_reports_errors_while_finishing() {
+    let temp = tempdir().expect("tempdir");
+    let input = temp.path().join("names.csv");
+    std::fs::write(&input, "id,name\n1,alpha\n2,beta\n").expect("write csv");
+    // Small outputs stay buffered until the stream is closed, so nothing fails
+    // before the final block and trailer are written.
+    for name in ["full.csv.gz", "full.csv.zst"] {
+        let output = temp.path().join(name);
+        std::os::unix::fs::symlink("/dev/full", &output).expect("link /dev/full");
+        Command::cargo_bin("csv-managed")
+            .expect("binary exists")
+            .args(["process", "-i"])
+            .arg(&input)
+            .arg("-o")
+            .arg(&output)
+            .assert()
+            .failure()
+            .stderr(contains("No space left on device"));
+    }
+}
+
+#[test]
+fn dialect_is_detected_into_schema_and_overridable_per_run() {
+    let temp = tempdir().expect("tempdir");
+    let input = temp.path().join("export.csv");
+    std::fs::write(
+        &input,
+        "# exported 2024-03-01\r\nid,name,city\r\n1,\"Smith, J\",Leeds\r\n2,Lee,York\r\n",
+    )
+    .expect("write csv");
+    let schema_path = temp.path().join("export-schema.yml");
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["schema", "infer", "-i"])
+        .arg(&input)
+        .arg("-o")
+        .arg(&schema_path)
+        .assert()
+        .success();
+    let schema = Schema::load(&schema_path).expect("load inferred schema");
+    assert_eq!(schema.headers(), vec!["id", "name", "city"]);
+    let yaml = std::fs::read_to_string(&schema_path).expect("read schema");
+    assert!(yaml.contains("quote_style: necessary"), "{yaml}");
+    assert!(yaml.contains("terminator: crlf"), "{yaml}");
+    assert!(yaml.contains("comment: '#'"), "{yaml}");
+
+    // The schema's dialect drives both reading and writing.
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["process", "-i"])
+        .arg(&input)
+        .arg("-m")
+        .arg(&schema_path)
+        .assert()
+        .success()
+        .stdout("id,name,city\r\n1,\"Smith, J\",Leeds\r\n2,Lee,York\r\n");
+
+    // Flags override the schema for one run.
+    let output = Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["process", "-i"])
+        .arg(&input)
+        .arg("-m")
+        .arg(&schema_path)
+        .args([
+            "--quote-style",
+            "non-numeric",
+            "--line-terminator",
+            "lf",
+            "--bom",
+        ])
+        .output()
+        .expect("run process");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).expect("utf8 output"),
+        "\u{FEFF}\"id\",\"name\",\"city\"\n1,\"Smith, J\",\"Leeds\"\n2,\"Lee\",\"York\"\n"
+    );
+
+    // Without a schema, quoting can be switched off and padding trimmed.
+    let padded = temp.path().join("padded.csv");
+    std::fs::write(&padded, "id, note\n1, say \"hi\"\n").expect("write padded csv");
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["process", "-i"])
+        .arg(&padded)
+        .args(["--no-quoting", "--trim", "all", "--quote-style", "never"])
+        .assert()
+        .success()
+        .stdout("id,note\n1,say \"hi\"\n");
+}
+
+#[test]
+fn malformed_rows_are_padded_skipped_or_quarantined_on_request() {
+    let temp = tempdir().expect("tempdir");
+    let input = temp.path().join("ragged.csv");
+    std::fs::write(
+        &input,
+        "id,name,city\n1,Ann,Leeds\n2,Bob\n3,\"Cy, \"\"Jr\"\"\",York,extra\n4,Di,Hull\n",
+    )
+    .expect("write csv");
+
+    // Ragged rows still stop the run by default.
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["process", "-i"])
+        .arg(&input)
+        .assert()
+        .failure();
+
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["process", "-i"])
+        .arg(&input)
+        .args(["--on-malformed", "pad"])
+        .assert()
+        .success()
+        .stdout(
+            "\"id\",\"name\",\"city\"\n\"1\",\"Ann\",\"Leeds\"\n\"2\",\"Bob\",\"\"\n\"3\",\"Cy, \"\"Jr\"\"\",\"York\"\n\"4\",\"Di\",\"Hull\"\n",
+        )
+        .stderr(contains("Malformed rows: 1 padded, 1 truncated"));
+
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["process", "-i"])
+        .arg(&input)
+        .args(["--on-malformed", "skip"])
+        .assert()
+        .success()
+        .stdout("\"id\",\"name\",\"city\"\n\"1\",\"Ann\",\"Leeds\"\n\"4\",\"Di\",\"Hull\"\n")
+        .stderr(contains("Malformed rows: 2 skipped"));
+
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["process", "-i"])
+        .arg(&input)
+        .args(["--on-malformed", "quarantine"])
+        .assert()
+        .failure()
+        .stderr(contains("requires --reject-file"));
+
+    let rejects = temp.path().join("rejects.csv");
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["process", "-i"])
+        .arg(&input)
+        .args(["--on-malformed", "quarantine", "--reject-file"])
+        .arg(&rejects)
+        .assert()
+        .success()
+        .stdout("\"id\",\"name\",\"city\"\n\"1\",\"Ann\",\"Leeds\"\n\"4\",\"Di\",\"Hull\"\n");
+    // Each rejected row keeps its raw fields as separate columns.
+    let rows = ReaderBuilder::new()
+        .flexible(true)
+        .from_path(&rejects)
+        .expect("open rejects")
+        .records()
+        .map(|record| record.expect("read reject"))
+        .collect::<Vec<_>>();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(
+        &rows[0].iter().skip(1).collect::<Vec<_>>(),
+        &["3", "expected 3 fields, found 2", "2", "Bob"]
+    );
+    assert_eq!(
+        &rows[1].iter().skip(1).collect::<Vec<_>>(),
+        &[
+            "4",
+            "expected 3 fields, found 4",
+            "3",
+            "Cy, \"Jr\"",
+            "York",
+            "extra"
+        ]
+    );
+
+    // Inference stays strict unless the command was given a policy.
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["schema", "infer", "-i"])
+        .arg(&input)
+        .assert()
+        .failure()
+        .stderr(contains("found record with 2 fields"));
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["stats", "-i"])
+        .arg(&input)
+        .args(["-C", "id", "--on-malformed", "skip"])
+        .assert()
+        .success()
+        .stderr(contains("Malformed rows: 2 skipped"));
+}
+
+#[test]
+fn type_errors_are_nulled_or_quarantined_on_request() {
+    let temp = tempdir().expect("tempdir");
+    let input = temp.path().join("amounts.csv");
+    std::fs::write(&input, "id,amount\n1,2.5\n2,abc\nx,4\n4,1.25\n").expect("write csv");
+    let schema_path = temp.path().join("amounts-schema.yml");
+    std::fs::write(
+        &schema_path,
+        "columns:\n- name: id\n  datatype: Integer\n- name: amount\n  datatype: Float\n",
+    )
+    .expect("write schema");
+    let output = temp.path().join("out.csv");
+
+    Command::cargo_bin("csv-managed")
+        .expect("binary exists")
+        .args(["process", "-i"])
+        .arg(&input)
+        .arg("-m")
+        .arg(&schema_path)
+        .assert()
+        .failure()
+        .stderr(contains("Failed to parse 'abc' as float"));
+
+    let rows = run_process_rows(
+        &[
+            "-i",
+            input.to_str().unwrap(),
+            "-m",
+            schema_path.to_str().unwrap(),
+            "--on-type-error",
+            "null",
+        ],
+        &output,
+    );
+    assert_eq!(column_values(&rows, 0), ["1", "2", "", "4"]);
+    assert_eq!(column_values(&rows, 1), ["2.5", "", "4", "1.25"]);
+
+    // Row numbers are lines within each file, after rows skipped as malformed.
+    let second = temp.path().join("more.csv");
+    std::fs::write(&second, "id,amount\n5\n6,bad\n7,3\n").expect("write csv");
+    let rejects = temp.path().join("rejects.csv");
+    let rows = run_process_rows(
+        &[
+            "-i",
+            input.to_str().unwrap(),
+            "-i",
+            second.to_str().unwrap(),
+            "--on-malformed",
+            "skip",
+            "-m",
+            schema_path.to_str().unwrap(),
+            "--on-type-error",
+            "quarantine",
+            "--type-reject-file",
+            rejects.to_str().unwrap(),
+            "--threads",
+            "2",
+        ],
+        &output,
+    );
+    assert_eq!(column_values(&rows, 0), ["1", "4", "7"]);
+    let (headers, rejected) = read_csv(&rejects);
+    assert_eq!(
+        headers.iter().collect::<Vec<_>>(),
+        [
+            "id",
+            "amount",
+            "source",
+            "row_number",
+            "column_name",
+            "raw_value",
+            "reason"
+        ]
+    );
+    assert_eq!(rejected.len(), 3);
+    let first = input.display().to_string();
+    let more = second.display().to_string();
+    assert_eq!(
+        rejected[0].iter().take(6).collect::<Vec<_>>(),
+        ["2", "abc", first.as_str(), "3", "amount", "abc"]
+    );
+    assert!(rejected[0][6].contains("float"), "{:?}", rejected[0]);
+    assert_eq!(
+        rejected[1].iter().take(6).collect::<Vec<_>>(),
+        ["x", "4", first.as_str(), "4", "id", "x"]
+    );
+    assert_eq!(
+        rejected[2].iter().take(6).collect::<Vec<_>>(),
+        ["6", "bad", more.as_str(), "3", "amount", "bad"]
+    );
+
+    // Headerless input counts from the first line.
+    let headerless_schema = temp.path().join("headerless-schema.yml");
+    std::fs::write(
+        &headerless_schema,
+        "has_headers: false\ncolumns:\n- name: id\n  datatype: Integer\n- name: amount\n  datatype: Float\n",
+    )
+    .expect("write schema");
+    let headerless = temp.path().join("headerless.csv");
+    std::fs::write(&headerless, "1,2.5\n2,abc\n").expect("write csv");
+    run_process_rows(
+        &[
+            "-i",
+            headerless.to_str().unwrap(),
+            "-m",
+            headerless_schema.to_str().unwrap(),
+            "--on-type-error",
+            "quarantine",
+            "--type-reject-file",
+            rejects.to_str().unwrap(),
+        ],
+        &output,
+    );
+    let (_, rejected) = read_csv(&rejects);
+    assert_eq!(&rejected[0][3], "2");
+}